@@ -37,10 +37,13 @@ use wasmer_vm::{
 ///   with native functions. Attempting to create a native `Function` with one will
 ///   result in a panic.
 ///   [Closures as host functions tracking issue](https://github.com/wasmerio/wasmer/issues/1840)
-#[derive(PartialEq, MemoryUsage)]
+#[derive(MemoryUsage)]
 pub struct Function {
     pub(crate) store: Store,
     pub(crate) exported: ExportFunction,
+    /// The name this function was exported as by its instance, if any, for
+    /// the traps of its calls.
+    pub(crate) export_name: Option<String>,
 }
 
 impl wasmer_types::WasmValueType for Function {
@@ -243,6 +246,7 @@ impl Function {
                     instance_ref: None,
                 },
             },
+            export_name: None,
         }
     }
 
@@ -295,6 +299,7 @@ impl Function {
                     instance_ref: None,
                 },
             },
+            export_name: None,
         }
     }
 
@@ -353,6 +358,7 @@ impl Function {
                     instance_ref: None,
                 },
             },
+            export_name: None,
         }
     }
 
@@ -438,6 +444,10 @@ impl Function {
                 values_vec.as_mut_ptr() as *mut u8,
             )
         } {
+            let error = match &self.export_name {
+                Some(name) => error.with_invoked_export(name.as_str()),
+                None => error,
+            };
             return Err(RuntimeError::from_trap(error));
         }
 
@@ -548,9 +558,17 @@ impl Function {
         Self {
             store: store.clone(),
             exported: wasmer_export,
+            export_name: None,
         }
     }
 
+    /// Returns this function, attaching `name` to the traps of its calls
+    /// as the export they were invoked through.
+    pub(crate) fn with_export_name(mut self, name: &str) -> Self {
+        self.export_name = Some(name.to_string());
+        self
+    }
+
     pub(crate) fn vm_funcref(&self) -> VMFuncRef {
         let engine = self.store.engine();
         let vmsignature = engine.register_signature(&self.exported.vm_function.signature);
@@ -711,6 +729,14 @@ impl<'a> Exportable<'a> for Function {
     }
 }
 
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        // The same function exported under different names, or not exported
+        // at all, is still the same function.
+        self.store == other.store && self.exported == other.exported
+    }
+}
+
 impl Clone for Function {
     fn clone(&self) -> Self {
         let mut exported = self.exported.clone();
@@ -719,6 +745,7 @@ impl Clone for Function {
         Self {
             store: self.store.clone(),
             exported,
+            export_name: self.export_name.clone(),
         }
     }
 }
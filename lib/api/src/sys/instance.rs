@@ -129,7 +129,12 @@ impl Instance {
             .map(|export| {
                 let name = export.name().to_string();
                 let export = handle.lookup(&name).expect("export");
-                let extern_ = Extern::from_vm_export(store, export.into());
+                let extern_ = match Extern::from_vm_export(store, export.into()) {
+                    Extern::Function(function) => {
+                        Extern::Function(function.with_export_name(&name))
+                    }
+                    extern_ => extern_,
+                };
                 (name, extern_)
             })
             .collect::<Exports>();
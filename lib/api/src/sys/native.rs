@@ -119,6 +119,7 @@ where
         Self {
             store: other.store,
             exported: other.exported,
+            export_name: None,
         }
     }
 }
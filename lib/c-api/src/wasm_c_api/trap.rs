@@ -113,9 +113,15 @@ pub unsafe extern "C" fn wasm_trap_message(
     // own
     out: &mut wasm_byte_vec_t,
 ) {
-    let message = trap.inner.message();
-    let mut byte_vec = message.into_bytes();
-    byte_vec.push(0);
+    // Traps with a trap code use the same text as the reference C API.
+    let byte_vec = match trap.inner.clone().to_trap() {
+        Some(trap_code) => trap_code.c_api_message(),
+        None => {
+            let mut byte_vec = trap.inner.message().into_bytes();
+            byte_vec.push(0);
+            byte_vec
+        }
+    };
 
     out.set_buffer(byte_vec);
 }
@@ -61,6 +61,11 @@ pub struct Run {
     #[structopt(long = "enable-io-devices")]
     enable_experimental_io_devices: bool,
 
+    /// Exit with the code associated to the trap code (starting at 100)
+    /// if the module traps
+    #[structopt(long = "trap-as-exit-code")]
+    trap_as_exit_code: bool,
+
     /// Enable debug output
     #[cfg(feature = "debug")]
     #[structopt(long = "debug", short = "d")]
@@ -82,7 +87,7 @@ impl Run {
         if self.debug {
             logging::set_up_logging(self.verbose).unwrap();
         }
-        self.inner_execute().with_context(|| {
+        let result = self.inner_execute().with_context(|| {
             format!(
                 "failed to run `{}`{}",
                 self.path.display(),
@@ -92,7 +97,23 @@ impl Run {
                     ""
                 }
             )
-        })
+        });
+        if self.trap_as_exit_code {
+            let exit_code = result.as_ref().err().and_then(Self::trap_exit_code);
+            if let Some(exit_code) = exit_code {
+                crate::error::PrettyError::report_with_exit_code(result, exit_code);
+            }
+        }
+        result
+    }
+
+    /// Get the exit code associated to the trap that caused `error`, if any.
+    fn trap_exit_code(error: &anyhow::Error) -> Option<i32> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<RuntimeError>())
+            .and_then(|runtime_error| runtime_error.clone().to_trap())
+            .map(|trap_code| trap_code.exit_code())
     }
 
     fn inner_execute(&self) -> Result<()> {
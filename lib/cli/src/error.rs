@@ -22,11 +22,17 @@ impl PrettyError {
     /// Process a `Result` printing any errors and exiting
     /// the process after
     pub fn report<T>(result: Result<T, Error>) -> ! {
+        Self::report_with_exit_code(result, 1)
+    }
+
+    /// Process a `Result` printing any errors and exiting
+    /// the process after, with `exit_code` if there was an error
+    pub fn report_with_exit_code<T>(result: Result<T, Error>, exit_code: i32) -> ! {
         std::process::exit(match result {
             Ok(_t) => 0,
             Err(error) => {
                 eprintln!("{:?}", PrettyError { error });
-                1
+                exit_code
             }
         });
     }
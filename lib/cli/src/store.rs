@@ -67,6 +67,21 @@ pub struct CompilerOptions {
     #[structopt(long)]
     enable_verifier: bool,
 
+    /// Emit position-independent code, as the dylib and staticlib engines
+    /// always do.
+    #[structopt(long)]
+    enable_pic: bool,
+
+    /// Cranelift optimization level: `none`, `speed` or `speed_and_size`.
+    #[cfg(feature = "cranelift")]
+    #[structopt(long, parse(try_from_str = parse_cranelift_opt_level))]
+    cranelift_opt_level: Option<wasmer_compiler_cranelift::CraneliftOptLevel>,
+
+    /// LLVM optimization level, from 0 (none) to 3 (aggressive).
+    #[cfg(feature = "llvm")]
+    #[structopt(long, parse(try_from_str = parse_llvm_opt_level))]
+    llvm_opt_level: Option<wasmer_compiler_llvm::LLVMOptLevel>,
+
     /// LLVM debug directory, where IR and object files will be written to.
     #[cfg(feature = "llvm")]
     #[structopt(long, parse(from_os_str))]
@@ -186,6 +201,9 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.enable_pic {
+                    config.enable_pic();
+                }
                 Box::new(config)
             }
             #[cfg(feature = "cranelift")]
@@ -194,6 +212,12 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.enable_pic {
+                    config.enable_pic();
+                }
+                if let Some(ref opt_level) = self.cranelift_opt_level {
+                    config.opt_level(opt_level.clone());
+                }
                 Box::new(config)
             }
             #[cfg(feature = "llvm")]
@@ -297,6 +321,12 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.enable_pic {
+                    config.enable_pic();
+                }
+                if let Some(opt_level) = self.llvm_opt_level {
+                    config.opt_level(opt_level);
+                }
                 Box::new(config)
             }
             #[cfg(not(all(feature = "singlepass", feature = "cranelift", feature = "llvm",)))]
@@ -313,6 +343,35 @@ impl CompilerOptions {
     }
 }
 
+#[cfg(feature = "cranelift")]
+fn parse_cranelift_opt_level(s: &str) -> Result<wasmer_compiler_cranelift::CraneliftOptLevel> {
+    use wasmer_compiler_cranelift::CraneliftOptLevel;
+    match s {
+        "none" => Ok(CraneliftOptLevel::None),
+        "speed" => Ok(CraneliftOptLevel::Speed),
+        "speed_and_size" => Ok(CraneliftOptLevel::SpeedAndSize),
+        _ => bail!(
+            "Unknown Cranelift optimization level `{}`, expected `none`, `speed` or `speed_and_size`",
+            s
+        ),
+    }
+}
+
+#[cfg(feature = "llvm")]
+fn parse_llvm_opt_level(s: &str) -> Result<wasmer_compiler_llvm::LLVMOptLevel> {
+    use wasmer_compiler_llvm::LLVMOptLevel;
+    match s {
+        "0" => Ok(LLVMOptLevel::None),
+        "1" => Ok(LLVMOptLevel::Less),
+        "2" => Ok(LLVMOptLevel::Default),
+        "3" => Ok(LLVMOptLevel::Aggressive),
+        _ => bail!(
+            "Unknown LLVM optimization level `{}`, expected a level from 0 to 3",
+            s
+        ),
+    }
+}
+
 /// The compiler used for the store
 #[derive(Debug, PartialEq, Eq)]
 pub enum CompilerType {
@@ -2,6 +2,8 @@
 
 use crate::address_map::get_function_address_map;
 use crate::config::Cranelift;
+#[cfg(all(feature = "unwind", debug_assertions))]
+use crate::dwarf::frame_layouts;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
 use crate::func_environ::{get_function_name, FuncEnvironment};
@@ -16,6 +18,8 @@ use cranelift_codegen::ir::ExternalName;
 use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::{ir, MachReloc};
 use cranelift_codegen::{Context, MachTrap};
+#[cfg(all(feature = "unwind", debug_assertions))]
+use gimli::write::Writer;
 #[cfg(feature = "unwind")]
 use gimli::write::{Address, EhFrame, FrameTable};
 use loupe::MemoryUsage;
@@ -220,7 +224,17 @@ impl Compiler for CraneliftCompiler {
             let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
             dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
 
+            #[cfg(debug_assertions)]
+            let endian = eh_frame.0.endian();
             let eh_frame_section = eh_frame.0.into_section();
+            // Every defined function must have a frame layout, decoded from
+            // its unwind information.
+            #[cfg(debug_assertions)]
+            frame_layouts(&eh_frame_section, endian)?
+                .assert_complete(module.functions.len() - module.num_imported_functions)
+                .map_err(|error| {
+                    CompileError::Codegen(format!("incomplete frame layouts: {}", error))
+                })?;
             custom_sections.push(eh_frame_section);
             Some(Dwarf::new(SectionIndex::new(custom_sections.len() - 1)))
         } else {
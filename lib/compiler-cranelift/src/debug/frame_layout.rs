@@ -0,0 +1,1782 @@
+//! Frame layout information of the compiled functions.
+//!
+//! A frame layout describes how the call frame of a function evolves over
+//! its body: where the call frame address (CFA) is located and where the
+//! callee-saved registers are spilled, at every code offset.
+
+use crate::HashMap;
+use core::convert::TryInto;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::mem;
+use std::ops::Range;
+use std::string::String;
+use std::vec::Vec;
+use wasmer_types::LocalFunctionIndex;
+
+/// A register number, using the DWARF numbering of the target architecture.
+pub type FrameRegister = u16;
+
+/// The DWARF numbers of the AArch64 frame pointer, link register and stack
+/// pointer.
+const AARCH64_FP: FrameRegister = 29;
+const AARCH64_LR: FrameRegister = 30;
+const AARCH64_SP: FrameRegister = 31;
+
+/// The compact unwind encoding of AArch64 frame-pointer frames.
+const UNWIND_ARM64_MODE_FRAME: u32 = 0x0400_0000;
+
+/// The register pairs a frame-pointer frame may save in the compact unwind
+/// encoding, in their save order, with their flag. `d8` is DWARF number 72.
+const UNWIND_ARM64_FRAME_PAIRS: &[(FrameRegister, FrameRegister, u32)] = &[
+    (19, 20, 0x001),
+    (21, 22, 0x002),
+    (23, 24, 0x004),
+    (25, 26, 0x008),
+    (27, 28, 0x010),
+    (72, 73, 0x100),
+    (74, 75, 0x200),
+    (76, 77, 0x400),
+    (78, 79, 0x800),
+];
+
+/// A change in the frame layout, taking effect at a given code offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameLayoutChange {
+    /// The call frame address is now at `reg + offset`.
+    CallFrameAddressAt {
+        /// The register the CFA is computed from.
+        reg: FrameRegister,
+        /// The offset added to `reg`.
+        offset: i32,
+    },
+    /// The register `reg` has been saved at `CFA + cfa_offset`.
+    RegAt {
+        /// The saved register.
+        reg: FrameRegister,
+        /// The offset of the save slot, relative to the CFA.
+        cfa_offset: i32,
+    },
+    /// The return address is now at `CFA + cfa_offset`.
+    ///
+    /// Without such a change, the return address is right below the CFA.
+    ReturnAddressAt {
+        /// The offset of the return address, relative to the CFA.
+        cfa_offset: i32,
+    },
+}
+
+impl fmt::Display for FrameLayoutChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::CallFrameAddressAt { reg, offset } => {
+                write!(f, "CFA = r{}{}", reg, SignedOffset(offset))
+            }
+            Self::RegAt { reg, cfa_offset } => {
+                write!(f, "r{} at CFA{}", reg, SignedOffset(cfa_offset))
+            }
+            Self::ReturnAddressAt { cfa_offset } => {
+                write!(f, "RA at CFA{}", SignedOffset(cfa_offset))
+            }
+        }
+    }
+}
+
+/// Formats an offset as ` + offset` or ` - offset`.
+struct SignedOffset(i32);
+
+impl fmt::Display for SignedOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 0 {
+            write!(f, " - {}", self.0.unsigned_abs())
+        } else {
+            write!(f, " + {}", self.0)
+        }
+    }
+}
+
+/// The frame layout of a single function.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FrameLayout {
+    /// The `(code_offset, change)` pairs, sorted by code offset.
+    changes: Vec<(u32, FrameLayoutChange)>,
+}
+
+/// The id of a [`FrameLayout`] interned in a [`FrameLayoutInterner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrameLayoutId(u32);
+
+/// A pool of frame layouts, deduplicating structurally-equal ones.
+///
+/// Many small functions (trampolines, thunks) share the same layout, which
+/// is then only stored once.
+#[derive(Debug, Clone, Default)]
+pub struct FrameLayoutInterner {
+    layouts: Vec<FrameLayout>,
+    ids: HashMap<FrameLayout, FrameLayoutId>,
+}
+
+/// The frame layouts of the functions of a module.
+///
+/// Identical layouts are only stored once, see [`FrameLayoutInterner`].
+/// Two `FrameLayouts` are equal when their functions have the same layouts
+/// and code ranges, whatever the order their layouts were interned in.
+#[derive(Debug, Clone, Default)]
+pub struct FrameLayouts {
+    /// The unique layouts, indexed by `FrameLayoutId`.
+    pool: Vec<FrameLayout>,
+    layouts: BTreeMap<LocalFunctionIndex, FrameLayoutId>,
+    code_ranges: BTreeMap<LocalFunctionIndex, Range<usize>>,
+    truncated: bool,
+}
+
+/// An inconsistency of [`FrameLayouts`], as reported by
+/// [`FrameLayouts::assert_complete`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameLayoutError {
+    /// A defined function has no frame layout.
+    Missing(LocalFunctionIndex),
+    /// A frame layout was recorded for a function that isn't defined.
+    Extra(LocalFunctionIndex),
+}
+
+impl fmt::Display for FrameLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(index) => {
+                write!(f, "missing frame layout for function {}", index.as_u32())
+            }
+            Self::Extra(index) => write!(
+                f,
+                "frame layout recorded for undefined function {}",
+                index.as_u32()
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameLayoutError {}
+
+/// The name of the object file section holding the frame layouts of a
+/// module, as encoded by [`FrameLayouts::to_section_bytes`].
+pub const FRAME_LAYOUTS_SECTION_NAME: &str = ".wasmer_frames";
+
+/// The version of the section format written by
+/// [`FrameLayouts::to_section_bytes`].
+const SECTION_VERSION: u8 = 1;
+
+const TAG_CALL_FRAME_ADDRESS_AT: u8 = 0;
+const TAG_REG_AT: u8 = 1;
+const TAG_RETURN_ADDRESS_AT: u8 = 2;
+
+/// An error decoding a [`FRAME_LAYOUTS_SECTION_NAME`] section, as reported
+/// by [`FrameLayouts::from_section_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameSectionError {
+    /// The section was encoded with an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The section ended before the frame layouts were fully decoded.
+    UnexpectedEnd,
+    /// A change tag doesn't match any [`FrameLayoutChange`].
+    InvalidChangeTag(u8),
+    /// There are bytes left after the frame layouts.
+    TrailingBytes,
+}
+
+impl fmt::Display for FrameSectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported frame layouts section version {}", version)
+            }
+            Self::UnexpectedEnd => write!(f, "unexpected end of the frame layouts section"),
+            Self::InvalidChangeTag(tag) => write!(f, "invalid frame layout change tag {}", tag),
+            Self::TrailingBytes => write!(f, "trailing bytes after the frame layouts"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameSectionError {}
+
+/// A problem found by [`FrameLayouts::from_section_bytes_lenient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSectionWarning {
+    /// The offset in the section of the header, function or trailing bytes
+    /// that couldn't be decoded.
+    pub offset: usize,
+    /// Why the decoding stopped.
+    pub error: FrameSectionError,
+}
+
+/// A summary of the size of [`FrameLayouts`], as returned by
+/// [`FrameLayouts::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameLayoutStats {
+    /// The number of functions with a frame layout.
+    pub functions: usize,
+    /// The number of changes of the frame layouts of all the functions,
+    /// counting the layouts shared by several functions once per function.
+    pub total_changes: usize,
+    /// The size of the frame layouts section, see
+    /// [`FrameLayouts::to_section_bytes`].
+    pub serialized_bytes: usize,
+}
+
+/// A builder for [`FrameLayouts`], bounding the amount of layouts recorded.
+///
+/// Once a limit is exceeded, no further layout is recorded and the
+/// resulting [`FrameLayouts`] are marked as truncated.
+#[derive(Debug, Clone, Default)]
+pub struct FrameLayoutsBuilder {
+    layouts: FrameLayouts,
+    interner: FrameLayoutInterner,
+    max_functions: Option<usize>,
+    max_bytes: Option<usize>,
+    bytes: usize,
+}
+
+/// A difference between two [`FrameLayout`]s, as reported by [`FrameLayout::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameLayoutDiff {
+    /// A change only present in the new layout.
+    Added {
+        /// The code offset of the change.
+        offset: u32,
+        /// The added change.
+        change: FrameLayoutChange,
+    },
+    /// A change only present in the old layout.
+    Removed {
+        /// The code offset of the change.
+        offset: u32,
+        /// The removed change.
+        change: FrameLayoutChange,
+    },
+    /// A change present in both layouts at the same offset, but with different contents.
+    Changed {
+        /// The code offset of the change.
+        offset: u32,
+        /// The change in the old layout.
+        old: FrameLayoutChange,
+        /// The change in the new layout.
+        new: FrameLayoutChange,
+    },
+}
+
+/// The role of a register in a [`FrameLayout`], as found by
+/// [`FrameLayout::canonical_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanonicalRegister {
+    /// The register the first CFA rule is computed from.
+    StackPointer,
+    /// The first other register the CFA gets computed from.
+    FramePointer,
+    /// Another register, numbered by order of appearance in the layout.
+    CalleeSaved(usize),
+}
+
+/// A [`FrameLayoutChange`] with its register replaced by its role, as
+/// returned by [`FrameLayout::canonical_shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanonicalChange {
+    /// The call frame address is now at `reg + offset`.
+    CallFrameAddressAt {
+        /// The role of the register the CFA is computed from.
+        reg: CanonicalRegister,
+        /// The offset added to `reg`.
+        offset: i32,
+    },
+    /// The register `reg` has been saved at `CFA + cfa_offset`.
+    RegAt {
+        /// The role of the saved register.
+        reg: CanonicalRegister,
+        /// The offset of the save slot, relative to the CFA.
+        cfa_offset: i32,
+    },
+    /// The return address is now at `CFA + cfa_offset`.
+    ReturnAddressAt {
+        /// The offset of the return address, relative to the CFA.
+        cfa_offset: i32,
+    },
+}
+
+impl FrameLayout {
+    /// Creates a new frame layout from `(code_offset, change)` pairs.
+    ///
+    /// The changes are sorted by code offset; changes at the same offset
+    /// keep their relative order.
+    pub fn new(mut changes: Vec<(u32, FrameLayoutChange)>) -> Self {
+        changes.sort_by_key(|&(offset, _)| offset);
+        Self { changes }
+    }
+
+    /// Returns the `(code_offset, change)` pairs, sorted by code offset.
+    pub fn changes(&self) -> &[(u32, FrameLayoutChange)] {
+        &self.changes
+    }
+
+    /// Returns the call frame address rule, as a `(reg, offset)` pair, in
+    /// effect at `code_offset`.
+    pub fn cfa_at(&self, code_offset: u32) -> Option<(FrameRegister, i32)> {
+        self.changes_until(code_offset)
+            .filter_map(|change| match *change {
+                FrameLayoutChange::CallFrameAddressAt { reg, offset } => Some((reg, offset)),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Returns the offset, relative to the CFA, where `reg` is saved at
+    /// `code_offset`, if it is saved.
+    pub fn saved_reg_at(&self, code_offset: u32, reg: FrameRegister) -> Option<i32> {
+        self.changes_until(code_offset)
+            .filter_map(|change| match *change {
+                FrameLayoutChange::RegAt {
+                    reg: saved_reg,
+                    cfa_offset,
+                } if saved_reg == reg => Some(cfa_offset),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Returns the offset, relative to the CFA, where the return address is
+    /// at `code_offset`, if a change records it.
+    pub fn return_address_at(&self, code_offset: u32) -> Option<i32> {
+        self.changes_until(code_offset)
+            .filter_map(|change| match *change {
+                FrameLayoutChange::ReturnAddressAt { cfa_offset } => Some(cfa_offset),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Removes the redundant changes, keeping the same CFA rule, return
+    /// address and saved registers at every code offset.
+    ///
+    /// A change is redundant if a later change at the same offset overrides
+    /// it, or if it restates the rule already in effect.
+    pub fn compact(&mut self) {
+        let mut compacted: Vec<(u32, FrameLayoutChange)> = Vec::with_capacity(self.changes.len());
+        let mut in_effect: HashMap<ChangeTarget, FrameLayoutChange> = HashMap::new();
+        let mut rest = &self.changes[..];
+        while let Some(&(offset, _)) = rest.first() {
+            let (here, next) = split_at_offset(rest, offset);
+            for (i, &(_, change)) in here.iter().enumerate() {
+                let target = ChangeTarget::of(change);
+                let overridden = here[i + 1..]
+                    .iter()
+                    .any(|&(_, later)| ChangeTarget::of(later) == target);
+                if !overridden && in_effect.get(&target) != Some(&change) {
+                    in_effect.insert(target, change);
+                    compacted.push((offset, change));
+                }
+            }
+            rest = next;
+        }
+        self.changes = compacted;
+    }
+
+    /// Returns the changes in order, without their code offsets and with
+    /// their registers replaced by their roles, to compare the layouts of
+    /// the same function compiled for different architectures.
+    ///
+    /// The roles are inferred from the layout alone: a link register saved
+    /// like any other register, as on AArch64, is a callee-saved one.
+    pub fn canonical_shape(&self) -> Vec<CanonicalChange> {
+        let mut cfa_regs = self.changes.iter().filter_map(|(_, change)| match *change {
+            FrameLayoutChange::CallFrameAddressAt { reg, .. } => Some(reg),
+            _ => None,
+        });
+        let stack_pointer = cfa_regs.next();
+        let frame_pointer = cfa_regs.find(|&reg| Some(reg) != stack_pointer);
+        let mut callee_saved: Vec<FrameRegister> = Vec::new();
+        let mut role = |reg| {
+            if Some(reg) == stack_pointer {
+                CanonicalRegister::StackPointer
+            } else if Some(reg) == frame_pointer {
+                CanonicalRegister::FramePointer
+            } else {
+                let n = match callee_saved.iter().position(|&saved| saved == reg) {
+                    Some(n) => n,
+                    None => {
+                        callee_saved.push(reg);
+                        callee_saved.len() - 1
+                    }
+                };
+                CanonicalRegister::CalleeSaved(n)
+            }
+        };
+        self.changes
+            .iter()
+            .map(|&(_, change)| match change {
+                FrameLayoutChange::CallFrameAddressAt { reg, offset } => {
+                    CanonicalChange::CallFrameAddressAt {
+                        reg: role(reg),
+                        offset,
+                    }
+                }
+                FrameLayoutChange::RegAt { reg, cfa_offset } => CanonicalChange::RegAt {
+                    reg: role(reg),
+                    cfa_offset,
+                },
+                FrameLayoutChange::ReturnAddressAt { cfa_offset } => {
+                    CanonicalChange::ReturnAddressAt { cfa_offset }
+                }
+            })
+            .collect()
+    }
+
+    /// Encodes this layout, of an AArch64 function, as an Apple compact
+    /// unwind encoding, for Mach-O `__unwind_info` sections.
+    ///
+    /// Only frame-pointer frames fit: the CFA is at `x29 + 16` once the
+    /// prologue is done, with `x29` and `x30` saved right below it, and the
+    /// callee-saved registers are saved in pairs right below them, in the
+    /// order of the encoding (`x19`/`x20`, ..., `x27`/`x28`, `d8`/`d9`, ...,
+    /// `d14`/`d15`). Returns `None` for any other shape, which then needs
+    /// DWARF CFI.
+    pub fn to_compact_unwind(&self) -> Option<u32> {
+        let mut saved: BTreeMap<FrameRegister, i32> = BTreeMap::new();
+        let mut has_frame_pointer = false;
+        for &(_, change) in &self.changes {
+            match change {
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: AARCH64_SP, ..
+                } => {}
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: AARCH64_FP,
+                    offset: 16,
+                } => has_frame_pointer = true,
+                FrameLayoutChange::CallFrameAddressAt { .. } => return None,
+                FrameLayoutChange::RegAt { reg, cfa_offset } => {
+                    if *saved.entry(reg).or_insert(cfa_offset) != cfa_offset {
+                        return None;
+                    }
+                }
+                FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 } => {}
+                FrameLayoutChange::ReturnAddressAt { .. } => return None,
+            }
+        }
+        if !has_frame_pointer
+            || saved.remove(&AARCH64_FP) != Some(-16)
+            || saved
+                .remove(&AARCH64_LR)
+                .map_or(false, |offset| offset != -8)
+        {
+            return None;
+        }
+
+        let mut encoding = UNWIND_ARM64_MODE_FRAME;
+        let mut slot = -24;
+        for &(first, second, flag) in UNWIND_ARM64_FRAME_PAIRS {
+            match (saved.remove(&first), saved.remove(&second)) {
+                (None, None) => {}
+                (Some(first), Some(second)) if first == slot && second == slot - 8 => {
+                    encoding |= flag;
+                    slot -= 16;
+                }
+                _ => return None,
+            }
+        }
+        if saved.is_empty() {
+            Some(encoding)
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the changes in effect at `code_offset`, in order.
+    fn changes_until(&self, code_offset: u32) -> impl Iterator<Item = &FrameLayoutChange> {
+        self.changes
+            .iter()
+            .take_while(move |&&(offset, _)| offset <= code_offset)
+            .map(|(_, change)| change)
+    }
+
+    /// Dumps the changes in an `objdump`-like format, one `offset: change`
+    /// line per change, sorted by code offset.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        for (offset, change) in &self.changes {
+            writeln!(dump, "{:8x}: {}", offset, change).unwrap();
+        }
+        dump
+    }
+
+    /// Returns the approximate amount of memory used by this layout, in bytes.
+    fn byte_size(&self) -> usize {
+        mem::size_of::<Self>() + self.changes.len() * mem::size_of::<(u32, FrameLayoutChange)>()
+    }
+
+    /// Compares this layout against `other`, reporting the changes that
+    /// were added, removed or modified in `other`, grouped by code offset.
+    ///
+    /// Changes at the same offset are compared as a multiset, so their
+    /// order doesn't matter: the changes left on both sides are paired up
+    /// as `Changed` when they set the same register or rule, and are
+    /// otherwise `Removed` or `Added`.
+    pub fn diff(&self, other: &Self) -> Vec<FrameLayoutDiff> {
+        let mut diffs = Vec::new();
+        let mut old = &self.changes[..];
+        let mut new = &other.changes[..];
+        loop {
+            let offset = match (old.first(), new.first()) {
+                (Some(&(a, _)), Some(&(b, _))) => a.min(b),
+                (Some(&(a, _)), None) => a,
+                (None, Some(&(b, _))) => b,
+                (None, None) => break,
+            };
+            let (old_here, old_rest) = split_at_offset(old, offset);
+            let (new_here, new_rest) = split_at_offset(new, offset);
+            let mut removed: Vec<FrameLayoutChange> =
+                old_here.iter().map(|&(_, change)| change).collect();
+            let mut added = Vec::new();
+            for &(_, change) in new_here {
+                match removed.iter().position(|&old_change| old_change == change) {
+                    Some(i) => {
+                        removed.remove(i);
+                    }
+                    None => added.push(change),
+                }
+            }
+            for old_change in removed {
+                let target = ChangeTarget::of(old_change);
+                match added
+                    .iter()
+                    .position(|&new_change| ChangeTarget::of(new_change) == target)
+                {
+                    Some(i) => diffs.push(FrameLayoutDiff::Changed {
+                        offset,
+                        old: old_change,
+                        new: added.remove(i),
+                    }),
+                    None => diffs.push(FrameLayoutDiff::Removed {
+                        offset,
+                        change: old_change,
+                    }),
+                }
+            }
+            diffs.extend(
+                added
+                    .into_iter()
+                    .map(|change| FrameLayoutDiff::Added { offset, change }),
+            );
+            old = old_rest;
+            new = new_rest;
+        }
+        diffs
+    }
+}
+
+impl FrameLayoutInterner {
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `layout` to the pool, returning its id.
+    ///
+    /// Interning a layout equal to a previously interned one returns the id
+    /// of the latter.
+    pub fn intern(&mut self, layout: FrameLayout) -> FrameLayoutId {
+        if let Some(&id) = self.ids.get(&layout) {
+            return id;
+        }
+        let id = FrameLayoutId(self.layouts.len() as u32);
+        self.layouts.push(layout.clone());
+        self.ids.insert(layout, id);
+        id
+    }
+
+    /// Returns the layout with the given id, if any.
+    pub fn get(&self, id: FrameLayoutId) -> Option<&FrameLayout> {
+        self.layouts.get(id.0 as usize)
+    }
+
+    /// Returns the number of unique layouts in the pool.
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Returns `true` if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    /// Consumes the pool, returning the unique layouts indexed by id.
+    fn into_layouts(self) -> Vec<FrameLayout> {
+        self.layouts
+    }
+}
+
+impl FrameLayouts {
+    /// Creates empty frame layouts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the frame layout of the function `index`, if recorded.
+    pub fn get(&self, index: LocalFunctionIndex) -> Option<&FrameLayout> {
+        self.layouts
+            .get(&index)
+            .map(|&FrameLayoutId(id)| &self.pool[id as usize])
+    }
+
+    /// Returns the number of recorded frame layouts.
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Returns `true` if no frame layout was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+
+    /// Iterates over the recorded frame layouts, by function index.
+    pub fn iter(&self) -> impl Iterator<Item = (LocalFunctionIndex, &FrameLayout)> {
+        self.layouts
+            .iter()
+            .map(move |(&index, &FrameLayoutId(id))| (index, &self.pool[id as usize]))
+    }
+
+    /// Returns the number of unique frame layouts.
+    pub fn unique_len(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Sets the range of addresses of the code of the function `index`.
+    pub fn set_code_range(&mut self, index: LocalFunctionIndex, code_range: Range<usize>) {
+        self.code_ranges.insert(index, code_range);
+    }
+
+    /// Returns the range of addresses of the code of the function `index`,
+    /// if known.
+    pub fn code_range(&self, index: LocalFunctionIndex) -> Option<Range<usize>> {
+        self.code_ranges.get(&index).cloned()
+    }
+
+    /// Returns a copy of these frame layouts with the code ranges moved by
+    /// `base_offset`, eg. for a compilation unit linked at that offset.
+    pub fn relocated(&self, base_offset: usize) -> Self {
+        let mut relocated = self.clone();
+        for code_range in relocated.code_ranges.values_mut() {
+            *code_range = code_range.start + base_offset..code_range.end + base_offset;
+        }
+        relocated
+    }
+
+    /// Merges the frame layouts of compilation units linked together, each
+    /// `(base_offset, layouts)` unit having its code ranges moved by its
+    /// base offset.
+    ///
+    /// The units are expected to hold distinct functions; otherwise, the
+    /// last unit wins. The result is truncated if any unit is.
+    pub fn merge_relocated(units: &[(usize, &Self)]) -> Self {
+        let mut interner = FrameLayoutInterner::new();
+        let mut merged = Self::new();
+        for &(base_offset, unit) in units {
+            for (index, layout) in unit.iter() {
+                merged
+                    .layouts
+                    .insert(index, interner.intern(layout.clone()));
+            }
+            for (index, code_range) in unit.relocated(base_offset).code_ranges {
+                merged.code_ranges.insert(index, code_range);
+            }
+            merged.truncated |= unit.truncated;
+        }
+        merged.pool = interner.into_layouts();
+        merged
+    }
+
+    /// Iterates over the recorded code ranges, by function index.
+    pub fn code_ranges(&self) -> impl Iterator<Item = (LocalFunctionIndex, Range<usize>)> + '_ {
+        self.code_ranges
+            .iter()
+            .map(|(&index, code_range)| (index, code_range.clone()))
+    }
+
+    /// Returns the function whose code contains the address `pc`, if any.
+    pub fn function_for_pc(&self, pc: usize) -> Option<LocalFunctionIndex> {
+        self.code_ranges
+            .iter()
+            .find(|(_, code_range)| code_range.contains(&pc))
+            .map(|(&index, _)| index)
+    }
+
+    /// Returns `true` if `pc` is in the code of one of the functions.
+    ///
+    /// This neither allocates nor locks, so that it can be called from a
+    /// signal handler to tell faults in wasm code from faults in host code.
+    pub fn contains_pc(&self, pc: usize) -> bool {
+        self.code_ranges
+            .values()
+            .any(|code_range| code_range.contains(&pc))
+    }
+
+    /// Counts the sampled program counters `pcs` falling in the code of each
+    /// function. Program counters outside of any known code range are
+    /// ignored.
+    pub fn attribute(&self, pcs: &[usize]) -> HashMap<LocalFunctionIndex, usize> {
+        let mut counts = HashMap::new();
+        for index in pcs.iter().filter_map(|&pc| self.function_for_pc(pc)) {
+            *counts.entry(index).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Checks that exactly the functions `0..expected_function_count` have
+    /// a frame layout, reporting the first missing or extra one otherwise.
+    pub fn assert_complete(&self, expected_function_count: usize) -> Result<(), FrameLayoutError> {
+        if let Some(missing) = (0..expected_function_count)
+            .map(|i| LocalFunctionIndex::from_u32(i as u32))
+            .find(|index| !self.layouts.contains_key(index))
+        {
+            return Err(FrameLayoutError::Missing(missing));
+        }
+        match self
+            .layouts
+            .keys()
+            .find(|index| index.as_u32() as usize >= expected_function_count)
+        {
+            Some(&extra) => Err(FrameLayoutError::Extra(extra)),
+            None => Ok(()),
+        }
+    }
+
+    /// Encodes the frame layouts and code ranges as the contents of a
+    /// [`FRAME_LAYOUTS_SECTION_NAME`] section, for tools symbolicating the
+    /// module out of process.
+    ///
+    /// The section holds, in order:
+    ///
+    /// * the format version (`u8`) and whether the layouts are truncated
+    ///   (`u8`),
+    /// * the number of functions (`u32`), then for each function, by
+    ///   increasing index:
+    ///   * the function index (`u32`),
+    ///   * its code range, as a presence byte followed by the start and end
+    ///     addresses (`u64`s),
+    ///   * its frame layout, as a presence byte followed by the number of
+    ///     changes (`u32`) and the changes, each one being its code offset
+    ///     (`u32`), a tag (`u8`: 0 for `CallFrameAddressAt`, 1 for `RegAt`,
+    ///     2 for `ReturnAddressAt`) and its fields, registers as `u16`s and
+    ///     offsets as `i32`s.
+    ///
+    /// All integers are little-endian. Identical layouts are repeated for
+    /// each function using them.
+    pub fn to_section_bytes(&self) -> Vec<u8> {
+        let indices: BTreeMap<LocalFunctionIndex, ()> = self
+            .layouts
+            .keys()
+            .chain(self.code_ranges.keys())
+            .map(|&index| (index, ()))
+            .collect();
+        let mut bytes = vec![SECTION_VERSION, self.truncated as u8];
+        bytes.extend_from_slice(&(indices.len() as u32).to_le_bytes());
+        for &index in indices.keys() {
+            bytes.extend_from_slice(&index.as_u32().to_le_bytes());
+            match self.code_ranges.get(&index) {
+                Some(code_range) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&(code_range.start as u64).to_le_bytes());
+                    bytes.extend_from_slice(&(code_range.end as u64).to_le_bytes());
+                }
+                None => bytes.push(0),
+            }
+            match self.get(index) {
+                Some(layout) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&(layout.changes.len() as u32).to_le_bytes());
+                    for &(offset, change) in &layout.changes {
+                        bytes.extend_from_slice(&offset.to_le_bytes());
+                        write_change(&mut bytes, change);
+                    }
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes
+    }
+
+    /// Decodes frame layouts encoded with [`FrameLayouts::to_section_bytes`].
+    pub fn from_section_bytes(bytes: &[u8]) -> Result<Self, FrameSectionError> {
+        match Self::decode_section(bytes) {
+            (layouts, None) => Ok(layouts),
+            (_, Some(warning)) => Err(warning.error),
+        }
+    }
+
+    /// Decodes frame layouts encoded with [`FrameLayouts::to_section_bytes`],
+    /// keeping the functions decoded before the section turned out to be
+    /// truncated or corrupt.
+    ///
+    /// The decoding stops at the first invalid function, which is reported
+    /// as a warning; the recovered layouts are then marked as truncated.
+    pub fn from_section_bytes_lenient(bytes: &[u8]) -> (Self, Vec<FrameSectionWarning>) {
+        let (layouts, warning) = Self::decode_section(bytes);
+        (layouts, warning.into_iter().collect())
+    }
+
+    fn decode_section(bytes: &[u8]) -> (Self, Option<FrameSectionWarning>) {
+        let mut reader = SectionReader { bytes };
+        let mut interner = FrameLayoutInterner::new();
+        let mut layouts = Self::new();
+        let mut offset = 0;
+        let result = layouts.read_section(&mut reader, &mut interner, &mut offset);
+        layouts.pool = interner.into_layouts();
+        let warning = result.err().map(|error| {
+            layouts.truncated = true;
+            FrameSectionWarning { offset, error }
+        });
+        (layouts, warning)
+    }
+
+    /// Reads the section into `self`, keeping in `offset` the start of the
+    /// part being read.
+    fn read_section(
+        &mut self,
+        reader: &mut SectionReader,
+        interner: &mut FrameLayoutInterner,
+        offset: &mut usize,
+    ) -> Result<(), FrameSectionError> {
+        let len = reader.bytes.len();
+        let version = reader.u8()?;
+        if version != SECTION_VERSION {
+            return Err(FrameSectionError::UnsupportedVersion(version));
+        }
+        self.truncated = reader.u8()? != 0;
+        for _ in 0..reader.u32()? {
+            *offset = len - reader.bytes.len();
+            let (index, code_range, layout) = reader.function()?;
+            if let Some(code_range) = code_range {
+                self.set_code_range(index, code_range);
+            }
+            if let Some(layout) = layout {
+                self.layouts.insert(index, interner.intern(layout));
+            }
+        }
+        if !reader.bytes.is_empty() {
+            *offset = len - reader.bytes.len();
+            return Err(FrameSectionError::TrailingBytes);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if some frame layouts were dropped because a limit of
+    /// the [`FrameLayoutsBuilder`] was exceeded.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Summarizes the size of the frame layouts, eg. to report how much of
+    /// an artifact they take.
+    pub fn stats(&self) -> FrameLayoutStats {
+        FrameLayoutStats {
+            functions: self.len(),
+            total_changes: self.iter().map(|(_, layout)| layout.changes.len()).sum(),
+            serialized_bytes: self.to_section_bytes().len(),
+        }
+    }
+}
+
+impl PartialEq for FrameLayouts {
+    fn eq(&self, other: &Self) -> bool {
+        self.truncated == other.truncated
+            && self.code_ranges == other.code_ranges
+            && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for FrameLayouts {}
+
+impl FrameLayoutsBuilder {
+    /// Creates a new builder, without any limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of functions whose frame layout is recorded.
+    pub fn with_max_functions(&mut self, max_functions: usize) -> &mut Self {
+        self.max_functions = Some(max_functions);
+        self
+    }
+
+    /// Limits the approximate amount of memory used by the recorded frame
+    /// layouts, in bytes. Identical layouts are only counted once.
+    pub fn with_max_bytes(&mut self, max_bytes: usize) -> &mut Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Records the frame layout of the function `index`.
+    ///
+    /// Returns `false` if the layout was dropped because a limit was
+    /// exceeded, either now or by a previous layout.
+    pub fn push(&mut self, index: LocalFunctionIndex, layout: FrameLayout) -> bool {
+        let bytes = if self.interner.ids.contains_key(&layout) {
+            self.bytes
+        } else {
+            self.bytes + layout.byte_size()
+        };
+        let exceeded = self.layouts.truncated
+            || self
+                .max_functions
+                .map_or(false, |max| self.layouts.len() >= max)
+            || self.max_bytes.map_or(false, |max| bytes > max);
+        if exceeded {
+            self.layouts.truncated = true;
+            return false;
+        }
+        self.bytes = bytes;
+        let id = self.interner.intern(layout);
+        self.layouts.layouts.insert(index, id);
+        true
+    }
+
+    /// Finishes building the frame layouts.
+    pub fn build(self) -> FrameLayouts {
+        FrameLayouts {
+            pool: self.interner.into_layouts(),
+            ..self.layouts
+        }
+    }
+}
+
+fn write_change(bytes: &mut Vec<u8>, change: FrameLayoutChange) {
+    match change {
+        FrameLayoutChange::CallFrameAddressAt { reg, offset } => {
+            bytes.push(TAG_CALL_FRAME_ADDRESS_AT);
+            bytes.extend_from_slice(&reg.to_le_bytes());
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        FrameLayoutChange::RegAt { reg, cfa_offset } => {
+            bytes.push(TAG_REG_AT);
+            bytes.extend_from_slice(&reg.to_le_bytes());
+            bytes.extend_from_slice(&cfa_offset.to_le_bytes());
+        }
+        FrameLayoutChange::ReturnAddressAt { cfa_offset } => {
+            bytes.push(TAG_RETURN_ADDRESS_AT);
+            bytes.extend_from_slice(&cfa_offset.to_le_bytes());
+        }
+    }
+}
+
+/// Reads the fields of a frame layouts section, from the start of `bytes`.
+struct SectionReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SectionReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FrameSectionError> {
+        if self.bytes.len() < len {
+            return Err(FrameSectionError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, FrameSectionError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FrameSectionError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, FrameSectionError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, FrameSectionError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, FrameSectionError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads the index, code range and frame layout of a function.
+    fn function(
+        &mut self,
+    ) -> Result<
+        (
+            LocalFunctionIndex,
+            Option<Range<usize>>,
+            Option<FrameLayout>,
+        ),
+        FrameSectionError,
+    > {
+        let index = LocalFunctionIndex::from_u32(self.u32()?);
+        let code_range = if self.u8()? != 0 {
+            let start = self.u64()? as usize;
+            let end = self.u64()? as usize;
+            Some(start..end)
+        } else {
+            None
+        };
+        let layout = if self.u8()? != 0 {
+            let len = self.u32()?;
+            // Don't trust `len` for the allocation, the bytes may be cut.
+            let mut changes = Vec::new();
+            for _ in 0..len {
+                let offset = self.u32()?;
+                changes.push((offset, self.change()?));
+            }
+            Some(FrameLayout::new(changes))
+        } else {
+            None
+        };
+        Ok((index, code_range, layout))
+    }
+
+    fn change(&mut self) -> Result<FrameLayoutChange, FrameSectionError> {
+        match self.u8()? {
+            TAG_CALL_FRAME_ADDRESS_AT => Ok(FrameLayoutChange::CallFrameAddressAt {
+                reg: self.u16()?,
+                offset: self.i32()?,
+            }),
+            TAG_REG_AT => Ok(FrameLayoutChange::RegAt {
+                reg: self.u16()?,
+                cfa_offset: self.i32()?,
+            }),
+            TAG_RETURN_ADDRESS_AT => Ok(FrameLayoutChange::ReturnAddressAt {
+                cfa_offset: self.i32()?,
+            }),
+            tag => Err(FrameSectionError::InvalidChangeTag(tag)),
+        }
+    }
+}
+
+/// What a [`FrameLayoutChange`] sets, see [`FrameLayout::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ChangeTarget {
+    CallFrameAddress,
+    Reg(FrameRegister),
+    ReturnAddress,
+}
+
+impl ChangeTarget {
+    fn of(change: FrameLayoutChange) -> Self {
+        match change {
+            FrameLayoutChange::CallFrameAddressAt { .. } => Self::CallFrameAddress,
+            FrameLayoutChange::RegAt { reg, .. } => Self::Reg(reg),
+            FrameLayoutChange::ReturnAddressAt { .. } => Self::ReturnAddress,
+        }
+    }
+}
+
+/// Splits the sorted `changes` into the ones at `offset` and the rest.
+fn split_at_offset(
+    changes: &[(u32, FrameLayoutChange)],
+    offset: u32,
+) -> (&[(u32, FrameLayoutChange)], &[(u32, FrameLayoutChange)]) {
+    let len = changes
+        .iter()
+        .take_while(|&&(change_offset, _)| change_offset == offset)
+        .count();
+    changes.split_at(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::write::{Object, StandardSegment};
+    use object::{Architecture, BinaryFormat, Endianness, Object as _, ObjectSection, SectionKind};
+
+    fn layout(cfa_offset: i32) -> FrameLayout {
+        FrameLayout::new(vec![
+            (
+                0,
+                FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 8 },
+            ),
+            (
+                1,
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: 7,
+                    offset: cfa_offset,
+                },
+            ),
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 6,
+                    cfa_offset: -16,
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn builder_max_functions() {
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.with_max_functions(2);
+        assert!(builder.push(LocalFunctionIndex::from_u32(0), layout(16)));
+        assert!(builder.push(LocalFunctionIndex::from_u32(1), layout(16)));
+        assert!(!builder.push(LocalFunctionIndex::from_u32(2), layout(16)));
+        let layouts = builder.build();
+        assert!(layouts.is_truncated());
+        assert_eq!(layouts.len(), 2);
+        assert!(layouts.get(LocalFunctionIndex::from_u32(2)).is_none());
+    }
+
+    #[test]
+    fn builder_max_bytes() {
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.with_max_bytes(layout(16).byte_size());
+        assert!(builder.push(LocalFunctionIndex::from_u32(0), layout(16)));
+        // An identical layout doesn't take more memory.
+        assert!(builder.push(LocalFunctionIndex::from_u32(1), layout(16)));
+        assert!(!builder.push(LocalFunctionIndex::from_u32(2), layout(32)));
+        // Once truncated, even a layout that would fit is dropped.
+        assert!(!builder.push(LocalFunctionIndex::from_u32(3), FrameLayout::default()));
+        let layouts = builder.build();
+        assert!(layouts.is_truncated());
+        assert_eq!(layouts.len(), 2);
+    }
+
+    #[test]
+    fn builder_without_limits() {
+        let mut builder = FrameLayoutsBuilder::new();
+        assert!(builder.push(LocalFunctionIndex::from_u32(0), layout(16)));
+        let layouts = builder.build();
+        assert!(!layouts.is_truncated());
+        assert_eq!(
+            layouts.get(LocalFunctionIndex::from_u32(0)),
+            Some(&layout(16))
+        );
+    }
+
+    #[test]
+    fn interner() {
+        let mut interner = FrameLayoutInterner::new();
+        let a = interner.intern(layout(16));
+        let b = interner.intern(layout(32));
+        assert_eq!(interner.intern(layout(16)), a);
+        assert_ne!(a, b);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get(a), Some(&layout(16)));
+    }
+
+    #[test]
+    fn builder_deduplicates_layouts() {
+        let mut builder = FrameLayoutsBuilder::new();
+        for i in 0..10 {
+            builder.push(LocalFunctionIndex::from_u32(i), layout(16));
+        }
+        builder.push(LocalFunctionIndex::from_u32(10), layout(32));
+        let layouts = builder.build();
+        assert_eq!(layouts.len(), 11);
+        assert_eq!(layouts.unique_len(), 2);
+        assert_eq!(
+            layouts.get(LocalFunctionIndex::from_u32(9)),
+            Some(&layout(16))
+        );
+        assert_eq!(
+            layouts.get(LocalFunctionIndex::from_u32(10)),
+            Some(&layout(32))
+        );
+    }
+
+    #[test]
+    fn cfa_and_saved_reg_at() {
+        let layout = layout(16);
+        assert_eq!(layout.cfa_at(0), Some((7, 8)));
+        assert_eq!(layout.cfa_at(1), Some((7, 16)));
+        assert_eq!(layout.cfa_at(100), Some((7, 16)));
+        assert_eq!(layout.saved_reg_at(0, 6), None);
+        assert_eq!(layout.saved_reg_at(1, 6), Some(-16));
+        assert_eq!(FrameLayout::default().cfa_at(0), None);
+    }
+
+    #[test]
+    fn compact() {
+        let cfa = |offset| FrameLayoutChange::CallFrameAddressAt { reg: 7, offset };
+        let rbp_at = |cfa_offset| FrameLayoutChange::RegAt { reg: 6, cfa_offset };
+        let mut layout = FrameLayout::new(vec![
+            (0, cfa(8)),
+            // Restates the CFA rule in effect.
+            (1, cfa(8)),
+            // Overridden by the next change at the same offset.
+            (2, cfa(24)),
+            (2, cfa(16)),
+            (2, rbp_at(-16)),
+            (3, rbp_at(-16)),
+            (4, FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 }),
+            (5, FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 }),
+            (6, cfa(8)),
+        ]);
+        let before = layout.clone();
+        layout.compact();
+        assert_eq!(
+            layout.changes(),
+            &[
+                (0, cfa(8)),
+                (2, cfa(16)),
+                (2, rbp_at(-16)),
+                (4, FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 }),
+                (6, cfa(8)),
+            ]
+        );
+        for offset in 0..8 {
+            assert_eq!(layout.cfa_at(offset), before.cfa_at(offset));
+            assert_eq!(
+                layout.saved_reg_at(offset, 6),
+                before.saved_reg_at(offset, 6)
+            );
+            assert_eq!(
+                layout.return_address_at(offset),
+                before.return_address_at(offset)
+            );
+        }
+    }
+
+    #[test]
+    fn return_address_at() {
+        let mut changes = layout(16).changes().to_vec();
+        changes.push((4, FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 }));
+        changes.push((9, FrameLayoutChange::ReturnAddressAt { cfa_offset: -24 }));
+        let layout = FrameLayout::new(changes);
+        assert_eq!(layout.return_address_at(3), None);
+        assert_eq!(layout.return_address_at(4), Some(-8));
+        assert_eq!(layout.return_address_at(8), Some(-8));
+        assert_eq!(layout.return_address_at(9), Some(-24));
+        assert_eq!(layout.cfa_at(9), Some((7, 16)));
+        assert!(layout.dump().ends_with("       9: RA at CFA - 24\n"));
+    }
+
+    #[test]
+    fn assert_complete() {
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.push(LocalFunctionIndex::from_u32(0), layout(16));
+        builder.push(LocalFunctionIndex::from_u32(2), layout(16));
+        let layouts = builder.build();
+        assert_eq!(
+            layouts.assert_complete(3),
+            Err(FrameLayoutError::Missing(LocalFunctionIndex::from_u32(1)))
+        );
+        assert_eq!(
+            layouts.assert_complete(1),
+            Err(FrameLayoutError::Extra(LocalFunctionIndex::from_u32(2)))
+        );
+
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.push(LocalFunctionIndex::from_u32(0), layout(16));
+        builder.push(LocalFunctionIndex::from_u32(1), layout(32));
+        assert_eq!(builder.build().assert_complete(2), Ok(()));
+        assert_eq!(FrameLayouts::new().assert_complete(0), Ok(()));
+    }
+
+    fn section_layouts() -> FrameLayouts {
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.with_max_functions(3);
+        builder.push(LocalFunctionIndex::from_u32(0), layout(16));
+        builder.push(LocalFunctionIndex::from_u32(1), layout(16));
+        builder.push(
+            LocalFunctionIndex::from_u32(2),
+            FrameLayout::new(vec![(
+                4,
+                FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 },
+            )]),
+        );
+        builder.push(LocalFunctionIndex::from_u32(3), layout(32));
+        let mut layouts = builder.build();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(4), 0x1100..0x1180);
+        layouts
+    }
+
+    /// Writes `layouts` as the frame layouts section of an object file of
+    /// the given format, and reads the contents of the section back.
+    fn round_trip_through_object(layouts: &FrameLayouts, format: BinaryFormat) -> Vec<u8> {
+        let mut obj = Object::new(format, Architecture::X86_64, Endianness::Little);
+        let section = obj.add_section(
+            obj.segment_name(StandardSegment::Debug).to_vec(),
+            FRAME_LAYOUTS_SECTION_NAME.as_bytes().to_vec(),
+            SectionKind::Debug,
+        );
+        obj.append_section_data(section, &layouts.to_section_bytes(), 1);
+        let bytes = obj.write().unwrap();
+        let file = object::File::parse(&*bytes).unwrap();
+        file.section_by_name(FRAME_LAYOUTS_SECTION_NAME)
+            .unwrap()
+            .data()
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn section_bytes_through_object() {
+        let layouts = section_layouts();
+        for format in [BinaryFormat::Elf, BinaryFormat::MachO] {
+            let bytes = round_trip_through_object(&layouts, format);
+            assert_eq!(bytes, layouts.to_section_bytes(), "{:?}", format);
+            assert_eq!(
+                FrameLayouts::from_section_bytes(&bytes),
+                Ok(layouts.clone())
+            );
+        }
+    }
+
+    /// The layout of an AArch64 function saving `x29`, `x30` and `regs`,
+    /// in the saving order, with `stp`.
+    fn aarch64_layout(regs: &[FrameRegister]) -> FrameLayout {
+        let mut changes = vec![
+            (
+                4,
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: AARCH64_SP,
+                    offset: 16,
+                },
+            ),
+            (
+                4,
+                FrameLayoutChange::RegAt {
+                    reg: AARCH64_FP,
+                    cfa_offset: -16,
+                },
+            ),
+            (
+                4,
+                FrameLayoutChange::RegAt {
+                    reg: AARCH64_LR,
+                    cfa_offset: -8,
+                },
+            ),
+            (
+                8,
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: AARCH64_FP,
+                    offset: 16,
+                },
+            ),
+        ];
+        for (i, &reg) in regs.iter().enumerate() {
+            changes.push((
+                12 + 4 * (i as u32 / 2),
+                FrameLayoutChange::RegAt {
+                    reg,
+                    cfa_offset: -24 - 8 * i as i32,
+                },
+            ));
+        }
+        FrameLayout::new(changes)
+    }
+
+    #[test]
+    fn to_compact_unwind() {
+        assert_eq!(aarch64_layout(&[]).to_compact_unwind(), Some(0x0400_0000));
+        assert_eq!(
+            aarch64_layout(&[19, 20, 23, 24, 72, 73]).to_compact_unwind(),
+            Some(0x0400_0105)
+        );
+        // Out of the order of the encoding.
+        assert_eq!(aarch64_layout(&[21, 22, 19, 20]).to_compact_unwind(), None);
+        // Not a pair.
+        assert_eq!(aarch64_layout(&[19]).to_compact_unwind(), None);
+        // Without a frame pointer.
+        assert_eq!(layout(16).to_compact_unwind(), None);
+    }
+
+    #[test]
+    fn relocated() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x0..0x100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x100..0x180);
+        let relocated = layouts.relocated(0x4000);
+        assert_eq!(
+            relocated.code_range(LocalFunctionIndex::from_u32(1)),
+            Some(0x4100..0x4180)
+        );
+        assert_eq!(
+            relocated.function_for_pc(0x4010),
+            Some(LocalFunctionIndex::from_u32(0))
+        );
+        assert_eq!(relocated.function_for_pc(0x10), None);
+        assert_eq!(
+            layouts.function_for_pc(0x10),
+            Some(LocalFunctionIndex::from_u32(0))
+        );
+    }
+
+    #[test]
+    fn merge_relocated() {
+        let mut first = FrameLayouts::new();
+        first.set_code_range(LocalFunctionIndex::from_u32(0), 0x0..0x100);
+        let mut builder = FrameLayoutsBuilder::new();
+        builder.push(LocalFunctionIndex::from_u32(0), layout(16));
+        builder.push(LocalFunctionIndex::from_u32(1), layout(16));
+        let mut second = builder.build();
+        second.set_code_range(LocalFunctionIndex::from_u32(1), 0x0..0x80);
+
+        let merged = FrameLayouts::merge_relocated(&[(0x1000, &first), (0x2000, &second)]);
+        assert_eq!(
+            merged.function_for_pc(0x1010),
+            Some(LocalFunctionIndex::from_u32(0))
+        );
+        assert_eq!(
+            merged.function_for_pc(0x2010),
+            Some(LocalFunctionIndex::from_u32(1))
+        );
+        assert_eq!(
+            merged.get(LocalFunctionIndex::from_u32(1)),
+            Some(&layout(16))
+        );
+        assert_eq!(merged.len(), 2);
+        assert!(!merged.is_truncated());
+    }
+
+    #[test]
+    fn stats() {
+        assert_eq!(
+            FrameLayouts::new().stats(),
+            FrameLayoutStats {
+                functions: 0,
+                total_changes: 0,
+                serialized_bytes: 6,
+            }
+        );
+
+        let layouts = section_layouts();
+        let stats = layouts.stats();
+        // Two functions share `layout(16)`, of three changes, which are
+        // counted for each of them.
+        assert_eq!(stats.functions, 3);
+        assert_eq!(stats.total_changes, 3 + 3 + 1);
+        assert_eq!(stats.serialized_bytes, layouts.to_section_bytes().len());
+    }
+
+    #[test]
+    fn section_bytes_round_trip() {
+        let layouts = section_layouts();
+        let decoded = FrameLayouts::from_section_bytes(&layouts.to_section_bytes()).unwrap();
+        assert_eq!(decoded, layouts);
+        assert_eq!(decoded.unique_len(), 2);
+        assert!(decoded.is_truncated());
+        assert_eq!(
+            FrameLayouts::from_section_bytes(&FrameLayouts::new().to_section_bytes()),
+            Ok(FrameLayouts::new())
+        );
+    }
+
+    #[test]
+    fn section_bytes_truncated() {
+        let bytes = section_layouts().to_section_bytes();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                FrameLayouts::from_section_bytes(&bytes[..len]),
+                Err(FrameSectionError::UnexpectedEnd),
+                "{} bytes",
+                len
+            );
+        }
+        let mut bytes = bytes;
+        bytes.push(0);
+        assert_eq!(
+            FrameLayouts::from_section_bytes(&bytes),
+            Err(FrameSectionError::TrailingBytes)
+        );
+        assert_eq!(
+            FrameLayouts::from_section_bytes(&[SECTION_VERSION + 1]),
+            Err(FrameSectionError::UnsupportedVersion(SECTION_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn section_bytes_lenient() {
+        let layouts = section_layouts();
+        let bytes = layouts.to_section_bytes();
+        let (decoded, warnings) = FrameLayouts::from_section_bytes_lenient(&bytes);
+        assert_eq!(decoded, layouts);
+        assert!(warnings.is_empty());
+
+        // Cut in the middle of the last function, which only has a code range.
+        let last = bytes.len() - (4 + 1 + 16 + 1);
+        let cut = bytes.len() - 3;
+        let (decoded, warnings) = FrameLayouts::from_section_bytes_lenient(&bytes[..cut]);
+        assert_eq!(
+            warnings,
+            vec![FrameSectionWarning {
+                offset: last,
+                error: FrameSectionError::UnexpectedEnd,
+            }]
+        );
+        assert!(decoded.is_truncated());
+        assert_eq!(
+            decoded.get(LocalFunctionIndex::from_u32(0)),
+            Some(&layout(16))
+        );
+        assert_eq!(
+            decoded.code_range(LocalFunctionIndex::from_u32(0)),
+            Some(0x1000..0x1100)
+        );
+        assert!(decoded.get(LocalFunctionIndex::from_u32(2)).is_some());
+        assert_eq!(decoded.code_range(LocalFunctionIndex::from_u32(4)), None);
+
+        let (decoded, warnings) = FrameLayouts::from_section_bytes_lenient(&[]);
+        assert!(decoded.is_empty());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn canonical_shape() {
+        // The same prologue, with x86-64 and AArch64 register numbers and
+        // instruction sizes.
+        let shape = |sp, fp, saved, offsets: [u32; 4]| {
+            FrameLayout::new(vec![
+                (
+                    offsets[0],
+                    FrameLayoutChange::CallFrameAddressAt {
+                        reg: sp,
+                        offset: 16,
+                    },
+                ),
+                (
+                    offsets[1],
+                    FrameLayoutChange::RegAt {
+                        reg: fp,
+                        cfa_offset: -16,
+                    },
+                ),
+                (
+                    offsets[2],
+                    FrameLayoutChange::CallFrameAddressAt {
+                        reg: fp,
+                        offset: 16,
+                    },
+                ),
+                (
+                    offsets[3],
+                    FrameLayoutChange::RegAt {
+                        reg: saved,
+                        cfa_offset: -24,
+                    },
+                ),
+            ])
+            .canonical_shape()
+        };
+        let x86_64 = shape(7, 6, 3, [1, 1, 4, 5]);
+        let aarch64 = shape(31, 29, 19, [4, 4, 8, 12]);
+        assert_eq!(x86_64, aarch64);
+        assert_eq!(
+            x86_64,
+            vec![
+                CanonicalChange::CallFrameAddressAt {
+                    reg: CanonicalRegister::StackPointer,
+                    offset: 16
+                },
+                CanonicalChange::RegAt {
+                    reg: CanonicalRegister::FramePointer,
+                    cfa_offset: -16
+                },
+                CanonicalChange::CallFrameAddressAt {
+                    reg: CanonicalRegister::FramePointer,
+                    offset: 16
+                },
+                CanonicalChange::RegAt {
+                    reg: CanonicalRegister::CalleeSaved(0),
+                    cfa_offset: -24
+                },
+            ]
+        );
+        // Saving the frame pointer again instead of another register changes
+        // the shape.
+        assert_ne!(shape(7, 6, 6, [1, 1, 4, 5]), x86_64);
+    }
+
+    #[test]
+    fn function_for_pc() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x1100..0x1180);
+        assert_eq!(
+            layouts.function_for_pc(0x1000),
+            Some(LocalFunctionIndex::from_u32(0))
+        );
+        assert_eq!(
+            layouts.function_for_pc(0x1100),
+            Some(LocalFunctionIndex::from_u32(1))
+        );
+        assert_eq!(layouts.function_for_pc(0x1180), None);
+    }
+
+    #[test]
+    fn contains_pc() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x1200..0x1280);
+        assert!(layouts.contains_pc(0x1000));
+        assert!(layouts.contains_pc(0x10ff));
+        assert!(layouts.contains_pc(0x1240));
+        assert!(!layouts.contains_pc(0xfff));
+        assert!(!layouts.contains_pc(0x1100));
+        assert!(!layouts.contains_pc(0x1280));
+        assert!(!FrameLayouts::new().contains_pc(0x1000));
+    }
+
+    #[test]
+    fn attribute() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x1100..0x1180);
+        let counts = layouts.attribute(&[0x1000, 0x10ff, 0x1100, 0x1050, 0x2000, 0x10]);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&LocalFunctionIndex::from_u32(0)], 3);
+        assert_eq!(counts[&LocalFunctionIndex::from_u32(1)], 1);
+    }
+
+    #[test]
+    fn dump() {
+        assert_eq!(
+            layout(16).dump(),
+            "       0: CFA = r7 + 8\n       1: CFA = r7 + 16\n       1: r6 at CFA - 16\n"
+        );
+    }
+
+    #[test]
+    fn diff_identical() {
+        assert!(layout(16).diff(&layout(16)).is_empty());
+    }
+
+    #[test]
+    fn diff_single_change() {
+        assert_eq!(
+            layout(16).diff(&layout(32)),
+            vec![FrameLayoutDiff::Changed {
+                offset: 1,
+                old: FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 16 },
+                new: FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 32 },
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_added_and_removed() {
+        let old = FrameLayout::new(vec![(
+            4,
+            FrameLayoutChange::RegAt {
+                reg: 3,
+                cfa_offset: -24,
+            },
+        )]);
+        let new = FrameLayout::new(vec![(
+            8,
+            FrameLayoutChange::RegAt {
+                reg: 3,
+                cfa_offset: -24,
+            },
+        )]);
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                FrameLayoutDiff::Removed {
+                    offset: 4,
+                    change: FrameLayoutChange::RegAt {
+                        reg: 3,
+                        cfa_offset: -24
+                    },
+                },
+                FrameLayoutDiff::Added {
+                    offset: 8,
+                    change: FrameLayoutChange::RegAt {
+                        reg: 3,
+                        cfa_offset: -24
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_reordered_changes() {
+        let old = FrameLayout::new(vec![
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 6,
+                    cfa_offset: -16,
+                },
+            ),
+            (
+                1,
+                FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 16 },
+            ),
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 3,
+                    cfa_offset: -24,
+                },
+            ),
+        ]);
+        let new = FrameLayout::new(vec![
+            (
+                1,
+                FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 16 },
+            ),
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 3,
+                    cfa_offset: -32,
+                },
+            ),
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 6,
+                    cfa_offset: -16,
+                },
+            ),
+        ]);
+        assert_eq!(
+            old.diff(&new),
+            vec![FrameLayoutDiff::Changed {
+                offset: 1,
+                old: FrameLayoutChange::RegAt {
+                    reg: 3,
+                    cfa_offset: -24,
+                },
+                new: FrameLayoutChange::RegAt {
+                    reg: 3,
+                    cfa_offset: -32,
+                },
+            }]
+        );
+        assert_eq!(
+            layout(16).diff(&new),
+            vec![
+                FrameLayoutDiff::Removed {
+                    offset: 0,
+                    change: FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 8 },
+                },
+                FrameLayoutDiff::Added {
+                    offset: 1,
+                    change: FrameLayoutChange::RegAt {
+                        reg: 3,
+                        cfa_offset: -32,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn eq_ignores_interning_order() {
+        let mut a = FrameLayoutsBuilder::new();
+        a.push(LocalFunctionIndex::from_u32(0), layout(16));
+        a.push(LocalFunctionIndex::from_u32(1), layout(32));
+        let mut b = FrameLayoutsBuilder::new();
+        b.push(LocalFunctionIndex::from_u32(1), layout(32));
+        b.push(LocalFunctionIndex::from_u32(0), layout(16));
+        let (a, mut b) = (a.build(), b.build());
+        assert_eq!(a, b);
+
+        b.set_code_range(LocalFunctionIndex::from_u32(0), 0..16);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,128 @@
+//! Export of the compiled functions in the `perf` jitdump format.
+//!
+//! The format is described in `tools/perf/Documentation/jitdump-specification.txt`
+//! in the Linux sources. A jitdump file is a header followed by records;
+//! only `JIT_CODE_LOAD` records are written here, which is enough for
+//! `perf inject --jit` to attribute samples to the wasm functions.
+
+use super::FrameLayouts;
+use crate::HashMap;
+use std::io::{self, Write};
+use std::slice;
+use std::string::String;
+use wasmer_types::LocalFunctionIndex;
+
+/// The magic number of jitdump files, `JiTD`.
+const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+const JITDUMP_VERSION: u32 = 1;
+const JITDUMP_HEADER_SIZE: u32 = 40;
+
+/// The id of `JIT_CODE_LOAD` records.
+const JIT_CODE_LOAD: u32 = 0;
+/// The size of a `JIT_CODE_LOAD` record, without its name and code.
+const JIT_CODE_LOAD_SIZE: usize = 56;
+
+/// The ELF machine of the host architecture (`EM_AARCH64`, `EM_X86_64`).
+#[cfg(target_arch = "aarch64")]
+const ELF_MACHINE: u32 = 183;
+#[cfg(not(target_arch = "aarch64"))]
+const ELF_MACHINE: u32 = 62;
+
+/// Writes a jitdump file to `writer`, with a `JIT_CODE_LOAD` record for
+/// every function of `layouts` whose code range is known.
+///
+/// Functions missing from `names` are named after their index. All the
+/// timestamps are zero, so the code is considered loaded before any sample.
+///
+/// # Safety
+///
+/// The code ranges of `layouts` must point to readable memory, as the code
+/// of the functions is copied into the records.
+pub unsafe fn write_jitdump<W: Write>(
+    writer: &mut W,
+    layouts: &FrameLayouts,
+    names: &HashMap<LocalFunctionIndex, String>,
+    pid: u32,
+) -> io::Result<()> {
+    writer.write_all(&JITDUMP_MAGIC.to_ne_bytes())?;
+    writer.write_all(&JITDUMP_VERSION.to_ne_bytes())?;
+    writer.write_all(&JITDUMP_HEADER_SIZE.to_ne_bytes())?;
+    writer.write_all(&ELF_MACHINE.to_ne_bytes())?;
+    // Padding.
+    writer.write_all(&0u32.to_ne_bytes())?;
+    writer.write_all(&pid.to_ne_bytes())?;
+    // Timestamp.
+    writer.write_all(&0u64.to_ne_bytes())?;
+    // Flags.
+    writer.write_all(&0u64.to_ne_bytes())?;
+
+    for (code_index, (index, code_range)) in layouts.code_ranges().enumerate() {
+        let name = names
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("wasm-function[{}]", index.as_u32()));
+        let code = slice::from_raw_parts(code_range.start as *const u8, code_range.len());
+        let total_size = JIT_CODE_LOAD_SIZE + name.len() + 1 + code.len();
+
+        writer.write_all(&JIT_CODE_LOAD.to_ne_bytes())?;
+        writer.write_all(&(total_size as u32).to_ne_bytes())?;
+        // Timestamp.
+        writer.write_all(&0u64.to_ne_bytes())?;
+        // The process and thread ids.
+        writer.write_all(&pid.to_ne_bytes())?;
+        writer.write_all(&pid.to_ne_bytes())?;
+        // The virtual address and the address of the code are the same.
+        writer.write_all(&(code_range.start as u64).to_ne_bytes())?;
+        writer.write_all(&(code_range.start as u64).to_ne_bytes())?;
+        writer.write_all(&(code.len() as u64).to_ne_bytes())?;
+        writer.write_all(&(code_index as u64).to_ne_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&[0])?;
+        writer.write_all(code)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_ne_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn header_and_code_load_record() {
+        let code = [0x90u8; 16];
+        let start = code.as_ptr() as usize;
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), start..start + code.len());
+        let mut names = HashMap::new();
+        names.insert(LocalFunctionIndex::from_u32(0), "fib".to_string());
+
+        let mut bytes = Vec::new();
+        unsafe { write_jitdump(&mut bytes, &layouts, &names, 42) }.unwrap();
+
+        assert_eq!(u32_at(&bytes, 0), JITDUMP_MAGIC);
+        assert_eq!(u32_at(&bytes, 4), JITDUMP_VERSION);
+        assert_eq!(u32_at(&bytes, 8), JITDUMP_HEADER_SIZE);
+        assert_eq!(u32_at(&bytes, 20), 42);
+
+        let record = &bytes[JITDUMP_HEADER_SIZE as usize..];
+        assert_eq!(u32_at(record, 0), JIT_CODE_LOAD);
+        assert_eq!(u32_at(record, 4) as usize, record.len());
+        assert_eq!(u64_at(record, 24), start as u64);
+        assert_eq!(u64_at(record, 40), code.len() as u64);
+        assert_eq!(
+            &record[JIT_CODE_LOAD_SIZE..JIT_CODE_LOAD_SIZE + 4],
+            b"fib\0"
+        );
+        assert_eq!(&record[JIT_CODE_LOAD_SIZE + 4..], &code[..]);
+    }
+}
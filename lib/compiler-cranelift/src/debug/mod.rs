@@ -1,3 +1,322 @@
 mod address_map;
+mod frame_layout;
+#[cfg(feature = "std")]
+mod jitdump;
 
 pub use self::address_map::{ModuleInfoMemoryOffset, ModuleInfoVmctxInfo, ValueLabelsRanges};
+pub use self::frame_layout::{
+    CanonicalChange, CanonicalRegister, FrameLayout, FrameLayoutChange, FrameLayoutDiff,
+    FrameLayoutError, FrameLayoutId, FrameLayoutInterner, FrameLayoutStats, FrameLayouts,
+    FrameLayoutsBuilder, FrameRegister, FrameSectionError, FrameSectionWarning,
+    FRAME_LAYOUTS_SECTION_NAME,
+};
+#[cfg(feature = "std")]
+pub use self::jitdump::write_jitdump;
+
+use crate::HashMap;
+use std::fmt;
+use std::mem;
+use std::string::String;
+use std::vec::Vec;
+use wasmer_types::LocalFunctionIndex;
+
+/// The DWARF numbers of the stack pointer and frame pointer registers.
+#[cfg(target_arch = "aarch64")]
+const SP_REG: FrameRegister = 31;
+#[cfg(target_arch = "aarch64")]
+const FP_REG: FrameRegister = 29;
+#[cfg(not(target_arch = "aarch64"))]
+const SP_REG: FrameRegister = 7;
+#[cfg(not(target_arch = "aarch64"))]
+const FP_REG: FrameRegister = 6;
+
+/// Walks the stack starting from the frame described by `pc`, `sp` and `fp`,
+/// returning the program counter of every frame, innermost first.
+///
+/// This is a fallback for when the system unwinder can't be used. Each frame
+/// is unwound by following the CFA rule of its function at the current
+/// offset; the return address is read from its slot, right below the CFA
+/// unless the layout says otherwise, and the caller's frame pointer from its
+/// save slot, if any. Unwinding stops as soon as the
+/// program counter leaves the code of the functions in `layouts`.
+///
+/// # Safety
+///
+/// `sp` and `fp` must point into a stack laid out as described by `layouts`,
+/// which is read from.
+pub unsafe fn unwind_with_layouts(
+    pc: usize,
+    sp: usize,
+    fp: usize,
+    layouts: &FrameLayouts,
+) -> Vec<usize> {
+    let (mut pc, mut sp, mut fp) = (pc, sp, fp);
+    let mut frames = Vec::new();
+    while let Some(index) = layouts.function_for_pc(pc) {
+        frames.push(pc);
+        let code_offset = (pc - layouts.code_range(index).unwrap().start) as u32;
+        let layout = match layouts.get(index) {
+            Some(layout) => layout,
+            None => break,
+        };
+        let (reg, offset) = match layout.cfa_at(code_offset) {
+            Some(cfa) => cfa,
+            None => break,
+        };
+        let base = match reg {
+            SP_REG => sp,
+            FP_REG => fp,
+            _ => break,
+        };
+        let cfa = (base as isize).wrapping_add(offset as isize) as usize;
+        // The stack grows downwards: a CFA that doesn't go up means the
+        // layouts don't match the stack, don't loop on it.
+        if cfa <= sp {
+            break;
+        }
+        if let Some(fp_offset) = layout.saved_reg_at(code_offset, FP_REG) {
+            fp = read_word((cfa as isize).wrapping_add(fp_offset as isize) as usize);
+        }
+        let ra_offset = layout
+            .return_address_at(code_offset)
+            .unwrap_or(-(mem::size_of::<usize>() as i32));
+        pc = read_word((cfa as isize).wrapping_add(ra_offset as isize) as usize);
+        sp = cfa;
+    }
+    frames
+}
+
+unsafe fn read_word(address: usize) -> usize {
+    (address as *const usize).read_unaligned()
+}
+
+/// A backtrace of wasm frames, printed like a `std::backtrace::Backtrace`.
+///
+/// `Display` prints one numbered `#n name (module+0xoffset)` line per
+/// program counter in the code of a function of the `layouts`, innermost
+/// first, skipping the host frames. The offset is relative to the start of
+/// the code of the module, and unnamed functions are printed as
+/// `<unnamed>`.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmBacktrace<'a> {
+    module_name: &'a str,
+    pcs: &'a [usize],
+    layouts: &'a FrameLayouts,
+    names: &'a HashMap<LocalFunctionIndex, String>,
+}
+
+impl<'a> WasmBacktrace<'a> {
+    /// Creates the backtrace of the program counters `pcs`, innermost
+    /// first, eg. as returned by [`unwind_with_layouts`].
+    pub fn new(
+        module_name: &'a str,
+        pcs: &'a [usize],
+        layouts: &'a FrameLayouts,
+        names: &'a HashMap<LocalFunctionIndex, String>,
+    ) -> Self {
+        Self {
+            module_name,
+            pcs,
+            layouts,
+            names,
+        }
+    }
+}
+
+impl fmt::Display for WasmBacktrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code_start = self
+            .layouts
+            .code_ranges()
+            .map(|(_, code_range)| code_range.start)
+            .min()
+            .unwrap_or(0);
+        let frames = self
+            .pcs
+            .iter()
+            .filter_map(|&pc| Some((pc, self.layouts.function_for_pc(pc)?)));
+        for (n, (pc, index)) in frames.enumerate() {
+            let name = self.names.get(&index).map_or("<unnamed>", String::as_str);
+            writeln!(
+                f,
+                "#{} {} ({}+{:#x})",
+                n,
+                name,
+                self.module_name,
+                pc - code_start
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The line program of a module, decoded from its `.debug_line` custom
+/// section, eg. with `gimli`, and transformed to the native code.
+///
+/// Each row maps the code from its address to the address of the next row
+/// to a line of a source file. Addresses are in the same address space as
+/// the code ranges of the [`FrameLayouts`] of the module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DwarfLineProgram {
+    files: Vec<String>,
+    /// The `(address, file, line)` rows, sorted by address.
+    rows: Vec<(usize, usize, u32)>,
+}
+
+impl DwarfLineProgram {
+    /// Creates a line program from its source files and its
+    /// `(address, file, line)` rows, where `file` is an index in `files`.
+    ///
+    /// The rows are sorted by address. As in DWARF, line 0 means that the
+    /// code doesn't come from any source line.
+    pub fn new(files: Vec<String>, mut rows: Vec<(usize, usize, u32)>) -> Self {
+        rows.sort_by_key(|&(address, _, _)| address);
+        Self { files, rows }
+    }
+}
+
+/// Maps the program counter `pc` to the source file and line it was
+/// compiled from, if it is in the code of a function of `layouts` covered by
+/// a row of `dwarf`.
+///
+/// The rows of another function never apply, even if it has no row of its
+/// own before `pc`.
+pub fn pc_to_source_line(
+    pc: usize,
+    layouts: &FrameLayouts,
+    dwarf: &DwarfLineProgram,
+) -> Option<(String, u32)> {
+    let code_range = layouts.code_range(layouts.function_for_pc(pc)?)?;
+    let rows_until_pc = dwarf.rows.partition_point(|&(address, _, _)| address <= pc);
+    let &(address, file, line) = dwarf.rows[..rows_until_pc].last()?;
+    if address < code_range.start || line == 0 {
+        return None;
+    }
+    Some((dwarf.files.get(file)?.clone(), line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn unwind_two_frames() {
+        let word = mem::size_of::<usize>() as i32;
+        let mut builder = FrameLayoutsBuilder::new();
+        // A leaf function, which doesn't set up a frame.
+        builder.push(
+            LocalFunctionIndex::from_u32(0),
+            FrameLayout::new(vec![(
+                0,
+                FrameLayoutChange::CallFrameAddressAt {
+                    reg: SP_REG,
+                    offset: word,
+                },
+            )]),
+        );
+        // Its caller, which saves the frame pointer and uses it as the base
+        // of the CFA.
+        builder.push(
+            LocalFunctionIndex::from_u32(1),
+            FrameLayout::new(vec![
+                (
+                    0,
+                    FrameLayoutChange::CallFrameAddressAt {
+                        reg: SP_REG,
+                        offset: word,
+                    },
+                ),
+                (
+                    1,
+                    FrameLayoutChange::CallFrameAddressAt {
+                        reg: SP_REG,
+                        offset: 2 * word,
+                    },
+                ),
+                (
+                    1,
+                    FrameLayoutChange::RegAt {
+                        reg: FP_REG,
+                        cfa_offset: -2 * word,
+                    },
+                ),
+                (
+                    4,
+                    FrameLayoutChange::CallFrameAddressAt {
+                        reg: FP_REG,
+                        offset: 2 * word,
+                    },
+                ),
+            ]),
+        );
+        let mut layouts = builder.build();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x2000..0x2100);
+
+        // stack[0]: return address into the caller,
+        // stack[1]: the caller's saved frame pointer,
+        // stack[2]: return address out of the JIT code.
+        let mut stack: [usize; 3] = [0x2020, 0, 0x5000];
+        let sp = stack.as_mut_ptr() as usize;
+        let fp = sp + word as usize;
+        let frames = unsafe { unwind_with_layouts(0x1010, sp, fp, &layouts) };
+        assert_eq!(frames, vec![0x1010, 0x2020]);
+    }
+
+    #[test]
+    fn wasm_backtrace() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x1100..0x1200);
+        let mut names = HashMap::new();
+        names.insert(LocalFunctionIndex::from_u32(0), "fib".to_string());
+        // The frame of the host in the middle is skipped.
+        let pcs = [0x1010, 0x5000, 0x1120];
+        let backtrace = WasmBacktrace::new("fib.wasm", &pcs, &layouts, &names);
+        assert_eq!(
+            backtrace.to_string(),
+            "#0 fib (fib.wasm+0x10)\n#1 <unnamed> (fib.wasm+0x120)\n"
+        );
+    }
+
+    #[test]
+    fn source_line() {
+        let mut layouts = FrameLayouts::new();
+        layouts.set_code_range(LocalFunctionIndex::from_u32(0), 0x1000..0x1100);
+        layouts.set_code_range(LocalFunctionIndex::from_u32(1), 0x1100..0x1200);
+        let dwarf = DwarfLineProgram::new(
+            vec!["src/lib.rs".to_string(), "src/util.rs".to_string()],
+            vec![
+                (0x1020, 0, 12),
+                (0x1000, 0, 10),
+                (0x1040, 0, 0),
+                (0x1140, 1, 3),
+            ],
+        );
+        assert_eq!(
+            pc_to_source_line(0x1024, &layouts, &dwarf),
+            Some(("src/lib.rs".to_string(), 12))
+        );
+        assert_eq!(
+            pc_to_source_line(0x1000, &layouts, &dwarf),
+            Some(("src/lib.rs".to_string(), 10))
+        );
+        assert_eq!(
+            pc_to_source_line(0x1180, &layouts, &dwarf),
+            Some(("src/util.rs".to_string(), 3))
+        );
+        // Compiler-generated code.
+        assert_eq!(pc_to_source_line(0x1050, &layouts, &dwarf), None);
+        // Before the first row of the function.
+        assert_eq!(pc_to_source_line(0x1110, &layouts, &dwarf), None);
+        // Outside of the code of the module.
+        assert_eq!(pc_to_source_line(0x5000, &layouts, &dwarf), None);
+    }
+
+    #[test]
+    fn unwind_outside_of_jit_code() {
+        let frames = unsafe { unwind_with_layouts(0x1010, 0, 0, &FrameLayouts::new()) };
+        assert!(frames.is_empty());
+    }
+}
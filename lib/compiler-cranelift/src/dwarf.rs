@@ -1,6 +1,8 @@
+use crate::debug::{FrameLayout, FrameLayoutChange, FrameLayouts, FrameLayoutsBuilder};
+use gimli::read::{self, UnwindSection};
 use gimli::write::{Address, EndianVec, Result, Writer};
 use gimli::{RunTimeEndian, SectionId};
-use wasmer_compiler::{CustomSection, CustomSectionProtection, SectionBody};
+use wasmer_compiler::{CompileError, CustomSection, CustomSectionProtection, SectionBody};
 use wasmer_compiler::{Endianness, Relocation, RelocationKind, RelocationTarget};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::LocalFunctionIndex;
@@ -100,3 +102,151 @@ impl Writer for WriterRelocate {
         unimplemented!("write_offset_at not yet implemented");
     }
 }
+
+/// Decodes the frame layouts of the functions from the `.eh_frame` section
+/// written with a `WriterRelocate`, in the `endian` byte order.
+///
+/// The initial address of each FDE is its only relocation, against its
+/// function, so the FDEs and the relocations are matched in order.
+pub(crate) fn frame_layouts(
+    section: &CustomSection,
+    endian: RunTimeEndian,
+) -> core::result::Result<FrameLayouts, CompileError> {
+    let invalid =
+        |error: read::Error| CompileError::Codegen(format!("invalid .eh_frame: {}", error));
+    let mut eh_frame = read::EhFrame::new(section.bytes.as_slice(), endian);
+    eh_frame.set_address_size(8);
+    let bases = read::BaseAddresses::default();
+    let mut ctx = read::UnwindContext::new();
+    let mut functions = section.relocations.iter().map(|reloc| reloc.reloc_target);
+    let mut layouts = FrameLayoutsBuilder::new();
+    let mut entries = eh_frame.entries(&bases);
+    while let Some(entry) = entries.next().map_err(invalid)? {
+        let partial = match entry {
+            read::CieOrFde::Fde(partial) => partial,
+            read::CieOrFde::Cie(_) => continue,
+        };
+        let fde = partial
+            .parse(UnwindSection::cie_from_offset)
+            .map_err(invalid)?;
+        let index = match functions.next() {
+            Some(RelocationTarget::LocalFunc(index)) => index,
+            _ => {
+                return Err(CompileError::Codegen(
+                    "invalid .eh_frame: FDE without a function relocation".to_string(),
+                ))
+            }
+        };
+        let return_address_register = fde.cie().return_address_register();
+        let mut changes = Vec::new();
+        let mut cfa = None;
+        let mut saved: Vec<(gimli::Register, i64)> = Vec::new();
+        let mut rows = fde.rows(&eh_frame, &bases, &mut ctx).map_err(invalid)?;
+        while let Some(row) = rows.next_row().map_err(invalid)? {
+            let code_offset = (row.start_address() - fde.initial_address()) as u32;
+            if let read::CfaRule::RegisterAndOffset { register, offset } = *row.cfa() {
+                if cfa != Some((register, offset)) {
+                    cfa = Some((register, offset));
+                    changes.push((
+                        code_offset,
+                        FrameLayoutChange::CallFrameAddressAt {
+                            reg: register.0,
+                            offset: offset as i32,
+                        },
+                    ));
+                }
+            }
+            for &(register, ref rule) in row.registers() {
+                let cfa_offset = match *rule {
+                    read::RegisterRule::Offset(cfa_offset) => cfa_offset,
+                    _ => continue,
+                };
+                if saved.contains(&(register, cfa_offset)) {
+                    continue;
+                }
+                saved.retain(|&(saved_register, _)| saved_register != register);
+                saved.push((register, cfa_offset));
+                let cfa_offset = cfa_offset as i32;
+                let change = if register == return_address_register {
+                    FrameLayoutChange::ReturnAddressAt { cfa_offset }
+                } else {
+                    FrameLayoutChange::RegAt {
+                        reg: register.0,
+                        cfa_offset,
+                    }
+                };
+                changes.push((code_offset, change));
+            }
+        }
+        layouts.push(index, FrameLayout::new(changes));
+    }
+    Ok(layouts.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::write::{
+        CallFrameInstruction, CommonInformationEntry, EhFrame, FrameDescriptionEntry, FrameTable,
+    };
+    use gimli::{Encoding, Format, Register};
+
+    #[test]
+    fn frame_layouts_from_eh_frame() {
+        // The CIE of x86_64, and the prologue of a function setting up a
+        // frame pointer.
+        let encoding = Encoding {
+            address_size: 8,
+            format: Format::Dwarf32,
+            version: 1,
+        };
+        let mut cie = CommonInformationEntry::new(encoding, 1, -8, Register(16));
+        cie.add_instruction(CallFrameInstruction::Cfa(Register(7), 8));
+        cie.add_instruction(CallFrameInstruction::Offset(Register(16), -8));
+        let mut table = FrameTable::default();
+        let cie_id = table.add_cie(cie);
+        for index in 0..2 {
+            let mut fde = FrameDescriptionEntry::new(
+                Address::Symbol {
+                    symbol: WriterRelocate::FUNCTION_SYMBOL,
+                    addend: index,
+                },
+                16,
+            );
+            fde.add_instruction(1, CallFrameInstruction::CfaOffset(16));
+            fde.add_instruction(1, CallFrameInstruction::Offset(Register(6), -16));
+            fde.add_instruction(4, CallFrameInstruction::CfaRegister(Register(6)));
+            table.add_fde(cie_id, fde);
+        }
+        let mut eh_frame = EhFrame(WriterRelocate::new(Some(Endianness::Little)));
+        table.write_eh_frame(&mut eh_frame).unwrap();
+
+        let layouts = frame_layouts(&eh_frame.0.into_section(), RunTimeEndian::Little).unwrap();
+        assert_eq!(layouts.assert_complete(2), Ok(()));
+        let expected = FrameLayout::new(vec![
+            (
+                0,
+                FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 8 },
+            ),
+            (0, FrameLayoutChange::ReturnAddressAt { cfa_offset: -8 }),
+            (
+                1,
+                FrameLayoutChange::CallFrameAddressAt { reg: 7, offset: 16 },
+            ),
+            (
+                1,
+                FrameLayoutChange::RegAt {
+                    reg: 6,
+                    cfa_offset: -16,
+                },
+            ),
+            (
+                4,
+                FrameLayoutChange::CallFrameAddressAt { reg: 6, offset: 16 },
+            ),
+        ]);
+        for index in 0..2 {
+            assert_eq!(layouts.get(LocalFunctionIndex::new(index)), Some(&expected));
+        }
+    }
+}
@@ -109,6 +109,14 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The external function signature for implementing reference decrement for `extern.ref`.
     externref_dec_sig: Option<ir::SigRef>,
+
+    /// The external function signature for trapping on an indirect call to
+    /// a null table element.
+    table_null_element_trap_sig: Option<ir::SigRef>,
+
+    /// The external function signature for trapping on an indirect call
+    /// whose signature doesn't match the one of the callee.
+    signature_mismatch_trap_sig: Option<ir::SigRef>,
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -150,6 +158,8 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             table_fill_sig: None,
             externref_inc_sig: None,
             externref_dec_sig: None,
+            table_null_element_trap_sig: None,
+            signature_mismatch_trap_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -202,6 +212,64 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         )
     }
 
+    fn get_table_null_element_trap_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_null_element_trap_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // table index
+                    AbiParam::new(I32),
+                    // element index
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_null_element_trap_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_null_element_trap_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        (
+            self.get_table_null_element_trap_sig(func),
+            table_index.index(),
+            VMBuiltinFunctionIndex::get_table_null_element_trap_index(),
+        )
+    }
+
+    fn get_signature_mismatch_trap_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.signature_mismatch_trap_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // actual shared signature index
+                    AbiParam::new(I32),
+                    // expected signature index
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.signature_mismatch_trap_sig = Some(sig);
+        sig
+    }
+
+    fn get_signature_mismatch_trap_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        (
+            self.get_signature_mismatch_trap_sig(func),
+            VMBuiltinFunctionIndex::get_signature_mismatch_trap_index(),
+        )
+    }
+
     fn get_externref_inc_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.externref_inc_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -1138,7 +1206,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_call_indirect(
         &mut self,
-        mut pos: FuncCursor<'_>,
+        builder: &mut FunctionBuilder,
         table_index: TableIndex,
         table: ir::Table,
         sig_index: SignatureIndex,
@@ -1148,22 +1216,44 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
     ) -> WasmResult<ir::Inst> {
         let pointer_type = self.pointer_type();
 
-        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
+        let table_entry_addr = builder.ins().table_addr(pointer_type, table, callee, 0);
 
         // Dereference table_entry_addr to get the function address.
         let mem_flags = ir::MemFlags::trusted();
-        let table_entry_addr = pos.ins().load(
+        let table_entry_addr = builder.ins().load(
             pointer_type,
             mem_flags,
             table_entry_addr,
             i32::from(self.offsets.vm_funcref_anyfunc_ptr()),
         );
 
-        // check if the funcref is null
-        pos.ins()
-            .trapz(table_entry_addr, ir::TrapCode::IndirectCallToNull);
+        // Trap if the funcref is null, with a builtin telling apart the
+        // elements that were never initialized.
+        let null_block = builder.create_block();
+        let not_null_block = builder.create_block();
+        builder.ins().brz(table_entry_addr, null_block, &[]);
+        builder.ins().jump(not_null_block, &[]);
+        builder.seal_block(null_block);
+        builder.seal_block(not_null_block);
+
+        builder.switch_to_block(null_block);
+        builder.set_cold_block(null_block);
+        {
+            let mut pos = builder.cursor();
+            let (func_sig, table_index_arg, func_idx) =
+                self.get_table_null_element_trap_func(&mut pos.func, table_index);
+            let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+            let (vmctx, func_addr) =
+                self.translate_load_builtin_function_address(&mut pos, func_idx);
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, table_index_arg, callee]);
+            // The builtin doesn't return.
+            pos.ins().trap(ir::TrapCode::IndirectCallToNull);
+        }
 
-        let func_addr = pos.ins().load(
+        builder.switch_to_block(not_null_block);
+
+        let func_addr = builder.ins().load(
             pointer_type,
             mem_flags,
             table_entry_addr,
@@ -1175,35 +1265,59 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             TableStyle::CallerChecksSignature => {
                 let sig_id_size = self.offsets.size_of_vmshared_signature_index();
                 let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
-                let vmctx = self.vmctx(pos.func);
-                let base = pos.ins().global_value(pointer_type, vmctx);
+                let vmctx = self.vmctx(builder.func);
+                let base = builder.ins().global_value(pointer_type, vmctx);
                 let offset =
                     i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
 
                 // Load the caller ID.
                 let mut mem_flags = ir::MemFlags::trusted();
                 mem_flags.set_readonly();
-                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
+                let caller_sig_id = builder.ins().load(sig_id_type, mem_flags, base, offset);
 
                 // Load the callee ID.
                 let mem_flags = ir::MemFlags::trusted();
-                let callee_sig_id = pos.ins().load(
+                let callee_sig_id = builder.ins().load(
                     sig_id_type,
                     mem_flags,
                     table_entry_addr,
                     i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
                 );
 
-                // Check that they match.
-                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
-                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
+                // Check that they match, with a builtin telling the signature
+                // of the callee if they don't.
+                let cmp = builder
+                    .ins()
+                    .icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
+                let mismatch_block = builder.create_block();
+                let match_block = builder.create_block();
+                builder.ins().brz(cmp, mismatch_block, &[]);
+                builder.ins().jump(match_block, &[]);
+                builder.seal_block(mismatch_block);
+                builder.seal_block(match_block);
+
+                builder.switch_to_block(mismatch_block);
+                builder.set_cold_block(mismatch_block);
+                {
+                    let mut pos = builder.cursor();
+                    let (func_sig, func_idx) = self.get_signature_mismatch_trap_func(&mut pos.func);
+                    let expected = pos.ins().iconst(I32, sig_index.as_u32() as i64);
+                    let (vmctx, func_addr) =
+                        self.translate_load_builtin_function_address(&mut pos, func_idx);
+                    pos.ins()
+                        .call_indirect(func_sig, func_addr, &[vmctx, callee_sig_id, expected]);
+                    // The builtin doesn't return.
+                    pos.ins().trap(ir::TrapCode::BadSignature);
+                }
+
+                builder.switch_to_block(match_block);
             }
         }
 
         let mut real_call_args = Vec::with_capacity(call_args.len() + 2);
 
         // First append the callee vmctx address.
-        let vmctx = pos.ins().load(
+        let vmctx = builder.ins().load(
             pointer_type,
             mem_flags,
             table_entry_addr,
@@ -1214,7 +1328,9 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         // Then append the regular call arguments.
         real_call_args.extend_from_slice(call_args);
 
-        Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
+        Ok(builder
+            .ins()
+            .call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
     fn translate_call(
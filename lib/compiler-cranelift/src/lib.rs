@@ -55,7 +55,15 @@ mod translator;
 
 pub use crate::compiler::CraneliftCompiler;
 pub use crate::config::{Cranelift, CraneliftOptLevel};
-pub use crate::debug::{ModuleInfoMemoryOffset, ModuleInfoVmctxInfo, ValueLabelsRanges};
+#[cfg(feature = "std")]
+pub use crate::debug::write_jitdump;
+pub use crate::debug::{
+    pc_to_source_line, unwind_with_layouts, CanonicalChange, CanonicalRegister, DwarfLineProgram,
+    FrameLayout, FrameLayoutChange, FrameLayoutDiff, FrameLayoutError, FrameLayoutId,
+    FrameLayoutInterner, FrameLayoutStats, FrameLayouts, FrameLayoutsBuilder, FrameRegister,
+    FrameSectionError, FrameSectionWarning, ModuleInfoMemoryOffset, ModuleInfoVmctxInfo,
+    ValueLabelsRanges, WasmBacktrace, FRAME_LAYOUTS_SECTION_NAME,
+};
 pub use crate::trampoline::make_trampoline_function_call;
 
 /// Version number of this crate.
@@ -686,7 +686,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let sig_idx = SignatureIndex::from_u32(*index);
 
             let call = environ.translate_call_indirect(
-                builder.cursor(),
+                builder,
                 TableIndex::from_u32(*table_index),
                 table,
                 sig_idx,
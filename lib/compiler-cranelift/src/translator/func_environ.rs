@@ -158,9 +158,9 @@ pub trait FuncEnvironment: TargetEnvironment {
         index: FunctionIndex,
     ) -> WasmResult<ir::FuncRef>;
 
-    /// Translate a `call_indirect` WebAssembly instruction at `pos`.
+    /// Translate a `call_indirect` WebAssembly instruction with `builder`.
     ///
-    /// Insert instructions at `pos` for an indirect call to the function `callee` in the table
+    /// Insert instructions with `builder` for an indirect call to the function `callee` in the table
     /// `table_index` with WebAssembly signature `sig_index`. The `callee` value will have type
     /// `i32`.
     ///
@@ -170,7 +170,7 @@ pub trait FuncEnvironment: TargetEnvironment {
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
     fn translate_call_indirect(
         &mut self,
-        pos: FuncCursor,
+        builder: &mut FunctionBuilder,
         table_index: TableIndex,
         table: ir::Table,
         sig_index: SignatureIndex,
@@ -2348,7 +2348,8 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     .build_load(funcref_ptr, "anyfunc_struct_ptr")
                     .into_pointer_value();
 
-                // trap if we're trying to call a null funcref
+                // trap if we're trying to call a null funcref, with a builtin
+                // telling apart the elements that were never initialized
                 {
                     let funcref_not_null = self
                         .builder
@@ -2368,8 +2369,15 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     );
                     self.builder.position_at_end(funcref_is_null_block);
                     self.builder.build_call(
-                        self.intrinsics.throw_trap,
-                        &[self.intrinsics.trap_call_indirect_null.into()],
+                        self.intrinsics.table_null_element_trap,
+                        &[
+                            self.ctx.basic().into(),
+                            self.intrinsics
+                                .i32_ty
+                                .const_int(table_index as u64, false)
+                                .into(),
+                            func_index.into(),
+                        ],
                         "throw",
                     );
                     self.builder.build_unreachable();
@@ -2449,14 +2457,35 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 );
 
                 self.builder.position_at_end(sigindices_notequal_block);
-                let trap_code = self.builder.build_select(
+                let elem_uninitialized_block = self
+                    .context
+                    .append_basic_block(self.function, "elem_uninitialized_block");
+                let sigindices_mismatch_block = self
+                    .context
+                    .append_basic_block(self.function, "sigindices_mismatch_block");
+                self.builder.build_conditional_branch(
                     elem_initialized,
-                    self.intrinsics.trap_call_indirect_sig,
-                    self.intrinsics.trap_call_indirect_null,
-                    "",
+                    sigindices_mismatch_block,
+                    elem_uninitialized_block,
+                );
+                self.builder.position_at_end(elem_uninitialized_block);
+                self.builder.build_call(
+                    self.intrinsics.throw_trap,
+                    &[self.intrinsics.trap_call_indirect_null.into()],
+                    "throw",
+                );
+                self.builder.build_unreachable();
+                // Tell the signature of the callee with a builtin.
+                self.builder.position_at_end(sigindices_mismatch_block);
+                self.builder.build_call(
+                    self.intrinsics.signature_mismatch_trap,
+                    &[
+                        self.ctx.basic().into(),
+                        found_dynamic_sigindex.into(),
+                        self.intrinsics.i32_ty.const_int(index as u64, false).into(),
+                    ],
+                    "throw",
                 );
-                self.builder
-                    .build_call(self.intrinsics.throw_trap, &[trap_code.into()], "throw");
                 self.builder.build_unreachable();
                 self.builder.position_at_end(continue_block);
 
@@ -243,6 +243,8 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory_fill: FunctionValue<'ctx>,
 
     pub throw_trap: FunctionValue<'ctx>,
+    pub table_null_element_trap: FunctionValue<'ctx>,
+    pub signature_mismatch_trap: FunctionValue<'ctx>,
 
     // VM builtins.
     pub vmfunction_import_ptr_ty: PointerType<'ctx>,
@@ -1008,6 +1010,22 @@ impl<'ctx> Intrinsics<'ctx> {
                 void_ty.fn_type(&[i32_ty_basic_md], false),
                 None,
             ),
+            table_null_element_trap: module.add_function(
+                "wasmer_vm_table_null_element_trap",
+                void_ty.fn_type(
+                    &[ctx_ptr_ty_basic_md, i32_ty_basic_md, i32_ty_basic_md],
+                    false,
+                ),
+                None,
+            ),
+            signature_mismatch_trap: module.add_function(
+                "wasmer_vm_signature_mismatch_trap",
+                void_ty.fn_type(
+                    &[ctx_ptr_ty_basic_md, i32_ty_basic_md, i32_ty_basic_md],
+                    false,
+                ),
+                None,
+            ),
 
             vmfunction_import_ptr_ty: context
                 .struct_type(&[i8_ptr_ty_basic, i8_ptr_ty_basic], false)
@@ -1048,6 +1066,12 @@ impl<'ctx> Intrinsics<'ctx> {
         intrinsics
             .throw_trap
             .add_attribute(AttributeLoc::Function, noreturn);
+        intrinsics
+            .table_null_element_trap
+            .add_attribute(AttributeLoc::Function, noreturn);
+        intrinsics
+            .signature_mismatch_trap
+            .add_attribute(AttributeLoc::Function, noreturn);
         intrinsics
             .func_ref
             .add_attribute(AttributeLoc::Function, intrinsics.readonly);
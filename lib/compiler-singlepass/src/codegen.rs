@@ -94,6 +94,7 @@ struct SpecialLabelSet {
     integer_division_by_zero: Label,
     integer_overflow: Label,
     heap_access_oob: Label,
+    atomic_access_oob: Label,
     table_access_oob: Label,
     indirect_call_null: Label,
     bad_signature: Label,
@@ -963,6 +964,22 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
     /// Emits a memory operation.
     fn op_memory<F: FnOnce(&mut Self, bool, bool, i32, Label)>(&mut self, cb: F) {
+        let heap_access_oob = self.special_labels.heap_access_oob;
+        self.op_memory_with_oob_label(heap_access_oob, cb);
+    }
+
+    /// Emits an atomic memory operation. Out-of-bounds accesses trap with
+    /// `AtomicAccessOutOfBounds`.
+    fn op_atomic_memory<F: FnOnce(&mut Self, bool, bool, i32, Label)>(&mut self, cb: F) {
+        let atomic_access_oob = self.special_labels.atomic_access_oob;
+        self.op_memory_with_oob_label(atomic_access_oob, cb);
+    }
+
+    fn op_memory_with_oob_label<F: FnOnce(&mut Self, bool, bool, i32, Label)>(
+        &mut self,
+        oob_label: Label,
+        cb: F,
+    ) {
         let need_check = match self.memory_styles[MemoryIndex::new(0)] {
             MemoryStyle::Static { .. } => false,
             MemoryStyle::Dynamic { .. } => true,
@@ -980,7 +997,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             need_check,
             self.module.num_imported_memories != 0,
             offset as i32,
-            self.special_labels.heap_access_oob,
+            oob_label,
         );
     }
 
@@ -1077,6 +1094,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             integer_division_by_zero: machine.get_label(),
             integer_overflow: machine.get_label(),
             heap_access_oob: machine.get_label(),
+            atomic_access_oob: machine.get_label(),
             table_access_oob: machine.get_label(),
             indirect_call_null: machine.get_label(),
             bad_signature: machine.get_label(),
@@ -2762,14 +2780,47 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Location::Memory(table_count, self.vmoffsets.vm_funcref_anyfunc_ptr() as i32),
                     Location::GPR(table_count),
                 );
-                // Trap if the FuncRef is null
+                // Trap if the FuncRef is null, with a builtin telling apart the
+                // elements that were never initialized.
+                let not_null = self.machine.get_label();
                 self.machine.location_cmp(
                     Size::S64,
                     Location::Imm32(0),
                     Location::GPR(table_count),
                 );
+                self.machine.jmp_on_different(not_null);
+                // The temporary registers are dead on this path, as the builtin
+                // doesn't return.
+                self.machine.release_gpr(sigidx);
+                self.machine.release_gpr(table_count);
+                self.machine.release_gpr(table_base);
+                self.machine.move_location(
+                    Size::S64,
+                    Location::Memory(
+                        self.machine.get_vmctx_reg(),
+                        self.vmoffsets.vmctx_builtin_function(
+                            VMBuiltinFunctionIndex::get_table_null_element_trap_index(),
+                        ) as i32,
+                    ),
+                    Location::GPR(self.machine.get_grp_for_call()),
+                );
+                self.emit_call_native(
+                    |this| {
+                        this.machine
+                            .emit_call_register(this.machine.get_grp_for_call());
+                    },
+                    // [vmctx, table_index, elem_index] -> !
+                    [Location::Imm32(table_index.index() as u32), func_index]
+                        .iter()
+                        .cloned(),
+                    [WpType::I32, WpType::I32].iter().cloned(),
+                )?;
                 self.machine
-                    .jmp_on_equal(self.special_labels.indirect_call_null);
+                    .jmp_unconditionnal(self.special_labels.indirect_call_null);
+                self.machine.emit_label(not_null);
+                self.machine.reserve_unused_temp_gpr(table_base);
+                self.machine.reserve_unused_temp_gpr(table_count);
+                self.machine.reserve_unused_temp_gpr(sigidx);
                 self.machine.move_location(
                     Size::S64,
                     Location::Memory(
@@ -2779,17 +2830,63 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Location::GPR(sigidx),
                 );
 
-                // Trap if signature mismatches.
+                // Trap if signature mismatches, with a builtin telling the
+                // signature of the callee.
+                let vmcaller_checked_anyfunc_type_index = Location::Memory(
+                    table_count,
+                    (self.vmoffsets.vmcaller_checked_anyfunc_type_index() as usize) as i32,
+                );
+                let signature_matches = self.machine.get_label();
                 self.machine.location_cmp(
                     Size::S32,
                     Location::GPR(sigidx),
+                    vmcaller_checked_anyfunc_type_index,
+                );
+                self.machine.jmp_on_equal(signature_matches);
+                self.machine.move_location(
+                    Size::S32,
+                    vmcaller_checked_anyfunc_type_index,
+                    Location::GPR(sigidx),
+                );
+                // The temporary registers are dead on this path, as the builtin
+                // doesn't return. They must be released for `emit_call_native`,
+                // which reserves the register for calls, but the actual
+                // signature can still be passed in `sigidx`: it's never the
+                // register for calls the builtin address is loaded into (RAX
+                // is `table_base` on x64, as the three temporary registers are
+                // taken, and X27 isn't a temporary register on ARM64), and the
+                // register moves of the parameters are sorted so that `sigidx`
+                // is read before being overwritten.
+                self.machine.release_gpr(sigidx);
+                self.machine.release_gpr(table_count);
+                self.machine.release_gpr(table_base);
+                self.machine.move_location(
+                    Size::S64,
                     Location::Memory(
-                        table_count,
-                        (self.vmoffsets.vmcaller_checked_anyfunc_type_index() as usize) as i32,
+                        self.machine.get_vmctx_reg(),
+                        self.vmoffsets.vmctx_builtin_function(
+                            VMBuiltinFunctionIndex::get_signature_mismatch_trap_index(),
+                        ) as i32,
                     ),
+                    Location::GPR(self.machine.get_grp_for_call()),
                 );
+                self.emit_call_native(
+                    |this| {
+                        this.machine
+                            .emit_call_register(this.machine.get_grp_for_call());
+                    },
+                    // [vmctx, actual, expected] -> !
+                    [Location::GPR(sigidx), Location::Imm32(index.as_u32())]
+                        .iter()
+                        .cloned(),
+                    [WpType::I32, WpType::I32].iter().cloned(),
+                )?;
                 self.machine
-                    .jmp_on_different(self.special_labels.bad_signature);
+                    .jmp_unconditionnal(self.special_labels.bad_signature);
+                self.machine.emit_label(signature_matches);
+                self.machine.reserve_unused_temp_gpr(table_base);
+                self.machine.reserve_unused_temp_gpr(table_count);
+                self.machine.reserve_unused_temp_gpr(sigidx);
 
                 self.machine.release_gpr(sigidx);
                 self.machine.release_gpr(table_count);
@@ -4064,7 +4161,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load(
                             target,
@@ -4085,7 +4182,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_8u(
                             target,
@@ -4106,7 +4203,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_load_16u(
                             target,
@@ -4123,7 +4220,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I32AtomicStore { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save(
                             target_value,
@@ -4140,7 +4237,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I32AtomicStore8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_8(
                             target_value,
@@ -4157,7 +4254,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I32AtomicStore16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_save_16(
                             target_value,
@@ -4178,7 +4275,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load(
                             target,
@@ -4199,7 +4296,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_8u(
                             target,
@@ -4220,7 +4317,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_16u(
                             target,
@@ -4241,7 +4338,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_load_32u(
                             target,
@@ -4258,7 +4355,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I64AtomicStore { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save(
                             target_value,
@@ -4275,7 +4372,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I64AtomicStore8 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_8(
                             target_value,
@@ -4292,7 +4389,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I64AtomicStore16 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_16(
                             target_value,
@@ -4309,7 +4406,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             Operator::I64AtomicStore32 { ref memarg } => {
                 let target_value = self.pop_value_released();
                 let target_addr = self.pop_value_released();
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_save_32(
                             target_value,
@@ -4331,7 +4428,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add(
                             loc,
@@ -4354,7 +4451,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add(
                             loc,
@@ -4377,7 +4474,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_8u(
                             loc,
@@ -4400,7 +4497,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_add_16u(
                             loc,
@@ -4423,7 +4520,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_8u(
                             loc,
@@ -4446,7 +4543,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_16u(
                             loc,
@@ -4469,7 +4566,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_add_32u(
                             loc,
@@ -4492,7 +4589,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub(
                             loc,
@@ -4515,7 +4612,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub(
                             loc,
@@ -4538,7 +4635,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_8u(
                             loc,
@@ -4561,7 +4658,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_sub_16u(
                             loc,
@@ -4584,7 +4681,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_8u(
                             loc,
@@ -4607,7 +4704,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_16u(
                             loc,
@@ -4630,7 +4727,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_sub_32u(
                             loc,
@@ -4653,7 +4750,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and(
                             loc,
@@ -4676,7 +4773,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and(
                             loc,
@@ -4699,7 +4796,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_8u(
                             loc,
@@ -4722,7 +4819,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_and_16u(
                             loc,
@@ -4745,7 +4842,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_8u(
                             loc,
@@ -4768,7 +4865,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_16u(
                             loc,
@@ -4791,7 +4888,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_and_32u(
                             loc,
@@ -4814,7 +4911,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or(
                             loc,
@@ -4837,7 +4934,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or(
                             loc,
@@ -4860,7 +4957,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_8u(
                             loc,
@@ -4883,7 +4980,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_or_16u(
                             loc,
@@ -4906,7 +5003,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_8u(
                             loc,
@@ -4929,7 +5026,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_16u(
                             loc,
@@ -4952,7 +5049,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_or_32u(
                             loc,
@@ -4975,7 +5072,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor(
                             loc,
@@ -4998,7 +5095,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor(
                             loc,
@@ -5021,7 +5118,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_8u(
                             loc,
@@ -5044,7 +5141,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xor_16u(
                             loc,
@@ -5067,7 +5164,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_8u(
                             loc,
@@ -5090,7 +5187,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_16u(
                             loc,
@@ -5113,7 +5210,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xor_32u(
                             loc,
@@ -5136,7 +5233,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg(
                             loc,
@@ -5159,7 +5256,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg(
                             loc,
@@ -5182,7 +5279,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_8u(
                             loc,
@@ -5205,7 +5302,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_xchg_16u(
                             loc,
@@ -5228,7 +5325,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_8u(
                             loc,
@@ -5251,7 +5348,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_16u(
                             loc,
@@ -5274,7 +5371,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_xchg_32u(
                             loc,
@@ -5298,7 +5395,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg(
                             new,
@@ -5323,7 +5420,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg(
                             new,
@@ -5348,7 +5445,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_8u(
                             new,
@@ -5373,7 +5470,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i32_atomic_cmpxchg_16u(
                             new,
@@ -5398,7 +5495,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_8u(
                             new,
@@ -5423,7 +5520,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_16u(
                             new,
@@ -5448,7 +5545,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     false,
                 )[0];
                 self.value_stack.push(ret);
-                self.op_memory(
+                self.op_atomic_memory(
                     |this, need_check, imported_memories, offset, heap_access_oob| {
                         this.machine.i64_atomic_cmpxchg_32u(
                             new,
@@ -5878,6 +5975,12 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             .mark_address_with_trap_code(TrapCode::HeapAccessOutOfBounds);
         self.machine.emit_illegal_op();
 
+        self.machine
+            .emit_label(self.special_labels.atomic_access_oob);
+        self.machine
+            .mark_address_with_trap_code(TrapCode::AtomicAccessOutOfBounds);
+        self.machine.emit_illegal_op();
+
         self.machine
             .emit_label(self.special_labels.table_access_oob);
         self.machine
@@ -991,7 +991,13 @@ impl MachineARM64 {
         let begin = self.assembler.get_offset().0;
         cb(self, tmp_addr);
         let end = self.assembler.get_offset().0;
-        self.mark_address_range_with_trap_code(TrapCode::HeapAccessOutOfBounds, begin, end);
+        // Only atomic accesses check their alignment.
+        let trap_code = if check_alignment {
+            TrapCode::AtomicAccessOutOfBounds
+        } else {
+            TrapCode::HeapAccessOutOfBounds
+        };
+        self.mark_address_range_with_trap_code(trap_code, begin, end);
 
         self.release_gpr(tmp_addr);
     }
@@ -1,9 +1,9 @@
 use super::frame_info::{FrameInfo, GlobalFrameInfo, FRAME_INFO};
 use backtrace::Backtrace;
 use std::error::Error;
-use std::fmt;
+use std::fmt::{self, Write};
 use std::sync::Arc;
-use wasmer_vm::{raise_user_trap, Trap, TrapCode};
+use wasmer_vm::{raise_user_trap, ResourceKind, SignatureMismatch, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
@@ -19,6 +19,7 @@ enum RuntimeErrorSource {
     OOM,
     User(Box<dyn Error + Send + Sync>),
     Trap(TrapCode),
+    ResourceExhausted(ResourceKind),
 }
 
 impl fmt::Display for RuntimeErrorSource {
@@ -28,6 +29,7 @@ impl fmt::Display for RuntimeErrorSource {
             Self::User(s) => write!(f, "{}", s),
             Self::OOM => write!(f, "Wasmer VM out of memory"),
             Self::Trap(s) => write!(f, "{}", s.message()),
+            Self::ResourceExhausted(kind) => write!(f, "{}", kind.message()),
         }
     }
 }
@@ -39,6 +41,12 @@ struct RuntimeErrorInner {
     wasm_trace: Vec<FrameInfo>,
     /// The native backtrace
     native_trace: Backtrace,
+    /// The name of the export whose call led to this error, if known
+    invoked_export: Option<String>,
+    /// The signatures of the failed indirect call, if known
+    signature_mismatch: Option<SignatureMismatch>,
+    /// The index of the accessed memory, if the module has more than one
+    memory_index: Option<u32>,
 }
 
 fn _assert_trap_is_sync_and_send(t: &Trap) -> (&dyn Sync, &dyn Send) {
@@ -67,9 +75,18 @@ impl RuntimeError {
     /// Create a new RuntimeError from a Trap.
     pub fn from_trap(trap: Trap) -> Self {
         let info = FRAME_INFO.read().unwrap();
+        if trap.is_redacted() {
+            // Nothing of a redacted trap but its message may be shown.
+            return Self::new_with_trace(
+                &info,
+                None,
+                RuntimeErrorSource::Generic(trap.to_string()),
+                Backtrace::from(Vec::new()),
+            );
+        }
         match trap {
             // A user error
-            Trap::User(error) => {
+            Trap::User { error, .. } => {
                 match error.downcast::<Self>() {
                     // The error is already a RuntimeError, we return it directly
                     Ok(runtime_error) => *runtime_error,
@@ -82,14 +99,24 @@ impl RuntimeError {
                 }
             }
             // A trap caused by the VM being Out of Memory
-            Trap::OOM { backtrace } => {
+            Trap::OOM { backtrace, .. } => {
                 Self::new_with_trace(&info, None, RuntimeErrorSource::OOM, backtrace)
             }
+            // A trap raised by the host for an exceeded resource limit
+            Trap::ResourceExhausted { kind, .. } => Self::new_with_trace(
+                &info,
+                None,
+                RuntimeErrorSource::ResourceExhausted(kind),
+                Backtrace::new_unresolved(),
+            ),
             // A trap caused by an error on the generated machine code for a Wasm function
             Trap::Wasm {
                 pc,
                 signal_trap,
                 backtrace,
+                memory_index,
+                invoked_export,
+                ..
             } => {
                 let code = info
                     .lookup_trap_info(pc)
@@ -97,12 +124,38 @@ impl RuntimeError {
                         info.trap_code
                     });
                 Self::new_with_trace(&info, Some(pc), RuntimeErrorSource::Trap(code), backtrace)
+                    .with_invoked_export(invoked_export)
+                    .with_memory_index(memory_index)
             }
             // A trap triggered manually from the Wasmer runtime
             Trap::Lib {
-                trap_code,
+                trap_code: Some(trap_code),
                 backtrace,
-            } => Self::new_with_trace(&info, None, RuntimeErrorSource::Trap(trap_code), backtrace),
+                memory_index,
+                signature_mismatch,
+                invoked_export,
+                ..
+            } => {
+                let mut error = Self::new_with_trace(
+                    &info,
+                    None,
+                    RuntimeErrorSource::Trap(trap_code),
+                    backtrace,
+                )
+                .with_invoked_export(invoked_export)
+                .with_memory_index(memory_index);
+                Arc::get_mut(&mut error.inner)
+                    .expect("a new error isn't shared")
+                    .signature_mismatch = signature_mismatch;
+                error
+            }
+            // Only redacted `Lib` traps have no trap code.
+            Trap::Lib { backtrace, .. } => Self::new_with_trace(
+                &info,
+                None,
+                RuntimeErrorSource::Generic("lib trap".to_string()),
+                backtrace,
+            ),
         }
     }
 
@@ -173,13 +226,42 @@ impl RuntimeError {
                 source,
                 wasm_trace,
                 native_trace,
+                invoked_export: None,
+                signature_mismatch: None,
+                memory_index: None,
             }),
         }
     }
 
+    fn with_invoked_export(mut self, invoked_export: Option<String>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("a new error isn't shared")
+            .invoked_export = invoked_export;
+        self
+    }
+
+    fn with_memory_index(mut self, memory_index: Option<u32>) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("a new error isn't shared")
+            .memory_index = memory_index;
+        self
+    }
+
     /// Returns a reference the `message` stored in `Trap`.
     pub fn message(&self) -> String {
-        self.inner.source.to_string()
+        let mut message = self.inner.source.to_string();
+        if let Some(SignatureMismatch { expected, actual }) = self.inner.signature_mismatch {
+            write!(
+                message,
+                ": expected type #{}, got type #{}",
+                expected, actual
+            )
+            .unwrap();
+        }
+        if let Some(memory_index) = self.inner.memory_index {
+            write!(message, " on memory #{}", memory_index).unwrap();
+        }
+        message
     }
 
     /// Returns a list of function frames in WebAssembly code that led to this
@@ -188,6 +270,20 @@ impl RuntimeError {
         &self.inner.wasm_trace
     }
 
+    /// Returns the name of the export whose call led to this trap, if known.
+    ///
+    /// Only the traps of the calls of the exports of an instance through
+    /// `Function::call` know it.
+    pub fn invoked_export(&self) -> Option<&str> {
+        self.inner.invoked_export.as_deref()
+    }
+
+    /// Returns the index of the memory whose access led to this trap, if the
+    /// module has more than one.
+    pub fn memory_index(&self) -> Option<u32> {
+        self.inner.memory_index
+    }
+
     /// Attempts to downcast the `RuntimeError` to a concrete type.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
@@ -227,6 +323,9 @@ impl fmt::Debug for RuntimeError {
             .field("source", &self.inner.source)
             .field("wasm_trace", &self.inner.wasm_trace)
             .field("native_trace", &self.inner.native_trace)
+            .field("invoked_export", &self.inner.invoked_export)
+            .field("signature_mismatch", &self.inner.signature_mismatch)
+            .field("memory_index", &self.inner.memory_index)
             .finish()
     }
 }
@@ -277,3 +376,21 @@ impl From<Trap> for RuntimeError {
         Self::from_trap(trap)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_index() {
+        let error = RuntimeError::from_trap(
+            Trap::lib(TrapCode::HeapAccessOutOfBounds).with_memory_index(2),
+        );
+        assert_eq!(error.memory_index(), Some(2));
+        assert_eq!(error.message(), "out of bounds memory access on memory #2");
+
+        let error = RuntimeError::from_trap(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        assert_eq!(error.memory_index(), None);
+        assert_eq!(error.message(), "out of bounds memory access");
+    }
+}
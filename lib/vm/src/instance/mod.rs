@@ -19,7 +19,7 @@ use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
 use crate::table::{Table, TableElement};
-use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
+use crate::trap::{catch_traps, enter_wasm, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport,
@@ -426,6 +426,7 @@ impl Instance {
         };
 
         // Make the call.
+        let _wasm_pcs = enter_wasm(callee_address as usize);
         unsafe {
             catch_traps(trap_handler, || {
                 mem::transmute::<*const VMFunctionBody, unsafe extern "C" fn(VMFunctionEnvironment)>(
@@ -743,6 +744,7 @@ impl Instance {
         let memory = self.memory(memory_index);
         // The following memory copy is not synchronized and is not atomic:
         unsafe { memory.memory_copy(dst, src, len) }
+            .map_err(|trap| self.with_memory_index(trap, self.module.memory_index(memory_index)))
     }
 
     /// Perform a `memory.copy` on an imported memory.
@@ -757,6 +759,7 @@ impl Instance {
         let memory = unsafe { import.definition.as_ref() };
         // The following memory copy is not synchronized and is not atomic:
         unsafe { memory.memory_copy(dst, src, len) }
+            .map_err(|trap| self.with_memory_index(trap, memory_index))
     }
 
     /// Perform the `memory.fill` operation on a locally defined memory.
@@ -774,6 +777,7 @@ impl Instance {
         let memory = self.memory(memory_index);
         // The following memory fill is not synchronized and is not atomic:
         unsafe { memory.memory_fill(dst, val, len) }
+            .map_err(|trap| self.with_memory_index(trap, self.module.memory_index(memory_index)))
     }
 
     /// Perform the `memory.fill` operation on an imported memory.
@@ -792,6 +796,7 @@ impl Instance {
         let memory = unsafe { import.definition.as_ref() };
         // The following memory fill is not synchronized and is not atomic:
         unsafe { memory.memory_fill(dst, val, len) }
+            .map_err(|trap| self.with_memory_index(trap, memory_index))
     }
 
     /// Performs the `memory.init` operation.
@@ -822,7 +827,9 @@ impl Instance {
                 usize::try_from(m).unwrap() > memory.current_length
             })
         {
-            return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
+            return Err(
+                self.with_memory_index(Trap::lib(TrapCode::HeapAccessOutOfBounds), memory_index)
+            );
         }
 
         let src_slice = &data[src as usize..(src + len) as usize];
@@ -836,6 +843,16 @@ impl Instance {
         Ok(())
     }
 
+    /// Attaches `memory_index` to a trap raised while accessing that memory,
+    /// when the module has more than one memory.
+    fn with_memory_index(&self, trap: Trap, memory_index: MemoryIndex) -> Trap {
+        if self.module.memories.len() > 1 {
+            trap.with_memory_index(memory_index.as_u32())
+        } else {
+            trap
+        }
+    }
+
     /// Drop the given data segment, truncating its length to zero.
     pub(crate) fn data_drop(&self, data_index: DataIndex) {
         let mut passive_data = self.passive_data.borrow_mut();
@@ -852,6 +869,21 @@ impl Instance {
         }
     }
 
+    /// Get the index in the module of the signature registered as
+    /// `signature_id`, if the module declares it.
+    pub(crate) fn signature_index(
+        &self,
+        signature_id: VMSharedSignatureIndex,
+    ) -> Option<SignatureIndex> {
+        let signature_ids = unsafe {
+            slice::from_raw_parts(self.signature_ids_ptr(), self.module.signatures.len())
+        };
+        signature_ids
+            .iter()
+            .position(|&id| id == signature_id)
+            .map(SignatureIndex::new)
+    }
+
     /// Get a locally-defined table.
     pub(crate) fn get_local_table(&self, index: LocalTableIndex) -> &dyn Table {
         self.tables[index].as_ref()
@@ -41,7 +41,7 @@ use crate::func_data_registry::VMFuncRef;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
-use crate::vmcontext::VMContext;
+use crate::vmcontext::{VMContext, VMSharedSignatureIndex};
 use crate::{on_host_stack, VMExternRef};
 use enum_iterator::IntoEnumIterator;
 use loupe::MemoryUsage;
@@ -685,6 +685,60 @@ pub unsafe extern "C" fn wasmer_vm_raise_trap(trap_code: TrapCode) -> ! {
     raise_lib_trap(trap)
 }
 
+/// Implementation for raising the trap of an indirect call to the null
+/// element `elem_index` of a table: `TrapCode::UninitializedElement` if the
+/// element was never written, `TrapCode::IndirectCallToNull` otherwise.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and it's only safe to call when wasm
+/// code is on the stack, aka `wasmer_call` or `wasmer_call_trampoline` must
+/// have been previously called.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_table_null_element_trap(
+    vmctx: *mut VMContext,
+    table_index: u32,
+    elem_index: u32,
+) -> ! {
+    let trap_code = {
+        let table_index = TableIndex::from_u32(table_index);
+        let instance = (&*vmctx).instance();
+        if instance.get_table(table_index).is_uninitialized(elem_index) {
+            TrapCode::UninitializedElement
+        } else {
+            TrapCode::IndirectCallToNull
+        }
+    };
+    raise_lib_trap(Trap::lib(trap_code))
+}
+
+/// Implementation for raising the trap of an indirect call with the
+/// signature `expected` of the module to a function of the signature
+/// registered as `actual`: `Trap::lib_signature_mismatch` if the module
+/// declares the signature of the callee, a plain `TrapCode::BadSignature`
+/// trap otherwise.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable, and it's only safe to call when wasm
+/// code is on the stack, aka `wasmer_call` or `wasmer_call_trampoline` must
+/// have been previously called.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_signature_mismatch_trap(
+    vmctx: *mut VMContext,
+    actual: u32,
+    expected: u32,
+) -> ! {
+    let trap = {
+        let instance = (&*vmctx).instance();
+        match instance.signature_index(VMSharedSignatureIndex::new(actual)) {
+            Some(actual) => Trap::lib_signature_mismatch(expected, actual.as_u32()),
+            None => Trap::lib(TrapCode::BadSignature),
+        }
+    };
+    raise_lib_trap(trap)
+}
+
 /// Probestack check
 ///
 /// # Safety
@@ -798,6 +852,12 @@ pub enum LibCall {
     /// probe for stack overflow. These are emitted for functions which need
     /// when the `enable_probestack` setting is true.
     Probestack,
+
+    /// The trap of an indirect call to a null table element
+    TableNullElementTrap,
+
+    /// The trap of an indirect call with a mismatched signature
+    SignatureMismatchTrap,
 }
 
 impl LibCall {
@@ -835,6 +895,8 @@ impl LibCall {
             Self::DataDrop => wasmer_vm_data_drop as usize,
             Self::Probestack => wasmer_vm_probestack as usize,
             Self::RaiseTrap => wasmer_vm_raise_trap as usize,
+            Self::TableNullElementTrap => wasmer_vm_table_null_element_trap as usize,
+            Self::SignatureMismatchTrap => wasmer_vm_signature_mismatch_trap as usize,
         }
     }
 
@@ -877,6 +939,8 @@ impl LibCall {
             Self::Probestack => "_wasmer_vm_probestack",
             #[cfg(not(target_vendor = "apple"))]
             Self::Probestack => "wasmer_vm_probestack",
+            Self::TableNullElementTrap => "wasmer_vm_table_null_element_trap",
+            Self::SignatureMismatchTrap => "wasmer_vm_signature_mismatch_trap",
         }
     }
 }
@@ -14,10 +14,11 @@ use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use serde::{Deserialize, Serialize};
 use std::borrow::BorrowMut;
 use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fmt;
 use std::ptr::NonNull;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 use thiserror::Error;
 use wasmer_types::{Bytes, MemoryType, Pages};
 
@@ -182,6 +183,54 @@ struct WasmMmap {
     size: Pages,
 }
 
+lazy_static::lazy_static! {
+    /// The host address ranges reserved by the linear memories alive in the
+    /// process, guard pages included, as `start => len`.
+    static ref MEMORY_RANGES: RwLock<BTreeMap<usize, usize>> = RwLock::new(BTreeMap::new());
+}
+
+/// Translates the host address of a fault to an offset in the linear memory
+/// whose reservation contains it, or `None` if it is outside of every
+/// linear memory.
+pub(crate) fn linear_memory_offset(addr: usize) -> Option<u64> {
+    let ranges = MEMORY_RANGES.read().unwrap();
+    let (&start, &len) = ranges.range(..=addr).next_back()?;
+    if addr - start < len {
+        Some((addr - start) as u64)
+    } else {
+        None
+    }
+}
+
+impl WasmMmap {
+    fn new(alloc: Mmap, size: Pages) -> Self {
+        let mut mmap = Self {
+            alloc: Mmap::new(),
+            size,
+        };
+        mmap.set_alloc(alloc);
+        mmap
+    }
+
+    /// Replaces the allocation, keeping `MEMORY_RANGES` in sync.
+    fn set_alloc(&mut self, alloc: Mmap) {
+        let mut ranges = MEMORY_RANGES.write().unwrap();
+        if !self.alloc.is_empty() {
+            ranges.remove(&(self.alloc.as_ptr() as usize));
+        }
+        if !alloc.is_empty() {
+            ranges.insert(alloc.as_ptr() as usize, alloc.len());
+        }
+        self.alloc = alloc;
+    }
+}
+
+impl Drop for WasmMmap {
+    fn drop(&mut self) {
+        self.set_alloc(Mmap::new());
+    }
+}
+
 impl LinearMemory {
     /// Create a new linear memory instance with specified minimum and maximum number of wasm pages.
     ///
@@ -259,11 +308,11 @@ impl LinearMemory {
         let mapped_pages = memory.minimum;
         let mapped_bytes = mapped_pages.bytes();
 
-        let mut mmap = WasmMmap {
-            alloc: Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
+        let mut mmap = WasmMmap::new(
+            Mmap::accessible_reserved(mapped_bytes.0, request_bytes)
                 .map_err(MemoryError::Region)?,
-            size: memory.minimum,
-        };
+            memory.minimum,
+        );
 
         let base_ptr = mmap.alloc.as_mut_ptr();
         let mem_length = memory.minimum.bytes().0.try_into().unwrap();
@@ -396,7 +445,7 @@ impl Memory for LinearMemory {
             let copy_len = mmap.alloc.len() - self.offset_guard_size;
             new_mmap.as_mut_slice()[..copy_len].copy_from_slice(&mmap.alloc.as_slice()[..copy_len]);
 
-            mmap.alloc = new_mmap;
+            mmap.set_alloc(new_mmap);
         } else if delta_bytes > 0 {
             // Make the newly allocated pages accessible.
             mmap.alloc
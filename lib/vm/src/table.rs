@@ -61,6 +61,17 @@ pub trait Table: fmt::Debug + Send + Sync + MemoryUsage {
     /// Returns an error if the index is out of bounds.
     fn set(&self, index: u32, reference: TableElement) -> Result<(), Trap>;
 
+    /// Returns whether the element at `index` was never written since the
+    /// table was created, by an element segment, `table.set` or any other
+    /// operation storing a value.
+    ///
+    /// Tells apart `TrapCode::UninitializedElement` from
+    /// `TrapCode::IndirectCallToNull` when calling a null element. Tables
+    /// that don't keep track of it always return `false`.
+    fn is_uninitialized(&self, _index: u32) -> bool {
+        false
+    }
+
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition>;
 
@@ -179,6 +190,9 @@ impl Default for TableElement {
 pub struct LinearTable {
     // TODO: we can remove the mutex by using atomic swaps and preallocating the max table size
     vec: Mutex<Vec<RawTableElement>>,
+    /// Whether each element was written since the table was created. Only
+    /// locked while holding the lock of `vec`.
+    initialized: Mutex<Vec<bool>>,
     maximum: Option<u32>,
     /// The WebAssembly table description.
     table: TableType,
@@ -259,6 +273,7 @@ impl LinearTable {
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
                 vec: Mutex::new(vec),
+                initialized: Mutex::new(vec![false; table_minimum]),
                 maximum: table.maximum,
                 table: *table,
                 style: style.clone(),
@@ -350,6 +365,11 @@ impl Table for LinearTable {
         };
 
         vec.resize(usize::try_from(new_len).unwrap(), element);
+        // `table.grow` always comes with the value of the new elements.
+        self.initialized
+            .lock()
+            .unwrap()
+            .resize(usize::try_from(new_len).unwrap(), true);
 
         // update table definition
         unsafe {
@@ -408,6 +428,7 @@ impl Table for LinearTable {
                         )
                     }
                 };
+                self.initialized.lock().unwrap()[index as usize] = true;
 
                 Ok(())
             }
@@ -415,9 +436,38 @@ impl Table for LinearTable {
         }
     }
 
+    /// Returns whether the element at `index` was never written since the
+    /// table was created.
+    fn is_uninitialized(&self, index: u32) -> bool {
+        let _vec_guard = self.vec.lock().unwrap();
+        let initialized = self.initialized.lock().unwrap();
+        initialized.get(index as usize) == Some(&false)
+    }
+
     /// Return a `VMTableDefinition` for exposing the table to compiled wasm code.
     fn vmtable(&self) -> NonNull<VMTableDefinition> {
         let _vec_guard = self.vec.lock().unwrap();
         unsafe { self.get_vm_table_definition() }
     }
 }
+
+#[cfg(test)]
+#[test]
+fn uninitialized_elements_test() {
+    let table = LinearTable::new(
+        &TableType::new(ValType::FuncRef, 2, None),
+        &TableStyle::CallerChecksSignature,
+    )
+    .unwrap();
+    assert!(table.is_uninitialized(0));
+    assert!(table.is_uninitialized(1));
+
+    // Storing a null reference initializes the element all the same.
+    table.set(1, TableElement::default()).unwrap();
+    assert!(table.is_uninitialized(0));
+    assert!(!table.is_uninitialized(1));
+
+    table.grow(1, TableElement::default()).unwrap();
+    assert!(!table.is_uninitialized(2));
+    assert!(!table.is_uninitialized(3));
+}
@@ -0,0 +1,160 @@
+//! A circuit breaker for host functions whose calls keep trapping.
+
+use super::trapcode::TrapCodeSet;
+use super::traphandlers::{Trap, TrapOrigin};
+use std::time::{Duration, Instant};
+
+/// The state of a `CircuitBreaker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BreakerState {
+    /// Calls go through.
+    Closed,
+    /// Calls fail fast, without calling the guarded function.
+    Open,
+    /// The cooldown is over: the next call goes through, as a trial.
+    HalfOpen,
+}
+
+/// Counts the consecutive failed calls of a guarded function, and opens
+/// once `threshold` calls in a row failed, for a `cooldown`.
+///
+/// A call fails if it trapped with one of the counted trap codes, or with
+/// a trap of one of the counted origins, eg. `TrapOrigin::Host` for the
+/// errors of a flaky dependency. Once the cooldown is over, the breaker is
+/// half-open: a failure of the next call opens it again, and a success
+/// closes it.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    codes: TrapCodeSet,
+    origins: Vec<TrapOrigin>,
+    threshold: usize,
+    cooldown: Duration,
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed breaker, opening for `cooldown` after `threshold`
+    /// failed calls in a row. No trap counts as a failure yet.
+    pub fn new(threshold: usize, cooldown: Duration) -> Self {
+        Self {
+            codes: TrapCodeSet::empty(),
+            origins: Vec::new(),
+            threshold,
+            cooldown,
+            consecutive_failures: 0,
+            opened_at: None,
+        }
+    }
+
+    /// Returns this breaker, counting the traps with the trap codes of
+    /// `codes` as failures.
+    pub fn with_codes(mut self, codes: TrapCodeSet) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    /// Returns this breaker, also counting the traps of `origin` as
+    /// failures.
+    pub fn with_origin(mut self, origin: TrapOrigin) -> Self {
+        if !self.origins.contains(&origin) {
+            self.origins.push(origin);
+        }
+        self
+    }
+
+    /// Records a call of the guarded function that trapped with `trap`,
+    /// now.
+    pub fn record(&mut self, trap: &Trap) {
+        self.record_at(trap, Instant::now())
+    }
+
+    /// Records a call of the guarded function that trapped with `trap`, at
+    /// `now`.
+    ///
+    /// A trap that doesn't count as a failure is recorded as a success.
+    pub fn record_at(&mut self, trap: &Trap, now: Instant) {
+        if !self.is_failure(trap) {
+            self.record_success();
+            return;
+        }
+        match self.state_at(now) {
+            BreakerState::Closed => {
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= self.threshold {
+                    self.opened_at = Some(now);
+                }
+            }
+            // The trial call failed.
+            BreakerState::HalfOpen => self.opened_at = Some(now),
+            BreakerState::Open => {}
+        }
+    }
+
+    /// Records a call of the guarded function that succeeded, closing the
+    /// breaker.
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    /// Returns the state of the breaker, now.
+    pub fn state(&self) -> BreakerState {
+        self.state_at(Instant::now())
+    }
+
+    /// Returns the state of the breaker at `now`.
+    pub fn state_at(&self, now: Instant) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if now.saturating_duration_since(opened_at) >= self.cooldown => {
+                BreakerState::HalfOpen
+            }
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    fn is_failure(&self, trap: &Trap) -> bool {
+        self.origins.contains(&trap.origin())
+            || trap
+                .trap_code()
+                .map_or(false, |code| self.codes.contains(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trap::TrapCode;
+
+    #[test]
+    fn open_and_recover() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(10))
+            .with_origin(TrapOrigin::Host)
+            .with_codes(TrapCodeSet::empty().with(TrapCode::StackOverflow));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let failure = || Trap::user_boxed("downstream unavailable".into());
+
+        breaker.record_at(&failure(), at(0));
+        breaker.record_at(&failure(), at(1));
+        // Traps that don't count are successes, and reset the count.
+        breaker.record_at(&Trap::lib(TrapCode::IntegerOverflow), at(2));
+        breaker.record_at(&failure(), at(3));
+        breaker.record_at(&Trap::lib(TrapCode::StackOverflow), at(4));
+        assert_eq!(breaker.state_at(at(4)), BreakerState::Closed);
+        breaker.record_at(&failure(), at(5));
+        assert_eq!(breaker.state_at(at(5)), BreakerState::Open);
+        assert_eq!(breaker.state_at(at(14)), BreakerState::Open);
+
+        // The trial call fails, the breaker opens again.
+        assert_eq!(breaker.state_at(at(15)), BreakerState::HalfOpen);
+        breaker.record_at(&failure(), at(15));
+        assert_eq!(breaker.state_at(at(16)), BreakerState::Open);
+
+        // The trial call succeeds, the breaker closes.
+        assert_eq!(breaker.state_at(at(25)), BreakerState::HalfOpen);
+        breaker.record_success();
+        assert_eq!(breaker.state_at(at(25)), BreakerState::Closed);
+    }
+}
@@ -0,0 +1,79 @@
+//! A per-trap-code error budget, for hosts enforcing an SLO.
+
+use super::trapcode::TrapCode;
+use std::collections::HashMap;
+
+/// Tracks the traps of each trap code against an error budget, allowing
+/// up to `allowed_traps` traps every `per_requests` requests.
+///
+/// Trap codes without a budget are counted, but never exhausted.
+#[derive(Debug, Clone, Default)]
+pub struct TrapBudget {
+    budgets: HashMap<TrapCode, (u64, u64)>,
+    traps: HashMap<TrapCode, u64>,
+    total: u64,
+}
+
+impl TrapBudget {
+    /// Creates a tracker without any budget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this tracker with a budget of `allowed_traps` traps with the
+    /// given code every `per_requests` requests, eg. `(1, 1000)` for 0.1%.
+    pub fn with_budget(mut self, code: TrapCode, allowed_traps: u64, per_requests: u64) -> Self {
+        self.budgets.insert(code, (allowed_traps, per_requests));
+        self
+    }
+
+    /// Records a trap with the given code, `total` being the number of
+    /// requests served so far, the trapping one included.
+    pub fn observe(&mut self, total: u64, code: TrapCode) {
+        self.total = self.total.max(total);
+        *self.traps.entry(code).or_insert(0) += 1;
+    }
+
+    /// Whether the traps with the given code exceeded their budget, out of
+    /// the requests served so far.
+    pub fn is_exhausted(&self, code: TrapCode) -> bool {
+        let (allowed_traps, per_requests) = match self.budgets.get(&code) {
+            Some(&budget) => budget,
+            None => return false,
+        };
+        let traps = self.traps.get(&code).copied().unwrap_or(0);
+        // `traps / total > allowed_traps / per_requests`, without rounding.
+        u128::from(traps) * u128::from(per_requests)
+            > u128::from(allowed_traps) * u128::from(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted() {
+        let mut budget = TrapBudget::new()
+            .with_budget(TrapCode::HeapAccessOutOfBounds, 1, 1000)
+            .with_budget(TrapCode::IntegerDivisionByZero, 1, 100);
+
+        budget.observe(1000, TrapCode::HeapAccessOutOfBounds);
+        budget.observe(1000, TrapCode::IntegerDivisionByZero);
+        assert!(!budget.is_exhausted(TrapCode::HeapAccessOutOfBounds));
+        assert!(!budget.is_exhausted(TrapCode::IntegerDivisionByZero));
+
+        budget.observe(1500, TrapCode::HeapAccessOutOfBounds);
+        assert!(budget.is_exhausted(TrapCode::HeapAccessOutOfBounds));
+        assert!(!budget.is_exhausted(TrapCode::IntegerDivisionByZero));
+
+        // Codes without a budget are never exhausted.
+        budget.observe(1500, TrapCode::StackOverflow);
+        assert!(!budget.is_exhausted(TrapCode::StackOverflow));
+
+        // The budget recovers as more requests are served without traps.
+        budget.observe(2000, TrapCode::IntegerDivisionByZero);
+        assert!(!budget.is_exhausted(TrapCode::HeapAccessOutOfBounds));
+        assert!(!budget.is_exhausted(TrapCode::IntegerDivisionByZero));
+    }
+}
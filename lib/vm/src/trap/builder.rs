@@ -0,0 +1,136 @@
+//! A builder for `Trap::Wasm` traps, setting any of their optional fields.
+
+use super::trapcode::TrapCode;
+use super::traphandlers::{Trap, TrapTimestamp};
+use backtrace::Backtrace;
+
+/// A builder for `Trap::Wasm` traps.
+///
+/// `Trap::wasm` covers the common case of a trap with only a program
+/// counter, a backtrace and a trap code; this builder also sets the
+/// optional diagnostics of the trap. Fields that aren't set are left
+/// empty, the backtrace included.
+#[derive(Debug, Clone)]
+pub struct WasmTrapBuilder {
+    pc: usize,
+    backtrace: Option<Backtrace>,
+    signal_trap: Option<TrapCode>,
+    memory_index: Option<u32>,
+    function_name: Option<String>,
+    faulting_addr: Option<u64>,
+    invoked_export: Option<String>,
+}
+
+impl WasmTrapBuilder {
+    /// Creates a builder for a trap at the program counter `pc`.
+    pub fn new(pc: usize) -> Self {
+        Self {
+            pc,
+            backtrace: None,
+            signal_trap: None,
+            memory_index: None,
+            function_name: None,
+            faulting_addr: None,
+            invoked_export: None,
+        }
+    }
+
+    /// Sets the program counter in generated code where the trap happened.
+    pub fn pc(&mut self, pc: usize) -> &mut Self {
+        self.pc = pc;
+        self
+    }
+
+    /// Sets the native stack backtrace at the time the trap occurred.
+    pub fn backtrace(&mut self, backtrace: Backtrace) -> &mut Self {
+        self.backtrace = Some(backtrace);
+        self
+    }
+
+    /// Sets the trap code associated to the signal that caused the trap.
+    pub fn signal_trap(&mut self, trap_code: TrapCode) -> &mut Self {
+        self.signal_trap = Some(trap_code);
+        self
+    }
+
+    /// Sets the index of the memory that was accessed.
+    pub fn memory_index(&mut self, memory_index: u32) -> &mut Self {
+        self.memory_index = Some(memory_index);
+        self
+    }
+
+    /// Sets the name of the wasm function where the trap happened.
+    pub fn function_name(&mut self, function_name: impl Into<String>) -> &mut Self {
+        self.function_name = Some(function_name.into());
+        self
+    }
+
+    /// Sets the address of the faulting access.
+    pub fn faulting_addr(&mut self, faulting_addr: u64) -> &mut Self {
+        self.faulting_addr = Some(faulting_addr);
+        self
+    }
+
+    /// Sets the name of the export whose call led to the trap.
+    pub fn invoked_export(&mut self, invoked_export: impl Into<String>) -> &mut Self {
+        self.invoked_export = Some(invoked_export.into());
+        self
+    }
+
+    /// Builds the trap.
+    pub fn build(&self) -> Trap {
+        Trap::Wasm {
+            pc: self.pc,
+            backtrace: self
+                .backtrace
+                .clone()
+                .unwrap_or_else(|| Backtrace::from(Vec::new())),
+            signal_trap: self.signal_trap,
+            memory_index: self.memory_index,
+            function_name: self.function_name.clone(),
+            faulting_addr: self.faulting_addr,
+            invoked_export: self.invoked_export.clone(),
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_fully_populated() {
+        let trap = WasmTrapBuilder::new(0)
+            .pc(0x1000)
+            .backtrace(Backtrace::new_unresolved())
+            .signal_trap(TrapCode::HeapAccessOutOfBounds)
+            .memory_index(1)
+            .function_name("load")
+            .faulting_addr(0x10000)
+            .invoked_export("run")
+            .build();
+        assert!(matches!(trap, Trap::Wasm { pc: 0x1000, .. }));
+        assert_eq!(trap.trap_code(), Some(TrapCode::HeapAccessOutOfBounds));
+        assert_eq!(trap.memory_index(), Some(1));
+        assert_eq!(trap.function_name(), Some("load"));
+        assert_eq!(trap.faulting_address(), Some(0x10000));
+        assert_eq!(trap.invoked_export(), Some("run"));
+        assert_eq!(
+            trap.to_string(),
+            "wasm trap at 0x1000 in load: out of bounds memory access at offset 0x10000 on memory #1 \
+             while running export `run`"
+        );
+    }
+
+    #[test]
+    fn build_minimal() {
+        let trap = WasmTrapBuilder::new(0x1000).build();
+        assert_eq!(
+            trap.display_stable(),
+            Trap::wasm(0x2000, Backtrace::from(Vec::new()), None).display_stable()
+        );
+        assert_eq!(trap.function_name(), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Tracking whether an instance can keep being used after its traps.
+
+use super::traphandlers::Trap;
+
+/// Whether an instance can be reused after the traps it raised.
+///
+/// Once a trap poisons the instance, it stays unusable whatever the later
+/// traps: see `TrapCode::poisons_instance`. Running out of memory also
+/// poisons it, as it can happen in the middle of any operation. Host
+/// errors and exhausted resources stop the guest at a well-defined point,
+/// and don't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstanceHealth {
+    poisoned: bool,
+}
+
+impl InstanceHealth {
+    /// Creates the health of an instance that didn't trap yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a trap raised by the instance.
+    pub fn record(&mut self, trap: &Trap) {
+        self.poisoned |= match trap {
+            Trap::Wasm { .. } | Trap::Lib { .. } => trap.best_effort_code().poisons_instance(),
+            Trap::OOM { .. } => true,
+            Trap::User { .. } | Trap::ResourceExhausted { .. } => false,
+        };
+    }
+
+    /// Returns whether the instance can still be used.
+    pub fn is_reusable(&self) -> bool {
+        !self.poisoned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trap::{ResourceKind, TrapCode};
+
+    #[test]
+    fn poisoned_for_good() {
+        let mut health = InstanceHealth::new();
+        health.record(&Trap::lib(TrapCode::HeapAccessOutOfBounds));
+        health.record(&Trap::user_boxed("host error".into()));
+        health.record(&Trap::resource_exhausted(ResourceKind::Fuel));
+        assert!(health.is_reusable());
+
+        health.record(&Trap::lib(TrapCode::StackOverflow));
+        assert!(!health.is_reusable());
+        health.record(&Trap::lib(TrapCode::IntegerDivisionByZero));
+        assert!(!health.is_reusable());
+
+        let mut health = InstanceHealth::new();
+        health.record(&Trap::oom());
+        assert!(!health.is_reusable());
+    }
+}
@@ -3,12 +3,40 @@
 
 //! This is the module that facilitates the usage of Traps
 //! in Wasmer Runtime
+mod breaker;
+mod budget;
+mod builder;
+mod health;
+mod panic_hook;
+mod pool;
+mod rate_limit;
+mod ring;
+mod sampler;
 mod trapcode;
 mod traphandlers;
+mod watchdog;
+mod wire;
 
-pub use trapcode::TrapCode;
+pub use breaker::{BreakerState, CircuitBreaker};
+pub use budget::TrapBudget;
+pub use builder::WasmTrapBuilder;
+pub use health::InstanceHealth;
+pub(crate) use panic_hook::enter_wasm;
+pub use panic_hook::{install_trap_panic_hook, set_current_wasm_pcs};
+pub use pool::TrapPool;
+pub use rate_limit::TrapRateLimiter;
+pub use ring::{TrapRecord, TrapRingBuffer};
+pub use sampler::BacktraceSampler;
+pub use trapcode::{
+    check_i32_div, check_i32_rem, check_i64_div, check_i64_rem, codes_for_features,
+    resolve_precedence, summarize_trap_mismatches, LocalizationError, LocalizationTable, TrapCode,
+    TrapCodeSet, WasmProposal,
+};
 pub use traphandlers::{
-    catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, wasmer_call_trampoline, Trap,
-    TrapHandler, TrapHandlerFn,
+    catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, trap_eq_ignoring_backtrace,
+    wasmer_call_trampoline, ResourceKind, SignatureMismatch, Trap, TrapHandler, TrapHandlerFn,
+    TrapOrigin, TrapTimestamp,
 };
 pub use traphandlers::{init_traps, resume_panic};
+pub use watchdog::TrapWatchdog;
+pub use wire::{TrapTypeRegistry, WireError};
@@ -0,0 +1,156 @@
+//! A panic hook printing the wasm backtrace of the panicking thread, for
+//! debugging host functions called by guests.
+
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+thread_local! {
+    /// The program counters of the wasm frames the thread is running, if
+    /// any, innermost first.
+    static CURRENT_WASM_PCS: RefCell<Option<Vec<usize>>> = RefCell::new(None);
+}
+
+static INSTALL: Once = Once::new();
+
+/// Whether `install_trap_panic_hook` ran, so that the entries in wasm code
+/// are only recorded when there's a hook to print them.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the program counters of the wasm frames the current thread is
+/// running, innermost first, or clears them with `None`.
+///
+/// Once the hook is installed, `wasmer_call_trampoline` and the calls of
+/// start functions already set the entry points of the wasm code they run.
+/// Returns the previous program counters, to be restored when leaving a
+/// nested call.
+pub fn set_current_wasm_pcs(pcs: Option<Vec<usize>>) -> Option<Vec<usize>> {
+    CURRENT_WASM_PCS.with(|current| current.replace(pcs))
+}
+
+/// Restores the program counters set before entering wasm code with
+/// `enter_wasm`, when dropped.
+pub(crate) struct WasmPcsGuard {
+    previous: Option<Option<Vec<usize>>>,
+}
+
+/// Records that the current thread enters the wasm code at `pc`, until the
+/// returned guard is dropped.
+///
+/// The guard must live on the host side of `catch_traps`, so that it's
+/// dropped even when a trap unwinds the wasm stack. This does nothing until
+/// `install_trap_panic_hook` is called, so that calls into wasm don't pay
+/// for a backtrace nobody prints.
+pub(crate) fn enter_wasm(pc: usize) -> WasmPcsGuard {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        return WasmPcsGuard { previous: None };
+    }
+    let previous = CURRENT_WASM_PCS.with(|current| {
+        let mut current = current.borrow_mut();
+        let mut pcs = vec![pc];
+        pcs.extend(current.iter().flatten());
+        current.replace(pcs)
+    });
+    WasmPcsGuard {
+        previous: Some(previous),
+    }
+}
+
+impl Drop for WasmPcsGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            set_current_wasm_pcs(previous);
+        }
+    }
+}
+
+/// Installs a panic hook which, after running the previously installed
+/// hook, prints the wasm backtrace of the panicking thread if one was set
+/// with `set_current_wasm_pcs`.
+///
+/// Only the first call installs the hook, so that it doesn't stack up.
+pub fn install_trap_panic_hook() {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            let backtrace = CURRENT_WASM_PCS.with(|current| {
+                current
+                    .try_borrow()
+                    .ok()
+                    .and_then(|pcs| pcs.as_deref().map(format_wasm_backtrace))
+            });
+            if let Some(backtrace) = backtrace {
+                eprint!("{}", backtrace);
+            }
+        }));
+        INSTALLED.store(true, Ordering::Relaxed);
+    });
+}
+
+fn format_wasm_backtrace(pcs: &[usize]) -> String {
+    let mut backtrace = String::from("wasm backtrace:\n");
+    for (i, pc) in pcs.iter().enumerate() {
+        writeln!(backtrace, "  #{} {:#x}", i, pc).unwrap();
+    }
+    backtrace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::PanicInfo;
+    use std::sync::Arc;
+
+    #[test]
+    fn chains_previous_hook() {
+        static CALLED: AtomicBool = AtomicBool::new(false);
+        let default: Arc<dyn Fn(&PanicInfo) + Send + Sync> = panic::take_hook().into();
+        let chained = default.clone();
+        panic::set_hook(Box::new(move |info| {
+            CALLED.store(true, Ordering::SeqCst);
+            chained(info);
+        }));
+        install_trap_panic_hook();
+
+        assert_eq!(set_current_wasm_pcs(Some(vec![0x1010, 0x2020])), None);
+        assert!(panic::catch_unwind(|| panic!("host function failed")).is_err());
+        assert!(CALLED.load(Ordering::SeqCst));
+        assert_eq!(set_current_wasm_pcs(None), Some(vec![0x1010, 0x2020]));
+
+        // Don't leak the hooks into the other tests.
+        panic::set_hook(Box::new(move |info| default(info)));
+    }
+
+    #[test]
+    fn nested_wasm_entries() {
+        install_trap_panic_hook();
+        let outer = enter_wasm(0x1010);
+        {
+            let _inner = enter_wasm(0x2020);
+            assert_eq!(
+                CURRENT_WASM_PCS.with(|current| current.borrow().clone()),
+                Some(vec![0x2020, 0x1010])
+            );
+        }
+        assert_eq!(
+            CURRENT_WASM_PCS.with(|current| current.borrow().clone()),
+            Some(vec![0x1010])
+        );
+        drop(outer);
+        assert_eq!(
+            CURRENT_WASM_PCS.with(|current| current.borrow().clone()),
+            None
+        );
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(
+            format_wasm_backtrace(&[0x1010, 0x2020]),
+            "wasm backtrace:\n  #0 0x1010\n  #1 0x2020\n"
+        );
+    }
+}
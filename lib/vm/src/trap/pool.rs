@@ -0,0 +1,112 @@
+//! A thread-local pool of backtrace buffers for `Trap::Lib` traps, for
+//! workloads where traps are frequent and expected.
+
+use super::sampler::BacktraceSampler;
+use super::trapcode::TrapCode;
+use super::traphandlers::Trap;
+use backtrace::{Backtrace, BacktraceFrame};
+use std::cell::RefCell;
+
+/// The maximum number of backtrace buffers kept in the pool of each thread.
+const TRAP_POOL_CAPACITY: usize = 16;
+
+thread_local! {
+    static TRAP_POOL: RefCell<Vec<Vec<BacktraceFrame>>> = RefCell::new(Vec::new());
+}
+
+/// A thread-local pool of the buffers holding the backtraces of traps.
+///
+/// Capturing a backtrace is the bulk of the cost of a trap, and its frames
+/// are most of its allocations. `TrapPool::lib` creates a trap like
+/// `Trap::lib`, capturing its backtrace into a buffer recycled from a
+/// previous trap with `TrapPool::recycle`, so that once the pool is warm,
+/// trapping at the same depth doesn't allocate.
+pub struct TrapPool;
+
+impl TrapPool {
+    /// Construct a new Wasm trap with the given trap code, like
+    /// `Trap::lib`, reusing a recycled backtrace buffer if there is one.
+    pub fn lib(trap_code: TrapCode) -> Trap {
+        let mut frames = TRAP_POOL
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_default();
+        if BacktraceSampler::global().should_capture(trap_code) {
+            backtrace::trace(|frame| {
+                frames.push(frame.clone().into());
+                true
+            });
+        }
+        Trap::deferred_with_backtrace(trap_code, Backtrace::from(frames))
+    }
+
+    /// Gives the backtrace buffer of a handled trap back to the pool of the
+    /// current thread.
+    ///
+    /// The buffer is only kept while the pool isn't full, and if it was
+    /// allocated at all; the rest of the trap is dropped.
+    pub fn recycle(trap: Trap) {
+        let backtrace = match trap {
+            Trap::Wasm { backtrace, .. }
+            | Trap::Lib { backtrace, .. }
+            | Trap::OOM { backtrace, .. } => backtrace,
+            Trap::User { .. } | Trap::ResourceExhausted { .. } => return,
+        };
+        let mut frames: Vec<BacktraceFrame> = backtrace.into();
+        if frames.capacity() == 0 {
+            return;
+        }
+        // The frames of the handled trap must not leak into the next one.
+        frames.clear();
+        TRAP_POOL.with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < TRAP_POOL_CAPACITY {
+                pool.push(frames);
+            }
+        });
+    }
+
+    /// Returns the number of backtrace buffers in the pool of the current
+    /// thread.
+    pub fn len() -> usize {
+        TRAP_POOL.with(|pool| pool.borrow().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_count(trap: &Trap) -> usize {
+        match trap {
+            Trap::Lib { backtrace, .. } => backtrace.frames().len(),
+            _ => panic!("expected a lib trap, got `{}`", trap),
+        }
+    }
+
+    #[test]
+    fn lib() {
+        let trap = TrapPool::lib(TrapCode::IntegerOverflow);
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerOverflow));
+        let frames = frame_count(&trap);
+        assert_ne!(frames, 0);
+        TrapPool::recycle(trap);
+
+        // The recycled buffer doesn't keep the frames of the previous trap.
+        let trap = TrapPool::lib(TrapCode::HeapAccessOutOfBounds);
+        assert_eq!(trap.trap_code(), Some(TrapCode::HeapAccessOutOfBounds));
+        assert_eq!(frame_count(&trap), frames);
+    }
+
+    #[test]
+    fn pool_is_bounded() {
+        // Traps without a backtrace have no buffer to give back.
+        TrapPool::recycle(Trap::user_boxed("host error".into()));
+        TrapPool::recycle(Trap::deferred(TrapCode::IntegerOverflow));
+        assert_eq!(TrapPool::len(), 0);
+
+        for _ in 0..100 {
+            TrapPool::recycle(Trap::lib(TrapCode::IntegerOverflow));
+        }
+        assert_eq!(TrapPool::len(), TRAP_POOL_CAPACITY);
+    }
+}
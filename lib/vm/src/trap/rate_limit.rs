@@ -0,0 +1,93 @@
+//! A per-tenant limiter of the traps of guests, for multi-tenant hosts.
+
+use super::trapcode::{TrapCode, TrapCodeSet};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Tracks the traps of the guests of each tenant, and tells when a tenant
+/// trapped more than `max_traps` times within a sliding `window`.
+///
+/// Only the trap codes of `tracked` count towards the limit, so that eg.
+/// the deliberate aborts of `unreachable` can be left out.
+#[derive(Debug, Clone)]
+pub struct TrapRateLimiter<T> {
+    tracked: TrapCodeSet,
+    max_traps: usize,
+    window: Duration,
+    traps: HashMap<T, VecDeque<Instant>>,
+}
+
+impl<T: Eq + Hash> TrapRateLimiter<T> {
+    /// Creates a limiter allowing up to `max_traps` traps of the `tracked`
+    /// trap codes per tenant, within any `window`.
+    pub fn new(tracked: TrapCodeSet, max_traps: usize, window: Duration) -> Self {
+        Self {
+            tracked,
+            max_traps,
+            window,
+            traps: HashMap::new(),
+        }
+    }
+
+    /// Records a trap with the given code for `tenant`, now.
+    ///
+    /// Returns whether the tenant is over its limit.
+    pub fn record(&mut self, tenant: T, code: TrapCode) -> bool {
+        self.record_at(tenant, code, Instant::now())
+    }
+
+    /// Records a trap with the given code for `tenant`, at `now`.
+    ///
+    /// Returns whether the tenant is over its limit. The instants of the
+    /// traps of a tenant are expected to be increasing.
+    pub fn record_at(&mut self, tenant: T, code: TrapCode, now: Instant) -> bool {
+        let window = self.window;
+        let traps = self.traps.entry(tenant).or_default();
+        while traps
+            .front()
+            .map_or(false, |&trap| now.saturating_duration_since(trap) >= window)
+        {
+            traps.pop_front();
+        }
+        if self.tracked.contains(code) {
+            traps.push_back(now);
+        }
+        traps.len() > self.max_traps
+    }
+
+    /// Forgets the traps of `tenant`.
+    pub fn reset(&mut self, tenant: &T) {
+        self.traps.remove(tenant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn over_limit() {
+        let tracked = TrapCodeSet::empty()
+            .with(TrapCode::HeapAccessOutOfBounds)
+            .with(TrapCode::TableAccessOutOfBounds);
+        let mut limiter = TrapRateLimiter::new(tracked, 2, Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!limiter.record_at("a", TrapCode::HeapAccessOutOfBounds, at(0)));
+        assert!(!limiter.record_at("a", TrapCode::TableAccessOutOfBounds, at(1)));
+        // Untracked trap codes don't count.
+        assert!(!limiter.record_at("a", TrapCode::UnreachableCodeReached, at(2)));
+        // Other tenants have their own limit.
+        assert!(!limiter.record_at("b", TrapCode::HeapAccessOutOfBounds, at(2)));
+        assert!(limiter.record_at("a", TrapCode::HeapAccessOutOfBounds, at(3)));
+
+        // The first trap left the window.
+        assert!(!limiter.record_at("a", TrapCode::UnreachableCodeReached, at(10)));
+        assert!(limiter.record_at("a", TrapCode::HeapAccessOutOfBounds, at(10)));
+
+        limiter.reset(&"a");
+        assert!(!limiter.record_at("a", TrapCode::HeapAccessOutOfBounds, at(11)));
+    }
+}
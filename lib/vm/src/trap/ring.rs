@@ -0,0 +1,242 @@
+//! Compact records of traps, and a lock-free ring buffer to log them from
+//! hot paths and format them later.
+
+use super::trapcode::TrapCode;
+use super::traphandlers::{Trap, TrapOrigin};
+use std::sync::atomic::{fence, AtomicU32, AtomicUsize, Ordering};
+
+/// A compact record of a trap, copied without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrapRecord {
+    /// The trap code of the trap, see `Trap::trap_code`.
+    pub code: Option<TrapCode>,
+    /// The program counter in generated code where the trap happened, or 0
+    /// for traps not raised by wasm code.
+    pub pc: usize,
+    /// What caused the trap.
+    pub origin: TrapOrigin,
+}
+
+impl From<&Trap> for TrapRecord {
+    fn from(trap: &Trap) -> Self {
+        Self {
+            code: trap.trap_code(),
+            pc: match *trap {
+                Trap::Wasm { pc, .. } => pc,
+                _ => 0,
+            },
+            origin: trap.origin(),
+        }
+    }
+}
+
+/// The `TrapRecord::code` of an encoded record without a trap code.
+const NO_TRAP_CODE: u32 = 0xffff;
+
+impl TrapRecord {
+    /// Encodes the code and origin of the record in a `u32`.
+    fn encode_meta(&self) -> u32 {
+        let code = self.code.map_or(NO_TRAP_CODE, |code| code as u32);
+        let origin = match self.origin {
+            TrapOrigin::Guest => 0,
+            TrapOrigin::Host => 1,
+            TrapOrigin::Runtime => 2,
+        };
+        code | (origin << 16)
+    }
+
+    fn decode(meta: u32, pc: usize) -> Self {
+        Self {
+            code: TrapCode::from_u32(meta & 0xffff),
+            pc,
+            origin: match meta >> 16 {
+                0 => TrapOrigin::Guest,
+                1 => TrapOrigin::Host,
+                _ => TrapOrigin::Runtime,
+            },
+        }
+    }
+}
+
+/// A slot of a `TrapRingBuffer`, guarded by a sequence lock.
+#[derive(Debug, Default)]
+struct Slot {
+    /// `2 * (n + 1)` once the `n`th record pushed is written to the slot,
+    /// and odd while a record is being written.
+    seq: AtomicUsize,
+    pc: AtomicUsize,
+    meta: AtomicU32,
+}
+
+/// A fixed-capacity ring buffer of trap records, which any number of
+/// threads push to without locking or allocating.
+///
+/// Once full, pushing a record overwrites the oldest one. The records are
+/// meant to be drained by a single thread, eg. a background thread
+/// formatting them.
+#[derive(Debug)]
+pub struct TrapRingBuffer {
+    slots: Box<[Slot]>,
+    /// The number of records pushed so far.
+    head: AtomicUsize,
+    /// The number of records drained or overwritten so far.
+    tail: AtomicUsize,
+}
+
+impl TrapRingBuffer {
+    /// Creates a ring buffer holding up to `capacity` records.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "a ring buffer needs room for a record");
+        Self {
+            slots: (0..capacity).map(|_| Slot::default()).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of records the ring buffer holds.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Pushes a record, overwriting the oldest one if the ring buffer is
+    /// full.
+    ///
+    /// Pushes from several threads each claim their own slot. A push only
+    /// waits when the ring buffer wrapped around while the push of the
+    /// previous record of its slot is still writing it.
+    pub fn push(&self, record: TrapRecord) {
+        let n = self.head.fetch_add(1, Ordering::Relaxed);
+        let capacity = self.slots.len();
+        let slot = &self.slots[n % capacity];
+        // The sequence of the slot once the record it holds before this one
+        // is written.
+        let previous = if n >= capacity {
+            2 * (n - capacity + 1)
+        } else {
+            0
+        };
+        while slot
+            .seq
+            .compare_exchange_weak(previous, 2 * n + 1, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        fence(Ordering::Release);
+        slot.pc.store(record.pc, Ordering::Relaxed);
+        slot.meta.store(record.encode_meta(), Ordering::Relaxed);
+        slot.seq.store(2 * (n + 1), Ordering::Release);
+    }
+
+    /// Takes the records pushed since the last drain, oldest first.
+    ///
+    /// Records that were overwritten before being drained are lost, and
+    /// the ones still being pushed are left for the next drain.
+    pub fn drain(&self) -> Vec<TrapRecord> {
+        let head = self.head.load(Ordering::Acquire);
+        let mut n = self
+            .tail
+            .load(Ordering::Relaxed)
+            .max(head.saturating_sub(self.slots.len()));
+        let mut records = Vec::with_capacity(head - n);
+        while n < head {
+            let slot = &self.slots[n % self.slots.len()];
+            let seq = slot.seq.load(Ordering::Acquire);
+            if seq < 2 * (n + 1) {
+                // Not written yet.
+                break;
+            }
+            let pc = slot.pc.load(Ordering::Relaxed);
+            let meta = slot.meta.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            // Otherwise, the record was overwritten while reading it.
+            if seq == 2 * (n + 1) && slot.seq.load(Ordering::Relaxed) == seq {
+                records.push(TrapRecord::decode(meta, pc));
+            }
+            n += 1;
+        }
+        self.tail.store(n, Ordering::Relaxed);
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backtrace::Backtrace;
+
+    fn record(pc: usize) -> TrapRecord {
+        TrapRecord {
+            code: Some(TrapCode::HeapAccessOutOfBounds),
+            pc,
+            origin: TrapOrigin::Guest,
+        }
+    }
+
+    #[test]
+    fn from_trap() {
+        let trap = Trap::wasm(
+            0x1000,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::IntegerDivisionByZero),
+        );
+        assert_eq!(
+            TrapRecord::from(&trap),
+            TrapRecord {
+                code: Some(TrapCode::IntegerDivisionByZero),
+                pc: 0x1000,
+                origin: TrapOrigin::Guest,
+            }
+        );
+        let record = TrapRecord {
+            code: None,
+            pc: 0,
+            origin: TrapOrigin::Host,
+        };
+        assert_eq!(TrapRecord::decode(record.encode_meta(), 0), record);
+    }
+
+    #[test]
+    fn overwrites_oldest() {
+        let ring = TrapRingBuffer::new(4);
+        for pc in 0..3 {
+            ring.push(record(pc));
+        }
+        assert_eq!(ring.drain(), [record(0), record(1), record(2)]);
+        assert_eq!(ring.drain(), []);
+
+        for pc in 3..10 {
+            ring.push(record(pc));
+        }
+        assert_eq!(ring.drain(), [record(6), record(7), record(8), record(9)]);
+    }
+
+    #[test]
+    fn concurrent_pushes() {
+        let ring = std::sync::Arc::new(TrapRingBuffer::new(8));
+        let threads: Vec<_> = (0..4)
+            .map(|thread| {
+                let ring = ring.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        ring.push(record(thread * 100 + i));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let records = ring.drain();
+        assert_eq!(records.len(), ring.capacity());
+        let mut pcs: Vec<_> = records.iter().map(|record| record.pc).collect();
+        pcs.sort_unstable();
+        pcs.dedup();
+        assert_eq!(pcs.len(), ring.capacity());
+    }
+}
@@ -0,0 +1,111 @@
+//! Sampling of the backtraces captured by `Trap::lib`.
+
+use super::trapcode::TrapCode;
+use enum_iterator::IntoEnumIterator;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const TRAP_CODE_COUNT: usize = <TrapCode as IntoEnumIterator>::VARIANT_COUNT;
+
+/// The sampler consulted by `Trap::lib`.
+static GLOBAL_SAMPLER: BacktraceSampler = BacktraceSampler::new();
+
+/// Decides, for each trap code, how often a trap captures a backtrace.
+///
+/// Capturing a backtrace is the bulk of the cost of a trap, which matters
+/// for workloads where traps are frequent. Each trap code has a sampling
+/// rate `n`: one in every `n` traps with that code captures a backtrace,
+/// the others get an empty one. A rate of 0 never captures, and the default
+/// rate of 1 always captures.
+#[derive(Debug)]
+pub struct BacktraceSampler {
+    rates: [AtomicU32; TRAP_CODE_COUNT],
+    counters: [AtomicU32; TRAP_CODE_COUNT],
+}
+
+impl BacktraceSampler {
+    /// Creates a sampler capturing every backtrace.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ALWAYS: AtomicU32 = AtomicU32::new(1);
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU32 = AtomicU32::new(0);
+        Self {
+            rates: [ALWAYS; TRAP_CODE_COUNT],
+            counters: [ZERO; TRAP_CODE_COUNT],
+        }
+    }
+
+    /// Gets the sampler consulted by `Trap::lib`.
+    pub fn global() -> &'static Self {
+        &GLOBAL_SAMPLER
+    }
+
+    /// Sets the sampling rate of the traps with the given code: one in
+    /// every `rate` traps captures a backtrace, none if `rate` is 0.
+    pub fn set_rate(&self, trap_code: TrapCode, rate: u32) {
+        self.rates[trap_code as usize].store(rate, Ordering::Relaxed);
+    }
+
+    /// Gets the sampling rate of the traps with the given code.
+    pub fn rate(&self, trap_code: TrapCode) -> u32 {
+        self.rates[trap_code as usize].load(Ordering::Relaxed)
+    }
+
+    /// Whether the next trap with the given code should capture a backtrace.
+    pub fn should_capture(&self, trap_code: TrapCode) -> bool {
+        match self.rate(trap_code) {
+            0 => false,
+            1 => true,
+            rate => self.counters[trap_code as usize].fetch_add(1, Ordering::Relaxed) % rate == 0,
+        }
+    }
+}
+
+impl Default for BacktraceSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Trap;
+
+    #[test]
+    fn rates() {
+        let sampler = BacktraceSampler::new();
+        assert!((0..10).all(|_| sampler.should_capture(TrapCode::StackOverflow)));
+
+        sampler.set_rate(TrapCode::HeapAccessOutOfBounds, 0);
+        assert!((0..10).all(|_| !sampler.should_capture(TrapCode::HeapAccessOutOfBounds)));
+
+        sampler.set_rate(TrapCode::HeapAccessOutOfBounds, 1);
+        assert!((0..10).all(|_| sampler.should_capture(TrapCode::HeapAccessOutOfBounds)));
+
+        sampler.set_rate(TrapCode::IntegerOverflow, 3);
+        let captured = (0..9)
+            .filter(|_| sampler.should_capture(TrapCode::IntegerOverflow))
+            .count();
+        assert_eq!(captured, 3);
+        // Other codes are unaffected.
+        assert_eq!(sampler.rate(TrapCode::StackOverflow), 1);
+    }
+
+    #[test]
+    fn lib_trap_consults_the_global_sampler() {
+        // No other test traps with this code, so the global sampler can be
+        // changed safely.
+        let trap_code = TrapCode::UninitializedElement;
+        let backtrace_len = |trap: Trap| match trap {
+            Trap::Lib { backtrace, .. } => backtrace.frames().len(),
+            _ => unreachable!(),
+        };
+
+        BacktraceSampler::global().set_rate(trap_code, 0);
+        assert_eq!(backtrace_len(Trap::lib(trap_code)), 0);
+
+        BacktraceSampler::global().set_rate(trap_code, 1);
+        assert_ne!(backtrace_len(Trap::lib(trap_code)), 0);
+    }
+}
@@ -4,22 +4,65 @@
 //! Trap codes describing the reason for a trap.
 
 use core::fmt::{self, Display, Formatter};
+use core::iter::FromIterator;
 use core::str::FromStr;
+use enum_iterator::IntoEnumIterator;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
 use thiserror::Error;
 
 /// A trap code describing the reason for a trap.
 ///
 /// All trap instructions have an explicit trap code.
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize, Error, MemoryUsage)]
+///
+/// # Stability
+///
+/// New WebAssembly proposals (threads, exception handling, GC, ...) bring
+/// new trap codes, so this enum is `#[non_exhaustive]`: adding a trap code
+/// is not a breaking change, and matches outside of this crate need a
+/// wildcard arm. The trap codes specific to a proposal are listed by
+/// `TrapCode::all_for_proposal`. They are never put behind a cargo feature,
+/// as the compilers emit them whatever the features enabled by embedders.
+///
+/// ```
+/// use wasmer_vm::TrapCode;
+///
+/// fn is_out_of_bounds(trap_code: TrapCode) -> bool {
+///     match trap_code {
+///         TrapCode::HeapAccessOutOfBounds | TrapCode::TableAccessOutOfBounds => true,
+///         _ => false,
+///     }
+/// }
+/// # assert!(is_out_of_bounds(TrapCode::HeapAccessOutOfBounds));
+/// ```
+///
+/// Embedders who would rather have their matches break on a new trap code
+/// enable the `exhaustive-trap-codes` feature, which drops
+/// `#[non_exhaustive]`: adding a trap code is then a breaking change for
+/// them.
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Hash,
+    Serialize,
+    Deserialize,
+    Error,
+    MemoryUsage,
+    IntoEnumIterator,
+)]
 #[cfg_attr(
     feature = "enable-rkyv",
     derive(RkyvSerialize, RkyvDeserialize, Archive)
 )]
 #[repr(u32)]
+#[cfg_attr(not(feature = "exhaustive-trap-codes"), non_exhaustive)]
 pub enum TrapCode {
     /// The current stack space was exhausted.
     ///
@@ -43,7 +86,7 @@ pub enum TrapCode {
     /// Other bounds checking error.
     OutOfBounds = 4,
 
-    /// Indirect call to a null table entry.
+    /// Indirect call to a null function reference.
     IndirectCallToNull = 5,
 
     /// Signature mismatch on indirect call.
@@ -63,31 +106,224 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 11,
+
+    /// An atomic memory access was out of bounds.
+    ///
+    /// Only reported by Singlepass: Cranelift and LLVM share the bounds
+    /// checks of the other memory accesses, and report these as
+    /// `HeapAccessOutOfBounds`.
+    AtomicAccessOutOfBounds = 12,
+
+    /// Indirect call to a table element that was never initialized.
+    UninitializedElement = 13,
+}
+
+/// The WebAssembly proposal that introduced a trap code, see
+/// `TrapCode::proposal`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum WasmProposal {
+    /// The MVP of WebAssembly.
+    MVP,
+    /// The threads proposal.
+    Threads,
+    /// The reference types proposal.
+    ReferenceTypes,
+    /// The exception handling proposal.
+    ExceptionHandling,
 }
 
 impl TrapCode {
+    /// Iterates over all the trap codes.
+    pub fn all() -> impl Iterator<Item = Self> {
+        Self::into_enum_iter()
+    }
+
+    /// Gets the trap code a host function should trap with when failing
+    /// with an I/O error of the given kind, if any.
+    ///
+    /// Only `UnexpectedEof` and `WriteZero` map to a trap code, namely
+    /// `OutOfBounds`: they tell that a buffer given by the guest was too
+    /// short for the host function to read or write all of it, and there
+    /// is no way for it to recover. Every other kind, eg. `NotFound` or
+    /// `PermissionDenied`, has a WASI errno to return to the guest instead,
+    /// and maps to `None`. So does `OutOfMemory`, which is reported as a
+    /// resource exhaustion rather than as a trap code.
+    pub fn from_io_error_kind(kind: io::ErrorKind) -> Option<Self> {
+        match kind {
+            io::ErrorKind::UnexpectedEof | io::ErrorKind::WriteZero => Some(Self::OutOfBounds),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the trap codes introduced by the proposal `proposal`.
+    pub fn all_for_proposal(proposal: WasmProposal) -> impl Iterator<Item = Self> {
+        Self::all().filter(move |trap_code| trap_code.proposal() == proposal)
+    }
+
+    /// Gets the WebAssembly proposal that introduced this trap code.
+    ///
+    /// No trap code is specific to the exception handling proposal yet.
+    pub const fn proposal(self) -> WasmProposal {
+        match self {
+            Self::HeapMisaligned | Self::UnalignedAtomic | Self::AtomicAccessOutOfBounds => {
+                WasmProposal::Threads
+            }
+            Self::UninitializedElement => WasmProposal::ReferenceTypes,
+            _ => WasmProposal::MVP,
+        }
+    }
+
+    /// Gets the trap code with the given `#[repr(u32)]` discriminant, if any.
+    pub fn from_u32(code: u32) -> Option<Self> {
+        Self::all().find(|trap_code| *trap_code as u32 == code)
+    }
+
+    /// Parses a trap code from either its short identifier, as `FromStr`
+    /// does, or its `#[repr(u32)]` discriminant in decimal, as found in
+    /// some serialized logs.
+    #[allow(clippy::result_unit_err)]
+    pub fn parse_lenient(s: &str) -> Result<Self, ()> {
+        s.parse()
+            .or_else(|()| s.parse::<u32>().ok().and_then(Self::from_u32).ok_or(()))
+    }
+
     /// Gets the message for this trap code
-    pub fn message(&self) -> &str {
+    pub fn message(&self) -> &'static str {
         match self {
             Self::StackOverflow => "call stack exhausted",
             Self::HeapAccessOutOfBounds => "out of bounds memory access",
             Self::HeapMisaligned => "misaligned heap",
             Self::TableAccessOutOfBounds => "undefined element: out of bounds table access",
             Self::OutOfBounds => "out of bounds",
-            Self::IndirectCallToNull => "uninitialized element",
+            Self::IndirectCallToNull => "uninitialized element: null function reference",
             Self::BadSignature => "indirect call type mismatch",
             Self::IntegerOverflow => "integer overflow",
             Self::IntegerDivisionByZero => "integer divide by zero",
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::AtomicAccessOutOfBounds => "atomic: out of bounds memory access",
+            Self::UninitializedElement => "uninitialized element",
         }
     }
-}
 
-impl Display for TrapCode {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let identifier = match *self {
+    /// Gets the message for this trap code from the localization `table`,
+    /// or the English one of `message` if the table doesn't translate it.
+    pub fn localized_message<'a>(&'a self, table: &'a LocalizationTable) -> &'a str {
+        table.get(*self).unwrap_or_else(|| self.message())
+    }
+
+    /// Whether a trap with this code passes a `.wast` `assert_trap` expecting
+    /// `expected_substring`.
+    ///
+    /// Like the spec interpreter, this checks that the message contains the
+    /// expected text. Some tests of the testsuite expect a more specific
+    /// text than the message, such as the `uninitialized element 2` of the
+    /// bulk memory tests, which are accepted as aliases.
+    pub fn message_matches_wast(self, expected_substring: &str) -> bool {
+        self.message().contains(expected_substring)
+            || self
+                .wast_aliases()
+                .iter()
+                .any(|alias| alias.contains(expected_substring))
+    }
+
+    /// Gets the texts that the testsuite expects for this trap code, besides
+    /// its message.
+    const fn wast_aliases(self) -> &'static [&'static str] {
+        match self {
+            Self::IndirectCallToNull | Self::UninitializedElement => &["uninitialized element 2"],
+            _ => &[],
+        }
+    }
+
+    /// Gets a short suggestion on how to fix the code that raised this trap.
+    pub const fn hint(self) -> &'static str {
+        match self {
+            Self::StackOverflow => "check for unbounded recursion, or raise the stack size",
+            Self::HeapAccessOutOfBounds | Self::OutOfBounds | Self::AtomicAccessOutOfBounds => {
+                "check that the address is below `memory.size`, or grow the memory first"
+            }
+            Self::HeapMisaligned | Self::UnalignedAtomic => {
+                "align the address of the atomic access to the size of the access"
+            }
+            Self::TableAccessOutOfBounds => {
+                "check that the index is below `table.size`, or grow the table first"
+            }
+            Self::IndirectCallToNull | Self::UninitializedElement => {
+                "initialize the table element before calling it"
+            }
+            Self::BadSignature => {
+                "check that the type of the `call_indirect` matches the type of the callee"
+            }
+            Self::IntegerOverflow => "avoid dividing the minimum signed integer by -1",
+            Self::IntegerDivisionByZero => "check that the divisor is not zero",
+            Self::BadConversionToInteger => {
+                "use the saturating `trunc_sat` instructions, or check the range first"
+            }
+            Self::UnreachableCodeReached => {
+                "the module reached an `unreachable` instruction, often a failed assertion"
+            }
+        }
+    }
+
+    /// Gets the anchor of the section documenting this trap code, without
+    /// the leading `#`.
+    pub const fn doc_anchor(self) -> &'static str {
+        match self {
+            Self::StackOverflow => "stack-overflow",
+            Self::HeapAccessOutOfBounds => "heap-access-out-of-bounds",
+            Self::HeapMisaligned => "heap-misaligned",
+            Self::TableAccessOutOfBounds => "table-access-out-of-bounds",
+            Self::OutOfBounds => "out-of-bounds",
+            Self::IndirectCallToNull => "indirect-call-to-null",
+            Self::BadSignature => "bad-signature",
+            Self::IntegerOverflow => "integer-overflow",
+            Self::IntegerDivisionByZero => "integer-division-by-zero",
+            Self::BadConversionToInteger => "bad-conversion-to-integer",
+            Self::UnreachableCodeReached => "unreachable-code-reached",
+            Self::UnalignedAtomic => "unaligned-atomic",
+            Self::AtomicAccessOutOfBounds => "atomic-access-out-of-bounds",
+            Self::UninitializedElement => "uninitialized-element",
+        }
+    }
+
+    /// Gets the message for this trap code in the form used by the wasm C
+    /// API: the trap text of the reference interpreter, as a null-terminated
+    /// byte vector.
+    ///
+    /// The text differs from `message()` for the trap codes that add detail
+    /// to the spec text, or that have no equivalent in the spec:
+    ///
+    /// * `TableAccessOutOfBounds` is reported as `undefined element`,
+    /// * `IndirectCallToNull` as `uninitialized element`,
+    /// * `OutOfBounds` and `AtomicAccessOutOfBounds` as
+    ///   `out of bounds memory access`,
+    /// * `HeapMisaligned` and `UnalignedAtomic` as `unaligned atomic`,
+    /// * `UnreachableCodeReached` as `unreachable executed`.
+    pub fn c_api_message(self) -> Vec<u8> {
+        let message = match self {
+            Self::TableAccessOutOfBounds => "undefined element",
+            Self::IndirectCallToNull | Self::UninitializedElement => "uninitialized element",
+            Self::OutOfBounds | Self::AtomicAccessOutOfBounds => {
+                Self::HeapAccessOutOfBounds.message()
+            }
+            Self::HeapMisaligned | Self::UnalignedAtomic => "unaligned atomic",
+            Self::UnreachableCodeReached => "unreachable executed",
+            other => other.message(),
+        };
+        let mut bytes = Vec::with_capacity(message.len() + 1);
+        bytes.extend_from_slice(message.as_bytes());
+        bytes.push(0);
+        bytes
+    }
+
+    /// Gets the short identifier of this trap code, as printed by `Display`
+    /// and accepted by `FromStr`.
+    ///
+    /// Unlike `to_string`, this doesn't allocate.
+    pub const fn as_static_str(self) -> &'static str {
+        match self {
             Self::StackOverflow => "stk_ovf",
             Self::HeapAccessOutOfBounds => "heap_get_oob",
             Self::HeapMisaligned => "heap_misaligned",
@@ -100,8 +336,271 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
-        };
-        f.write_str(identifier)
+            Self::AtomicAccessOutOfBounds => "atomic_oob",
+            Self::UninitializedElement => "uninit_elem",
+        }
+    }
+
+    /// Gets the value of the label identifying this trap code in metrics,
+    /// such as a Prometheus counter of traps.
+    ///
+    /// This is the short identifier of the trap code, which never changes
+    /// once released, so that renaming a variant doesn't break dashboards.
+    pub const fn metric_label(self) -> &'static str {
+        self.as_static_str()
+    }
+
+    /// Gets the OpenTelemetry status code of a span that ended with this
+    /// trap, which is always `ERROR`.
+    pub const fn otel_status_code(self) -> &'static str {
+        "ERROR"
+    }
+
+    /// Gets the OpenTelemetry status description of a span that ended with
+    /// this trap, which is its message.
+    pub fn otel_status_description(self) -> &'static str {
+        self.message()
+    }
+
+    /// Gets the OpenTelemetry attributes of a span that ended with this
+    /// trap: `trap.code`, its metric label, and `trap.category`, the kind of
+    /// check that failed.
+    pub const fn otel_attributes(self) -> [(&'static str, &'static str); 2] {
+        [
+            ("trap.code", self.metric_label()),
+            ("trap.category", self.category()),
+        ]
+    }
+
+    /// Gets the kind of check that raised this trap, for `otel_attributes`.
+    const fn category(self) -> &'static str {
+        match self {
+            Self::StackOverflow => "stack",
+            Self::HeapAccessOutOfBounds
+            | Self::HeapMisaligned
+            | Self::OutOfBounds
+            | Self::UnalignedAtomic
+            | Self::AtomicAccessOutOfBounds => "memory",
+            Self::TableAccessOutOfBounds
+            | Self::IndirectCallToNull
+            | Self::BadSignature
+            | Self::UninitializedElement => "call",
+            Self::IntegerOverflow | Self::IntegerDivisionByZero | Self::BadConversionToInteger => {
+                "arithmetic"
+            }
+            Self::UnreachableCodeReached => "unreachable",
+        }
+    }
+
+    /// Gets the name of the equivalent `wasmtime::TrapCode` variant, for
+    /// tools comparing traps across runtimes.
+    ///
+    /// Wasmtime has no equivalent for `OutOfBounds`, `AtomicAccessOutOfBounds`
+    /// (both `MemoryOutOfBounds`), `UnalignedAtomic` (`HeapMisaligned`) and
+    /// `UninitializedElement` (`IndirectCallToNull`): those map to the closest
+    /// wasmtime trap, and don't round-trip through `from_wasmtime_name`.
+    pub const fn wasmtime_name(self) -> &'static str {
+        match self {
+            Self::StackOverflow => "StackOverflow",
+            Self::HeapAccessOutOfBounds | Self::OutOfBounds | Self::AtomicAccessOutOfBounds => {
+                "MemoryOutOfBounds"
+            }
+            Self::HeapMisaligned | Self::UnalignedAtomic => "HeapMisaligned",
+            Self::TableAccessOutOfBounds => "TableOutOfBounds",
+            Self::IndirectCallToNull | Self::UninitializedElement => "IndirectCallToNull",
+            Self::BadSignature => "BadSignature",
+            Self::IntegerOverflow => "IntegerOverflow",
+            Self::IntegerDivisionByZero => "IntegerDivisionByZero",
+            Self::BadConversionToInteger => "BadConversionToInteger",
+            Self::UnreachableCodeReached => "UnreachableCodeReached",
+        }
+    }
+
+    /// Gets the trap code equivalent to the `wasmtime::TrapCode` variant
+    /// named `name`, if any. See `wasmtime_name`.
+    pub fn from_wasmtime_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "StackOverflow" => Self::StackOverflow,
+            "MemoryOutOfBounds" => Self::HeapAccessOutOfBounds,
+            "HeapMisaligned" => Self::HeapMisaligned,
+            "TableOutOfBounds" => Self::TableAccessOutOfBounds,
+            "IndirectCallToNull" => Self::IndirectCallToNull,
+            "BadSignature" => Self::BadSignature,
+            "IntegerOverflow" => Self::IntegerOverflow,
+            "IntegerDivisionByZero" => Self::IntegerDivisionByZero,
+            "BadConversionToInteger" => Self::BadConversionToInteger,
+            "UnreachableCodeReached" => Self::UnreachableCodeReached,
+            _ => return None,
+        })
+    }
+
+    /// Gets a unique name for a libcall raising this trap code: the
+    /// `wasmer_vm_raise_trap_` prefix followed by the short identifier of
+    /// the trap code.
+    ///
+    /// These names are reserved, the runtime does not define them: the
+    /// LLVM backend raises all the trap codes through the
+    /// `wasmer_vm_raise_trap` libcall, passing the trap code as argument.
+    pub const fn llvm_libcall_name(self) -> &'static str {
+        match self {
+            Self::StackOverflow => "wasmer_vm_raise_trap_stk_ovf",
+            Self::HeapAccessOutOfBounds => "wasmer_vm_raise_trap_heap_get_oob",
+            Self::HeapMisaligned => "wasmer_vm_raise_trap_heap_misaligned",
+            Self::TableAccessOutOfBounds => "wasmer_vm_raise_trap_table_get_oob",
+            Self::OutOfBounds => "wasmer_vm_raise_trap_oob",
+            Self::IndirectCallToNull => "wasmer_vm_raise_trap_icall_null",
+            Self::BadSignature => "wasmer_vm_raise_trap_bad_sig",
+            Self::IntegerOverflow => "wasmer_vm_raise_trap_int_ovf",
+            Self::IntegerDivisionByZero => "wasmer_vm_raise_trap_int_divz",
+            Self::BadConversionToInteger => "wasmer_vm_raise_trap_bad_toint",
+            Self::UnreachableCodeReached => "wasmer_vm_raise_trap_unreachable",
+            Self::UnalignedAtomic => "wasmer_vm_raise_trap_unalign_atom",
+            Self::AtomicAccessOutOfBounds => "wasmer_vm_raise_trap_atomic_oob",
+            Self::UninitializedElement => "wasmer_vm_raise_trap_uninit_elem",
+        }
+    }
+
+    /// Collapses the trap codes that different compilers report for the same
+    /// fault into a single canonical trap code.
+    ///
+    /// * `OutOfBounds` (LLVM) is normalized to `HeapAccessOutOfBounds` (Cranelift).
+    /// * `HeapMisaligned` (Cranelift) is normalized to `UnalignedAtomic` (LLVM).
+    /// * `AtomicAccessOutOfBounds` (Singlepass) is normalized to
+    ///   `HeapAccessOutOfBounds` (Cranelift, LLVM).
+    ///
+    /// All the other trap codes are already canonical.
+    pub fn normalize(self) -> Self {
+        match self {
+            Self::OutOfBounds => Self::HeapAccessOutOfBounds,
+            Self::HeapMisaligned => Self::UnalignedAtomic,
+            Self::AtomicAccessOutOfBounds => Self::HeapAccessOutOfBounds,
+            other => other,
+        }
+    }
+
+    /// Whether two trap codes, reported by different compilers for the same
+    /// program, should be considered the same trap when comparing the
+    /// compilers' results (eg. when differential fuzzing).
+    ///
+    /// The trap codes are compared after `normalize`, so the equivalence
+    /// classes are:
+    ///
+    /// * memory out-of-bounds accesses: `HeapAccessOutOfBounds`, `OutOfBounds`
+    ///   and `AtomicAccessOutOfBounds`,
+    /// * misaligned atomic accesses: `UnalignedAtomic` and `HeapMisaligned`,
+    /// * every other trap code on its own.
+    pub fn differential_eq(self, other: Self) -> bool {
+        self.normalize() == other.normalize()
+    }
+
+    /// Whether the instance should be discarded after this trap.
+    ///
+    /// A stack overflow can happen in the middle of any operation, leaving
+    /// the instance in an indeterminate state. All the other traps happen
+    /// at a well-defined point, before the faulting instruction has any
+    /// effect, so the instance can still be used afterwards.
+    pub const fn poisons_instance(self) -> bool {
+        matches!(self, Self::StackOverflow)
+    }
+
+    /// Whether this trap may be handed to a wasm exception handler, for
+    /// embedders that turn traps into exceptions.
+    ///
+    /// The exception handling proposal itself never lets a `catch` or
+    /// `catch_all` intercept a trap: traps always unwind to the host. When
+    /// an embedder opts into rethrowing traps as exceptions, only the traps
+    /// raised by a single faulting instruction (arithmetic, bounds and
+    /// signature checks) can be caught. `StackOverflow` leaves no stack to
+    /// run the handler on and poisons the instance, and
+    /// `UnreachableCodeReached` is how a module aborts on purpose.
+    pub const fn catchable_by_wasm(self) -> bool {
+        !matches!(self, Self::StackOverflow | Self::UnreachableCodeReached)
+    }
+
+    /// Whether this trap code is raised while running the module, as the
+    /// spec's `assert_trap`, rather than while linking or instantiating it,
+    /// as its `assert_unlinkable` and `assert_uninstantiable`.
+    ///
+    /// A trap of the start function is a runtime trap. All the current trap
+    /// codes are; the match is exhaustive so that a link-time code added
+    /// later has to opt out.
+    pub const fn is_runtime_trap(self) -> bool {
+        match self {
+            Self::StackOverflow
+            | Self::HeapAccessOutOfBounds
+            | Self::HeapMisaligned
+            | Self::TableAccessOutOfBounds
+            | Self::OutOfBounds
+            | Self::IndirectCallToNull
+            | Self::BadSignature
+            | Self::IntegerOverflow
+            | Self::IntegerDivisionByZero
+            | Self::BadConversionToInteger
+            | Self::UnreachableCodeReached
+            | Self::UnalignedAtomic
+            | Self::AtomicAccessOutOfBounds
+            | Self::UninitializedElement => true,
+        }
+    }
+
+    /// Whether this trap most likely reveals a bug of the guest, rather than
+    /// untrusted input reaching a check of a correct program.
+    ///
+    /// This is an advisory heuristic, for triage: reaching `unreachable`,
+    /// calling through a null, uninitialized or mistyped table element, and
+    /// misaligned atomics are rarely input-driven. Bounds checks, arithmetic
+    /// faults and stack exhaustion are routinely triggered by hostile
+    /// offsets, operands or nesting depths against correct code.
+    pub const fn likely_bug(self) -> bool {
+        matches!(
+            self,
+            Self::UnreachableCodeReached
+                | Self::BadSignature
+                | Self::IndirectCallToNull
+                | Self::UninitializedElement
+                | Self::HeapMisaligned
+                | Self::UnalignedAtomic
+        )
+    }
+
+    /// Whether execution can resume after this trap by substituting the
+    /// saturated result of the faulting instruction.
+    ///
+    /// This is only the case for failed float-to-int conversions, whose
+    /// `trunc_sat` counterparts clamp the result instead of trapping.
+    pub const fn is_saturatable(self) -> bool {
+        matches!(self, Self::BadConversionToInteger)
+    }
+
+    /// Gets the process exit code used to report this trap code, as done by
+    /// `wasmer run --trap-as-exit-code`.
+    ///
+    /// The exit codes start at 100, to stay clear of the codes commonly used
+    /// by programs and shells.
+    pub const fn exit_code(self) -> i32 {
+        100 + self as i32
+    }
+
+    /// The rank of this trap code in [`resolve_precedence`], lowest first.
+    const fn precedence(self) -> u8 {
+        match self {
+            Self::StackOverflow => 0,
+            Self::TableAccessOutOfBounds => 1,
+            Self::IndirectCallToNull | Self::UninitializedElement => 2,
+            Self::BadSignature => 3,
+            Self::HeapAccessOutOfBounds | Self::AtomicAccessOutOfBounds | Self::OutOfBounds => 4,
+            Self::HeapMisaligned | Self::UnalignedAtomic => 5,
+            Self::IntegerDivisionByZero => 6,
+            Self::IntegerOverflow => 7,
+            Self::BadConversionToInteger => 8,
+            Self::UnreachableCodeReached => 9,
+        }
+    }
+}
+
+impl Display for TrapCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_static_str())
     }
 }
 
@@ -122,17 +621,229 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(TrapCode::BadConversionToInteger),
             "unreachable" => Ok(TrapCode::UnreachableCodeReached),
             "unalign_atom" => Ok(TrapCode::UnalignedAtomic),
+            "atomic_oob" => Ok(TrapCode::AtomicAccessOutOfBounds),
+            "uninit_elem" => Ok(TrapCode::UninitializedElement),
             _ => Err(()),
         }
     }
 }
 
+/// Tallies the `(expected, observed)` trap code pairs of `results` that
+/// don't match, eg. to render the confusion matrix of a spec-test report.
+pub fn summarize_trap_mismatches(
+    results: &[(TrapCode, TrapCode)],
+) -> HashMap<(TrapCode, TrapCode), usize> {
+    let mut mismatches = HashMap::new();
+    for &(expected, observed) in results {
+        if expected != observed {
+            *mismatches.entry((expected, observed)).or_insert(0) += 1;
+        }
+    }
+    mismatches
+}
+
+/// Picks the trap code to report when an instruction meets several trap
+/// conditions at once, in the order the spec checks them:
+///
+/// - the stack is exhausted on entry of a function, before it executes;
+/// - `call_indirect` checks that the index is in the table's bounds, then
+///   that the element isn't null, then its signature;
+/// - atomic accesses check that the address is in bounds before checking
+///   its alignment;
+/// - signed division checks for a zero divisor before an overflow.
+///
+/// Trap codes that no instruction can raise together are ranked
+/// arbitrarily, and the first of equally ranked candidates is picked.
+///
+/// # Panics
+///
+/// Panics if `candidates` is empty.
+pub fn resolve_precedence(candidates: &[TrapCode]) -> TrapCode {
+    *candidates
+        .iter()
+        .min_by_key(|code| code.precedence())
+        .expect("no trap code to pick from")
+}
+
+/// Returns the trap codes a module using the WebAssembly proposals
+/// `features` may trap with, ie. the MVP trap codes and the ones
+/// introduced by any of `features`.
+pub fn codes_for_features(features: &[WasmProposal]) -> Vec<TrapCode> {
+    TrapCode::all()
+        .filter(|code| {
+            let proposal = code.proposal();
+            proposal == WasmProposal::MVP || features.contains(&proposal)
+        })
+        .collect()
+}
+
+/// Computes `i32.div_s`, or the trap code it traps with.
+///
+/// Dividing by zero traps with `IntegerDivisionByZero`, and `i32::MIN / -1`,
+/// which doesn't fit, with `IntegerOverflow`.
+pub fn check_i32_div(lhs: i32, rhs: i32) -> Result<i32, TrapCode> {
+    if rhs == 0 {
+        Err(TrapCode::IntegerDivisionByZero)
+    } else {
+        lhs.checked_div(rhs).ok_or(TrapCode::IntegerOverflow)
+    }
+}
+
+/// Computes `i32.rem_s`, or the trap code it traps with.
+///
+/// Dividing by zero traps with `IntegerDivisionByZero`. Unlike the division,
+/// `i32::MIN % -1` doesn't trap, and is 0.
+pub fn check_i32_rem(lhs: i32, rhs: i32) -> Result<i32, TrapCode> {
+    if rhs == 0 {
+        Err(TrapCode::IntegerDivisionByZero)
+    } else {
+        Ok(lhs.wrapping_rem(rhs))
+    }
+}
+
+/// Computes `i64.div_s`, or the trap code it traps with, like
+/// `check_i32_div`.
+pub fn check_i64_div(lhs: i64, rhs: i64) -> Result<i64, TrapCode> {
+    if rhs == 0 {
+        Err(TrapCode::IntegerDivisionByZero)
+    } else {
+        lhs.checked_div(rhs).ok_or(TrapCode::IntegerOverflow)
+    }
+}
+
+/// Computes `i64.rem_s`, or the trap code it traps with, like
+/// `check_i32_rem`.
+pub fn check_i64_rem(lhs: i64, rhs: i64) -> Result<i64, TrapCode> {
+    if rhs == 0 {
+        Err(TrapCode::IntegerDivisionByZero)
+    } else {
+        Ok(lhs.wrapping_rem(rhs))
+    }
+}
+
+/// Translations of the trap code messages, for `TrapCode::localized_message`.
+///
+/// A table is parsed from lines of `id=message`, where `id` is the short
+/// identifier of a trap code, as returned by `TrapCode::as_static_str`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LocalizationTable {
+    messages: HashMap<TrapCode, String>,
+}
+
+impl LocalizationTable {
+    /// Creates an empty table, translating nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a table from its `id=message` lines.
+    pub fn parse(source: &str) -> Result<Self, LocalizationError> {
+        let mut messages = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, message) = line
+                .split_once('=')
+                .ok_or(LocalizationError::MissingSeparator { line: index + 1 })?;
+            let code = id
+                .trim()
+                .parse()
+                .map_err(|()| LocalizationError::UnknownTrapCode {
+                    line: index + 1,
+                    id: id.trim().to_string(),
+                })?;
+            messages.insert(code, message.trim().to_string());
+        }
+        Ok(Self { messages })
+    }
+
+    /// Gets the translated message for `code`, if any.
+    pub fn get(&self, code: TrapCode) -> Option<&str> {
+        self.messages.get(&code).map(String::as_str)
+    }
+}
+
+/// Error type describing things that can go wrong when parsing a
+/// `LocalizationTable`.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LocalizationError {
+    /// A line has no `=` between the identifier and the message.
+    #[error("line {line}: expected `id=message`")]
+    MissingSeparator {
+        /// The line number, starting at 1.
+        line: usize,
+    },
+    /// The identifier of a line isn't the one of any `TrapCode`.
+    #[error("line {line}: unknown trap code `{id}`")]
+    UnknownTrapCode {
+        /// The line number, starting at 1.
+        line: usize,
+        /// The unknown identifier.
+        id: String,
+    },
+}
+
+/// A set of trap codes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct TrapCodeSet {
+    /// The bit `1 << code` is set for each trap code of the set.
+    bits: u32,
+}
+
+impl TrapCodeSet {
+    /// Creates an empty set.
+    pub const fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Creates the set of all the trap codes.
+    pub fn all() -> Self {
+        TrapCode::all().collect()
+    }
+
+    /// Returns this set with `code` added.
+    pub const fn with(self, code: TrapCode) -> Self {
+        Self {
+            bits: self.bits | (1 << code as u32),
+        }
+    }
+
+    /// Adds `code` to the set.
+    pub fn insert(&mut self, code: TrapCode) {
+        *self = self.with(code);
+    }
+
+    /// Whether `code` is in the set.
+    pub const fn contains(self, code: TrapCode) -> bool {
+        self.bits & (1 << code as u32) != 0
+    }
+
+    /// Whether the set is empty.
+    pub const fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+}
+
+impl FromIterator<TrapCode> for TrapCodeSet {
+    fn from_iter<I: IntoIterator<Item = TrapCode>>(codes: I) -> Self {
+        let mut set = Self::empty();
+        for code in codes {
+            set.insert(code);
+        }
+        set
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 12] = [
+    const CODES: [TrapCode; 14] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -145,6 +856,8 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::AtomicAccessOutOfBounds,
+        TrapCode::UninitializedElement,
     ];
 
     #[test]
@@ -161,4 +874,556 @@ mod tests {
         assert_eq!("user-1".parse::<TrapCode>(), Err(()));
         assert_eq!("users".parse::<TrapCode>(), Err(()));
     }
+
+    #[test]
+    fn atomic_access_out_of_bounds() {
+        let tc = TrapCode::AtomicAccessOutOfBounds;
+        assert_eq!(tc.to_string(), "atomic_oob");
+        assert_eq!("atomic_oob".parse(), Ok(tc));
+        // The message still matches what the spec tests expect.
+        assert!(tc
+            .message()
+            .contains(TrapCode::HeapAccessOutOfBounds.message()));
+    }
+
+    #[test]
+    fn uninitialized_element() {
+        let null = TrapCode::IndirectCallToNull;
+        let uninit = TrapCode::UninitializedElement;
+        assert_eq!(null.to_string(), "icall_null");
+        assert_eq!("icall_null".parse(), Ok(null));
+        assert_eq!(uninit.to_string(), "uninit_elem");
+        assert_eq!("uninit_elem".parse(), Ok(uninit));
+        assert_ne!(null.message(), uninit.message());
+        // Both messages still match what the spec tests expect.
+        assert!(null.message().contains("uninitialized element"));
+        assert!(uninit.message().contains("uninitialized element"));
+    }
+
+    #[test]
+    fn all() {
+        assert_eq!(TrapCode::all().collect::<Vec<_>>(), CODES);
+    }
+
+    #[test]
+    fn proposal() {
+        assert_eq!(
+            TrapCode::AtomicAccessOutOfBounds.proposal(),
+            WasmProposal::Threads
+        );
+        assert_eq!(TrapCode::UnalignedAtomic.proposal(), WasmProposal::Threads);
+        assert_eq!(
+            TrapCode::UninitializedElement.proposal(),
+            WasmProposal::ReferenceTypes
+        );
+        assert_eq!(
+            TrapCode::IntegerDivisionByZero.proposal(),
+            WasmProposal::MVP
+        );
+        assert_eq!(
+            TrapCode::all_for_proposal(WasmProposal::ReferenceTypes).collect::<Vec<_>>(),
+            [TrapCode::UninitializedElement]
+        );
+        assert_eq!(
+            TrapCode::all_for_proposal(WasmProposal::ExceptionHandling).count(),
+            0
+        );
+        let proposals = [
+            WasmProposal::MVP,
+            WasmProposal::Threads,
+            WasmProposal::ReferenceTypes,
+            WasmProposal::ExceptionHandling,
+        ];
+        let total: usize = proposals
+            .iter()
+            .map(|&p| TrapCode::all_for_proposal(p).count())
+            .sum();
+        assert_eq!(total, CODES.len());
+    }
+
+    /// The pairs of the edge cases of the division, see the `proptest`
+    /// feature for arbitrary operands.
+    fn operands<T: Copy>(edges: &[T]) -> Vec<(T, T)> {
+        edges
+            .iter()
+            .flat_map(|&lhs| edges.iter().map(move |&rhs| (lhs, rhs)))
+            .collect()
+    }
+
+    fn assert_i64_div_rem(lhs: i64, rhs: i64) {
+        let (div, rem) = (check_i64_div(lhs, rhs), check_i64_rem(lhs, rhs));
+        if rhs == 0 {
+            assert_eq!(div, Err(TrapCode::IntegerDivisionByZero));
+            assert_eq!(rem, Err(TrapCode::IntegerDivisionByZero));
+        } else if lhs == i64::MIN && rhs == -1 {
+            assert_eq!(div, Err(TrapCode::IntegerOverflow));
+            assert_eq!(rem, Ok(0));
+        } else {
+            assert_eq!(div, Ok(lhs.wrapping_div(rhs)), "{} / {}", lhs, rhs);
+            assert_eq!(rem, Ok(lhs.wrapping_rem(rhs)), "{} % {}", lhs, rhs);
+        }
+    }
+
+    fn assert_i32_div_rem(lhs: i32, rhs: i32) {
+        let (div, rem) = (check_i32_div(lhs, rhs), check_i32_rem(lhs, rhs));
+        if rhs == 0 {
+            assert_eq!(div, Err(TrapCode::IntegerDivisionByZero));
+            assert_eq!(rem, Err(TrapCode::IntegerDivisionByZero));
+        } else if lhs == i32::MIN && rhs == -1 {
+            assert_eq!(div, Err(TrapCode::IntegerOverflow));
+            assert_eq!(rem, Ok(0));
+        } else {
+            assert_eq!(div, Ok(lhs.wrapping_div(rhs)), "{} / {}", lhs, rhs);
+            assert_eq!(rem, Ok(lhs.wrapping_rem(rhs)), "{} % {}", lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn check_i64_div_rem() {
+        for (lhs, rhs) in operands(&[0, 1, -1, 2, -2, i64::MIN, i64::MAX]) {
+            assert_i64_div_rem(lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn check_i32_div_rem() {
+        for (lhs, rhs) in operands(&[0, 1, -1, 2, -2, i32::MIN, i32::MAX]) {
+            assert_i32_div_rem(lhs, rhs);
+        }
+        assert_eq!(check_i32_div(i32::MIN, -1), Err(TrapCode::IntegerOverflow));
+        assert_eq!(check_i32_rem(i32::MIN, -1), Ok(0));
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn check_i64_div_rem_arbitrary(lhs: i64, rhs: i64) {
+            assert_i64_div_rem(lhs, rhs);
+        }
+
+        #[test]
+        fn check_i32_div_rem_arbitrary(lhs: i32, rhs: i32) {
+            assert_i32_div_rem(lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn from_io_error_kind() {
+        assert_eq!(
+            TrapCode::from_io_error_kind(io::ErrorKind::UnexpectedEof),
+            Some(TrapCode::OutOfBounds)
+        );
+        assert_eq!(
+            TrapCode::from_io_error_kind(io::ErrorKind::WriteZero),
+            Some(TrapCode::OutOfBounds)
+        );
+        assert_eq!(
+            TrapCode::from_io_error_kind(io::ErrorKind::PermissionDenied),
+            None
+        );
+        assert_eq!(TrapCode::from_io_error_kind(io::ErrorKind::NotFound), None);
+        assert_eq!(
+            TrapCode::from_io_error_kind(io::ErrorKind::OutOfMemory),
+            None
+        );
+    }
+
+    #[test]
+    fn codes_for_features() {
+        let mvp = super::codes_for_features(&[]);
+        assert_eq!(mvp, super::codes_for_features(&[WasmProposal::MVP]));
+        assert!(mvp.iter().all(|code| code.proposal() == WasmProposal::MVP));
+        assert!(!mvp.contains(&TrapCode::UnalignedAtomic));
+        assert!(!mvp.contains(&TrapCode::UninitializedElement));
+
+        let threads = super::codes_for_features(&[WasmProposal::Threads]);
+        assert!(threads.contains(&TrapCode::UnalignedAtomic));
+        assert!(threads.contains(&TrapCode::IntegerDivisionByZero));
+        assert!(!threads.contains(&TrapCode::UninitializedElement));
+        assert_eq!(
+            super::codes_for_features(&[WasmProposal::Threads, WasmProposal::ReferenceTypes]).len(),
+            CODES.len()
+        );
+    }
+
+    #[test]
+    fn doc_anchors_are_unique() {
+        for (i, a) in CODES.iter().enumerate() {
+            assert!(!a.hint().is_empty());
+            for b in &CODES[i + 1..] {
+                assert_ne!(a.doc_anchor(), b.doc_anchor());
+            }
+        }
+    }
+
+    #[test]
+    fn metric_label() {
+        for code in CODES {
+            assert_eq!(code.metric_label(), code.to_string());
+        }
+        assert_eq!(TrapCode::StackOverflow.metric_label(), "stk_ovf");
+        assert_eq!(
+            TrapCode::HeapAccessOutOfBounds.metric_label(),
+            "heap_get_oob"
+        );
+        assert_eq!(TrapCode::IntegerDivisionByZero.metric_label(), "int_divz");
+    }
+
+    #[test]
+    fn otel_attributes() {
+        let code = TrapCode::IntegerDivisionByZero;
+        assert_eq!(code.otel_status_code(), "ERROR");
+        assert_eq!(code.otel_status_description(), "integer divide by zero");
+        let attributes = code.otel_attributes();
+        let keys: Vec<_> = attributes.iter().map(|&(key, _)| key).collect();
+        assert_eq!(keys, ["trap.code", "trap.category"]);
+        assert_eq!(attributes[0].1, "int_divz");
+        assert_eq!(attributes[1].1, "arithmetic");
+    }
+
+    #[test]
+    fn wasmtime_name() {
+        // The trap codes without a wasmtime equivalent.
+        let lossy = [
+            TrapCode::OutOfBounds,
+            TrapCode::UnalignedAtomic,
+            TrapCode::AtomicAccessOutOfBounds,
+            TrapCode::UninitializedElement,
+        ];
+        for code in CODES {
+            let round_trip = TrapCode::from_wasmtime_name(code.wasmtime_name());
+            if lossy.contains(&code) {
+                assert_ne!(round_trip, Some(code));
+            } else {
+                assert_eq!(round_trip, Some(code));
+            }
+        }
+        assert_eq!(
+            TrapCode::HeapAccessOutOfBounds.wasmtime_name(),
+            "MemoryOutOfBounds"
+        );
+        assert_eq!(TrapCode::from_wasmtime_name("Interrupt"), None);
+    }
+
+    #[test]
+    fn llvm_libcall_names_are_unique() {
+        let names: HashSet<_> = TrapCode::all().map(TrapCode::llvm_libcall_name).collect();
+        assert_eq!(names.len(), CODES.len());
+        for code in CODES {
+            assert_eq!(
+                code.llvm_libcall_name(),
+                format!("wasmer_vm_raise_trap_{}", code.as_static_str())
+            );
+        }
+    }
+
+    #[test]
+    fn catchable_by_wasm() {
+        for code in CODES {
+            let expected = !matches!(
+                code,
+                TrapCode::StackOverflow | TrapCode::UnreachableCodeReached
+            );
+            assert_eq!(code.catchable_by_wasm(), expected, "{:?}", code);
+            if code.poisons_instance() {
+                assert!(!code.catchable_by_wasm());
+            }
+        }
+    }
+
+    #[test]
+    fn is_runtime_trap() {
+        for code in CODES {
+            assert!(code.is_runtime_trap(), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn likely_bug() {
+        let bugs = [
+            TrapCode::HeapMisaligned,
+            TrapCode::IndirectCallToNull,
+            TrapCode::BadSignature,
+            TrapCode::UnreachableCodeReached,
+            TrapCode::UnalignedAtomic,
+            TrapCode::UninitializedElement,
+        ];
+        for code in CODES {
+            assert_eq!(code.likely_bug(), bugs.contains(&code), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn trap_code_set() {
+        let set = TrapCodeSet::empty()
+            .with(TrapCode::HeapAccessOutOfBounds)
+            .with(TrapCode::UninitializedElement);
+        assert!(set.contains(TrapCode::HeapAccessOutOfBounds));
+        assert!(set.contains(TrapCode::UninitializedElement));
+        assert!(!set.contains(TrapCode::UnreachableCodeReached));
+        assert!(TrapCodeSet::empty().is_empty());
+        for code in CODES {
+            assert!(TrapCodeSet::all().contains(code));
+        }
+        assert_eq!(
+            set,
+            vec![
+                TrapCode::UninitializedElement,
+                TrapCode::HeapAccessOutOfBounds
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn summarize_trap_mismatches() {
+        use TrapCode::*;
+        let results = [
+            (HeapAccessOutOfBounds, HeapAccessOutOfBounds),
+            (HeapAccessOutOfBounds, OutOfBounds),
+            (IndirectCallToNull, UninitializedElement),
+            (HeapAccessOutOfBounds, OutOfBounds),
+            (IntegerOverflow, IntegerOverflow),
+        ];
+        let mismatches = super::summarize_trap_mismatches(&results);
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[&(HeapAccessOutOfBounds, OutOfBounds)], 2);
+        assert_eq!(mismatches[&(IndirectCallToNull, UninitializedElement)], 1);
+        assert!(super::summarize_trap_mismatches(&[]).is_empty());
+    }
+
+    #[test]
+    fn resolve_precedence() {
+        use TrapCode::*;
+        let pairs = [
+            (AtomicAccessOutOfBounds, UnalignedAtomic),
+            (HeapAccessOutOfBounds, HeapMisaligned),
+            (TableAccessOutOfBounds, IndirectCallToNull),
+            (UninitializedElement, BadSignature),
+            (IntegerDivisionByZero, IntegerOverflow),
+            (StackOverflow, UnreachableCodeReached),
+        ];
+        for &(first, second) in &pairs {
+            assert_eq!(super::resolve_precedence(&[first, second]), first);
+            assert_eq!(super::resolve_precedence(&[second, first]), first);
+        }
+        assert_eq!(
+            super::resolve_precedence(&[IntegerOverflow]),
+            IntegerOverflow
+        );
+        assert_eq!(
+            super::resolve_precedence(&[IndirectCallToNull, UninitializedElement]),
+            IndirectCallToNull
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no trap code to pick from")]
+    fn resolve_precedence_empty() {
+        super::resolve_precedence(&[]);
+    }
+
+    #[test]
+    fn message_matches_wast() {
+        // The texts of the upstream testsuite.
+        let expected = [
+            (TrapCode::IntegerDivisionByZero, "integer divide by zero"),
+            (TrapCode::IntegerOverflow, "integer overflow"),
+            (
+                TrapCode::BadConversionToInteger,
+                "invalid conversion to integer",
+            ),
+            (
+                TrapCode::HeapAccessOutOfBounds,
+                "out of bounds memory access",
+            ),
+            (
+                TrapCode::AtomicAccessOutOfBounds,
+                "out of bounds memory access",
+            ),
+            (
+                TrapCode::TableAccessOutOfBounds,
+                "out of bounds table access",
+            ),
+            (TrapCode::TableAccessOutOfBounds, "undefined element"),
+            (TrapCode::IndirectCallToNull, "uninitialized element"),
+            (TrapCode::IndirectCallToNull, "uninitialized element 2"),
+            (TrapCode::UninitializedElement, "uninitialized element 2"),
+            (TrapCode::BadSignature, "indirect call type mismatch"),
+            (TrapCode::UnreachableCodeReached, "unreachable"),
+            (TrapCode::StackOverflow, "call stack exhausted"),
+            (TrapCode::UnalignedAtomic, "unaligned atomic"),
+        ];
+        for &(code, text) in &expected {
+            assert!(code.message_matches_wast(text), "{:?}: {}", code, text);
+        }
+        assert!(!TrapCode::IntegerOverflow.message_matches_wast("integer divide by zero"));
+        assert!(!TrapCode::HeapAccessOutOfBounds.message_matches_wast("out of bounds table access"));
+        assert!(!TrapCode::UnreachableCodeReached.message_matches_wast("uninitialized element 2"));
+    }
+
+    #[test]
+    fn localized_message() {
+        let table = LocalizationTable::parse(
+            "# French\n\
+             stk_ovf = pile d'appels épuisée\n\
+             \n\
+             int_divz=division entière par zéro\n",
+        )
+        .unwrap();
+        assert_eq!(
+            TrapCode::StackOverflow.localized_message(&table),
+            "pile d'appels épuisée"
+        );
+        assert_eq!(
+            TrapCode::IntegerDivisionByZero.localized_message(&table),
+            "division entière par zéro"
+        );
+        assert_eq!(table.get(TrapCode::IntegerOverflow), None);
+        for code in CODES.iter().filter(|code| table.get(**code).is_none()) {
+            assert_eq!(code.localized_message(&table), code.message());
+        }
+        assert_eq!(
+            TrapCode::UnreachableCodeReached.localized_message(&LocalizationTable::new()),
+            "unreachable"
+        );
+    }
+
+    #[test]
+    fn localization_table_errors() {
+        assert_eq!(
+            LocalizationTable::parse("stk_ovf=ok\nint_ovf"),
+            Err(LocalizationError::MissingSeparator { line: 2 })
+        );
+        assert_eq!(
+            LocalizationTable::parse("overflow=débordement"),
+            Err(LocalizationError::UnknownTrapCode {
+                line: 1,
+                id: "overflow".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn from_u32() {
+        for r in &CODES {
+            assert_eq!(TrapCode::from_u32(*r as u32), Some(*r));
+        }
+        assert_eq!(TrapCode::from_u32(CODES.len() as u32), None);
+    }
+
+    #[test]
+    fn parse_lenient() {
+        assert_eq!(
+            TrapCode::parse_lenient("1"),
+            Ok(TrapCode::HeapAccessOutOfBounds)
+        );
+        assert_eq!(TrapCode::parse_lenient("999"), Err(()));
+        assert_eq!(TrapCode::parse_lenient("-1"), Err(()));
+        assert_eq!(TrapCode::parse_lenient("bogus"), Err(()));
+        for r in &CODES {
+            assert_eq!(TrapCode::parse_lenient(&r.to_string()), Ok(*r));
+            assert_eq!(TrapCode::parse_lenient(&(*r as u32).to_string()), Ok(*r));
+        }
+        // The strict parser doesn't accept integers.
+        assert_eq!("1".parse::<TrapCode>(), Err(()));
+    }
+
+    #[test]
+    fn c_api_message() {
+        assert_eq!(
+            TrapCode::IntegerDivisionByZero.c_api_message(),
+            b"integer divide by zero\0"
+        );
+        assert_eq!(
+            TrapCode::UnreachableCodeReached.c_api_message(),
+            b"unreachable executed\0"
+        );
+        assert_eq!(
+            TrapCode::IndirectCallToNull.c_api_message(),
+            b"uninitialized element\0"
+        );
+        assert_eq!(
+            TrapCode::AtomicAccessOutOfBounds.c_api_message(),
+            b"out of bounds memory access\0"
+        );
+        for r in &CODES {
+            let message = r.c_api_message();
+            assert_eq!(
+                message.iter().position(|&b| b == 0),
+                Some(message.len() - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn as_static_str() {
+        const STACK_OVERFLOW: &str = TrapCode::StackOverflow.as_static_str();
+        assert_eq!(STACK_OVERFLOW, "stk_ovf");
+        for r in &CODES {
+            assert_eq!(r.as_static_str(), r.to_string());
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            TrapCode::OutOfBounds.normalize(),
+            TrapCode::HeapAccessOutOfBounds.normalize()
+        );
+        assert_eq!(
+            TrapCode::HeapMisaligned.normalize(),
+            TrapCode::UnalignedAtomic.normalize()
+        );
+        assert_eq!(
+            TrapCode::AtomicAccessOutOfBounds.normalize(),
+            TrapCode::HeapAccessOutOfBounds
+        );
+        for r in &CODES {
+            assert_eq!(r.normalize().normalize(), r.normalize());
+        }
+        assert_eq!(
+            TrapCode::TableAccessOutOfBounds.normalize(),
+            TrapCode::TableAccessOutOfBounds
+        );
+    }
+
+    #[test]
+    fn differential_eq() {
+        assert!(TrapCode::OutOfBounds.differential_eq(TrapCode::HeapAccessOutOfBounds));
+        assert!(TrapCode::AtomicAccessOutOfBounds.differential_eq(TrapCode::OutOfBounds));
+        assert!(TrapCode::HeapMisaligned.differential_eq(TrapCode::UnalignedAtomic));
+        assert!(!TrapCode::HeapAccessOutOfBounds.differential_eq(TrapCode::UnalignedAtomic));
+        assert!(!TrapCode::TableAccessOutOfBounds.differential_eq(TrapCode::OutOfBounds));
+        assert!(!TrapCode::IntegerOverflow.differential_eq(TrapCode::IntegerDivisionByZero));
+        for r in &CODES {
+            assert!(r.differential_eq(*r));
+        }
+    }
+
+    #[test]
+    fn poisons_instance() {
+        for r in &CODES {
+            assert_eq!(r.poisons_instance(), *r == TrapCode::StackOverflow);
+        }
+    }
+
+    #[test]
+    fn is_saturatable() {
+        for r in &CODES {
+            assert_eq!(r.is_saturatable(), *r == TrapCode::BadConversionToInteger);
+        }
+    }
+
+    #[test]
+    fn exit_code() {
+        assert_eq!(TrapCode::StackOverflow.exit_code(), 100);
+        assert_eq!(TrapCode::IntegerDivisionByZero.exit_code(), 108);
+        for (i, a) in CODES.iter().enumerate() {
+            for b in &CODES[i + 1..] {
+                assert_ne!(a.exit_code(), b.exit_code());
+            }
+        }
+    }
 }
@@ -4,16 +4,20 @@
 //! WebAssembly trap handling, which is built on top of the lower-level
 //! signalhandling mechanisms.
 
+use super::panic_hook::enter_wasm;
+use super::sampler::BacktraceSampler;
 use super::trapcode::TrapCode;
+use crate::memory::linear_memory_offset;
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMTrampoline};
-use backtrace::Backtrace;
+use backtrace::{Backtrace, BacktraceFrame};
 use corosensei::stack::DefaultStack;
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
 use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
 use scopeguard::defer;
-use std::any::Any;
+use std::any::{type_name, Any};
 use std::cell::Cell;
 use std::error::Error;
+use std::fmt;
 use std::io;
 use std::mem;
 #[cfg(unix)]
@@ -21,6 +25,7 @@ use std::mem::MaybeUninit;
 use std::ptr::{self, NonNull};
 use std::sync::atomic::{compiler_fence, AtomicPtr, Ordering};
 use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
 
 cfg_if::cfg_if! {
     if #[cfg(unix)] {
@@ -524,10 +529,19 @@ pub unsafe fn resume_panic(payload: Box<dyn Any + Send>) -> ! {
 }
 
 /// Stores trace message with backtrace.
+///
+/// `Trap` implements `std::error::Error`, so it can be converted with `?`
+/// into error types such as `anyhow::Error`, and recovered later with
+/// `error.downcast_ref::<Trap>()`, eg. to get its `trap_code()`.
 #[derive(Debug)]
 pub enum Trap {
     /// A user-raised trap through `raise_user_trap`.
-    User(Box<dyn Error + Send + Sync>),
+    User {
+        /// The error of the host
+        error: Box<dyn Error + Send + Sync>,
+        /// Name of the concrete type of the error, for traps created with `Trap::user`
+        type_name: Option<&'static str>,
+    },
 
     /// A trap raised from the Wasm generated code
     ///
@@ -539,16 +553,38 @@ pub enum Trap {
         backtrace: Backtrace,
         /// Optional trapcode associated to the signal that caused the trap
         signal_trap: Option<TrapCode>,
+        /// Index of the memory that was accessed, if the module has more than one
+        memory_index: Option<u32>,
+        /// Name of the wasm function where this trap happened, if known
+        function_name: Option<String>,
+        /// Offset of the faulting access in the linear memory, if known
+        faulting_addr: Option<u64>,
+        /// Name of the export whose call led to this trap, if known
+        invoked_export: Option<String>,
+        /// When the trap was created, with the `trap-timestamps` feature
+        timestamp: TrapTimestamp,
+        /// Whether this is a copy returned by `Trap::redacted`
+        redacted: bool,
     },
 
     /// A trap raised from a wasm libcall
     ///
     /// Note: this trap is deterministic (assuming a deterministic host implementation)
     Lib {
-        /// Code of the trap.
-        trap_code: TrapCode,
+        /// Code of the trap, cleared by `Trap::redacted`
+        trap_code: Option<TrapCode>,
         /// Native stack backtrace at the time the trap occurred
         backtrace: Backtrace,
+        /// Index of the memory that was accessed, if the module has more than one
+        memory_index: Option<u32>,
+        /// Expected and actual signatures, for a `BadSignature` trap
+        signature_mismatch: Option<SignatureMismatch>,
+        /// Name of the export whose call led to this trap, if known
+        invoked_export: Option<String>,
+        /// When the trap was created, with the `trap-timestamps` feature
+        timestamp: TrapTimestamp,
+        /// Whether this is a copy returned by `Trap::redacted`
+        redacted: bool,
     },
 
     /// A trap indicating that the runtime was unable to allocate sufficient memory.
@@ -557,10 +593,134 @@ pub enum Trap {
     OOM {
         /// Native stack backtrace at the time the OOM occurred
         backtrace: Backtrace,
+        /// When the trap was created, with the `trap-timestamps` feature
+        timestamp: TrapTimestamp,
+        /// Whether this is a copy returned by `Trap::redacted`
+        redacted: bool,
+    },
+
+    /// A trap raised by the host when the guest exceeded a resource limit,
+    /// such as the fuel of a metering middleware.
+    ///
+    /// Note: this trap is deterministic for the `Fuel` resource only.
+    ResourceExhausted {
+        /// The exhausted resource.
+        kind: ResourceKind,
+        /// Whether this is a copy returned by `Trap::redacted`
+        redacted: bool,
     },
 }
 
+/// A resource whose host-imposed limit can be exceeded by the guest, see
+/// `Trap::ResourceExhausted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// The fuel (or gas) of a metering middleware.
+    Fuel,
+    /// The wall-clock time allowed for the execution.
+    WallClock,
+    /// The number of memory pages.
+    MemoryPages,
+    /// The number of table elements.
+    TableElements,
+}
+
+impl ResourceKind {
+    /// Gets the message for this resource kind
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Fuel => "out of fuel",
+            Self::WallClock => "wall-clock time limit exceeded",
+            Self::MemoryPages => "memory pages limit exceeded",
+            Self::TableElements => "table elements limit exceeded",
+        }
+    }
+}
+
+/// When a trap was created, see `Trap::timestamp`.
+///
+/// Without the `trap-timestamps` feature, this is zero-sized and records
+/// nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TrapTimestamp {
+    #[cfg(feature = "trap-timestamps")]
+    instant: Option<Instant>,
+}
+
+impl TrapTimestamp {
+    /// Gets the creation time of a new trap.
+    pub(super) fn now() -> Self {
+        Self {
+            #[cfg(feature = "trap-timestamps")]
+            instant: Some(Instant::now()),
+        }
+    }
+
+    /// Gets the recorded time, if any.
+    pub fn get(self) -> Option<Instant> {
+        #[cfg(feature = "trap-timestamps")]
+        {
+            self.instant
+        }
+        #[cfg(not(feature = "trap-timestamps"))]
+        {
+            None
+        }
+    }
+}
+
+/// The signatures of a failed indirect call, see
+/// `Trap::lib_signature_mismatch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignatureMismatch {
+    /// The index of the type expected by the `call_indirect`.
+    pub expected: u32,
+    /// The index of the type of the called function.
+    pub actual: u32,
+}
+
+/// The error of the traps returned by `Trap::redacted`.
+#[derive(Debug)]
+struct Redacted;
+
+impl fmt::Display for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "execution faulted")
+    }
+}
+
+impl Error for Redacted {}
+
+/// What caused a trap, see `Trap::origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrapOrigin {
+    /// The guest, with an instruction that trapped.
+    Guest,
+    /// The host, with an error of a host function or an exceeded limit.
+    Host,
+    /// The runtime itself, with a condition of the host system.
+    Runtime,
+}
+
 impl Trap {
+    /// Construct a new user trap with the given host error, recording the
+    /// name of its type for `Trap::user_error_type_name`.
+    pub fn user<E: Error + Send + Sync + 'static>(error: E) -> Self {
+        Trap::User {
+            error: Box::new(error),
+            type_name: Some(type_name::<E>()),
+        }
+    }
+
+    /// Construct a new user trap with the given host error, whose type was
+    /// already erased, eg. by `raise_user_trap`.
+    pub fn user_boxed(error: Box<dyn Error + Send + Sync>) -> Self {
+        Trap::User {
+            error,
+            type_name: None,
+        }
+    }
+
     /// Construct a new Wasm trap with the given source location and backtrace.
     ///
     /// Internally saves a backtrace when constructed.
@@ -569,17 +729,115 @@ impl Trap {
             pc,
             backtrace,
             signal_trap,
+            memory_index: None,
+            function_name: None,
+            faulting_addr: None,
+            invoked_export: None,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
+        }
+    }
+
+    /// Construct a new Wasm trap like `Trap::wasm`, for a trap that happened
+    /// in the wasm function named `function_name`.
+    pub fn wasm_named(
+        pc: usize,
+        backtrace: Backtrace,
+        signal_trap: Option<TrapCode>,
+        function_name: String,
+    ) -> Self {
+        Trap::Wasm {
+            pc,
+            backtrace,
+            signal_trap,
+            memory_index: None,
+            function_name: Some(function_name),
+            faulting_addr: None,
+            invoked_export: None,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
         }
     }
 
     /// Construct a new Wasm trap with the given trap code.
     ///
-    /// Internally saves a backtrace when constructed.
+    /// Internally saves a backtrace when constructed, unless the global
+    /// `BacktraceSampler` skips it for this trap code.
     pub fn lib(trap_code: TrapCode) -> Self {
-        let backtrace = Backtrace::new_unresolved();
+        let backtrace = if BacktraceSampler::global().should_capture(trap_code) {
+            Backtrace::new_unresolved()
+        } else {
+            Backtrace::from(Vec::new())
+        };
+        Trap::Lib {
+            trap_code: Some(trap_code),
+            backtrace,
+            memory_index: None,
+            signature_mismatch: None,
+            invoked_export: None,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
+        }
+    }
+
+    /// Construct a new Wasm trap with the given trap code, like `Trap::lib`,
+    /// keeping at most the `max_frames` innermost frames of the backtrace.
+    pub fn lib_with_depth(trap_code: TrapCode, max_frames: usize) -> Self {
+        let backtrace = if BacktraceSampler::global().should_capture(trap_code) {
+            let backtrace = Backtrace::new_unresolved();
+            let frames = backtrace.frames();
+            Backtrace::from(frames[..frames.len().min(max_frames)].to_vec())
+        } else {
+            Backtrace::from(Vec::new())
+        };
+        Trap::Lib {
+            trap_code: Some(trap_code),
+            backtrace,
+            memory_index: None,
+            signature_mismatch: None,
+            invoked_export: None,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
+        }
+    }
+
+    /// Construct a new `BadSignature` trap like `Trap::lib`, for an indirect
+    /// call expecting the type `expected` that found a function of type
+    /// `actual`.
+    pub fn lib_signature_mismatch(expected: u32, actual: u32) -> Self {
+        let mut trap = Self::lib(TrapCode::BadSignature);
+        if let Self::Lib {
+            signature_mismatch, ..
+        } = &mut trap
+        {
+            *signature_mismatch = Some(SignatureMismatch { expected, actual });
+        }
+        trap
+    }
+
+    /// Construct a new Wasm trap with the given trap code, without a
+    /// backtrace.
+    ///
+    /// This is meant for async host functions propagating a trap out of a
+    /// `poll`, where the current stack isn't the one that led to the trap:
+    /// the backtrace captured where the host function got suspended can be
+    /// attached later with `Trap::with_backtrace`.
+    pub fn deferred(trap_code: TrapCode) -> Self {
+        Self::deferred_with_backtrace(trap_code, Backtrace::from(Vec::new()))
+    }
+
+    /// Construct a new Wasm trap with the given trap code and a backtrace
+    /// captured beforehand, eg. at the suspension point of an async host
+    /// function.
+    pub fn deferred_with_backtrace(trap_code: TrapCode, backtrace: Backtrace) -> Self {
         Trap::Lib {
-            trap_code,
+            trap_code: Some(trap_code),
             backtrace,
+            memory_index: None,
+            signature_mismatch: None,
+            invoked_export: None,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
         }
     }
 
@@ -588,10 +846,520 @@ impl Trap {
     /// Internally saves a backtrace when constructed.
     pub fn oom() -> Self {
         let backtrace = Backtrace::new_unresolved();
-        Trap::OOM { backtrace }
+        Trap::OOM {
+            backtrace,
+            timestamp: TrapTimestamp::now(),
+            redacted: false,
+        }
+    }
+
+    /// Construct a new trap for an exceeded resource limit.
+    pub fn resource_exhausted(kind: ResourceKind) -> Self {
+        Trap::ResourceExhausted {
+            kind,
+            redacted: false,
+        }
+    }
+
+    /// Gets what caused this trap.
+    pub fn origin(&self) -> TrapOrigin {
+        match self {
+            Self::Wasm { .. } | Self::Lib { .. } => TrapOrigin::Guest,
+            Self::User { .. } | Self::ResourceExhausted { .. } => TrapOrigin::Host,
+            Self::OOM { .. } => TrapOrigin::Runtime,
+        }
+    }
+
+    /// Gets the trap code of this trap, if known.
+    ///
+    /// For `Wasm` traps, this is the trap code associated to the signal that
+    /// caused the trap, if any.
+    pub fn trap_code(&self) -> Option<TrapCode> {
+        match self {
+            Self::Wasm { signal_trap, .. } => *signal_trap,
+            Self::Lib { trap_code, .. } => *trap_code,
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets the trap code of this trap, guessing one when it isn't known.
+    ///
+    /// This is a heuristic, for reports that need a trap code: a `Wasm` trap
+    /// without a signal trap code comes from a bare fault, which is most
+    /// often an out-of-bounds memory access, so it is reported as
+    /// `HeapAccessOutOfBounds`.
+    ///
+    /// The traps without a fault of the guest to report, those not raised
+    /// by the guest (`User`, `OOM` and `ResourceExhausted`) and the redacted
+    /// ones, default to `UnreachableCodeReached`, as they abort the guest
+    /// like `unreachable` would. `Trap::trap_code` tells them apart, since
+    /// it returns `None` for them.
+    pub fn best_effort_code(&self) -> TrapCode {
+        match self {
+            Self::Wasm { redacted: true, .. } => TrapCode::UnreachableCodeReached,
+            Self::Wasm { signal_trap, .. } => {
+                signal_trap.unwrap_or(TrapCode::HeapAccessOutOfBounds)
+            }
+            Self::Lib { trap_code, .. } => trap_code.unwrap_or(TrapCode::UnreachableCodeReached),
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => {
+                TrapCode::UnreachableCodeReached
+            }
+        }
+    }
+
+    /// Handles this trap with `f` if it has the trap code `code`, or gives
+    /// it back otherwise.
+    ///
+    /// Calls can be chained with `Result::or_else` to dispatch a trap to the
+    /// first matching handler:
+    ///
+    /// ```
+    /// use wasmer_vm::{Trap, TrapCode};
+    ///
+    /// let handled = Trap::lib(TrapCode::IntegerDivisionByZero)
+    ///     .on_oom(|| "retry")
+    ///     .or_else(|trap| trap.on_code(TrapCode::IntegerDivisionByZero, || "recover"));
+    /// assert_eq!(handled.ok(), Some("recover"));
+    /// ```
+    pub fn on_code<R>(self, code: TrapCode, f: impl FnOnce() -> R) -> Result<R, Self> {
+        if self.trap_code() == Some(code) {
+            Ok(f())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Handles this trap with `f` if it is an `OOM` trap, or gives it back
+    /// otherwise, like `Trap::on_code`.
+    pub fn on_oom<R>(self, f: impl FnOnce() -> R) -> Result<R, Self> {
+        match self {
+            Self::OOM { .. } => Ok(f()),
+            trap => Err(trap),
+        }
+    }
+
+    /// Attaches the index of the memory that was accessed when the trap
+    /// happened.
+    ///
+    /// This is only set for modules with more than one memory, and has no
+    /// effect on `User`, `OOM` and `ResourceExhausted` traps.
+    pub fn with_memory_index(mut self, index: u32) -> Self {
+        match &mut self {
+            Self::Wasm { memory_index, .. } | Self::Lib { memory_index, .. } => {
+                *memory_index = Some(index)
+            }
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => {}
+        }
+        self
+    }
+
+    /// Replaces the backtrace of this trap, eg. with the one captured where
+    /// an async host function got suspended.
+    ///
+    /// This has no effect on `User` and `ResourceExhausted` traps, which
+    /// have no backtrace.
+    pub fn with_backtrace(mut self, new_backtrace: Backtrace) -> Self {
+        match &mut self {
+            Self::Wasm { backtrace, .. }
+            | Self::Lib { backtrace, .. }
+            | Self::OOM { backtrace, .. } => *backtrace = new_backtrace,
+            Self::User { .. } | Self::ResourceExhausted { .. } => {}
+        }
+        self
+    }
+
+    /// Attaches the offset in the linear memory of the access that caused
+    /// this trap, replacing the one found by the signal handler.
+    ///
+    /// It has no effect on traps other than `Wasm` ones.
+    pub fn with_faulting_address(mut self, addr: u64) -> Self {
+        if let Self::Wasm { faulting_addr, .. } = &mut self {
+            *faulting_addr = Some(addr);
+        }
+        self
+    }
+
+    /// Gets the offset in the linear memory of the access that caused this
+    /// trap, if known.
+    ///
+    /// For the traps raised by the signal handler, this is `None` when the
+    /// fault is outside of every linear memory, e.g. a stack overflow.
+    pub fn faulting_address(&self) -> Option<u64> {
+        match self {
+            Self::Wasm { faulting_addr, .. } => *faulting_addr,
+            Self::User { .. }
+            | Self::Lib { .. }
+            | Self::OOM { .. }
+            | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Attaches the name of the export whose call led to this trap.
+    ///
+    /// This is meant for the entry points calling into an instance, and has
+    /// no effect on `User`, `OOM` and `ResourceExhausted` traps.
+    pub fn with_invoked_export(mut self, name: impl Into<String>) -> Self {
+        match &mut self {
+            Self::Wasm { invoked_export, .. } | Self::Lib { invoked_export, .. } => {
+                *invoked_export = Some(name.into())
+            }
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => {}
+        }
+        self
+    }
+
+    /// Gets the name of the export whose call led to this trap, if known.
+    pub fn invoked_export(&self) -> Option<&str> {
+        match self {
+            Self::Wasm { invoked_export, .. } | Self::Lib { invoked_export, .. } => {
+                invoked_export.as_deref()
+            }
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets the expected and actual signatures of a failed indirect call,
+    /// if known.
+    pub fn signature_mismatch(&self) -> Option<SignatureMismatch> {
+        match self {
+            Self::Lib {
+                signature_mismatch, ..
+            } => *signature_mismatch,
+            Self::User { .. }
+            | Self::Wasm { .. }
+            | Self::OOM { .. }
+            | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets when this trap was created.
+    ///
+    /// This is only recorded with the `trap-timestamps` feature, and never
+    /// for `User` and `ResourceExhausted` traps, nor for traps decoded with
+    /// `Trap::from_wire`.
+    pub fn timestamp(&self) -> Option<Instant> {
+        match self {
+            Self::Wasm { timestamp, .. }
+            | Self::Lib { timestamp, .. }
+            | Self::OOM { timestamp, .. } => timestamp.get(),
+            Self::User { .. } | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets the time elapsed between `start`, eg. the start of the
+    /// invocation, and the creation of this trap, if it was recorded.
+    pub fn elapsed_since(&self, start: Instant) -> Option<Duration> {
+        self.timestamp()
+            .map(|timestamp| timestamp.saturating_duration_since(start))
+    }
+
+    /// Gets the index of the memory that was accessed when the trap
+    /// happened, if the module has more than one memory.
+    pub fn memory_index(&self) -> Option<u32> {
+        match self {
+            Self::Wasm { memory_index, .. } | Self::Lib { memory_index, .. } => *memory_index,
+            Self::User { .. } | Self::OOM { .. } | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets the name of the wasm function where the trap happened, if known.
+    pub fn function_name(&self) -> Option<&str> {
+        match self {
+            Self::Wasm { function_name, .. } => function_name.as_deref(),
+            Self::User { .. }
+            | Self::Lib { .. }
+            | Self::OOM { .. }
+            | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Gets the name of the concrete type of the error of a `User` trap, for
+    /// structured logs.
+    ///
+    /// The name is only known for traps created with `Trap::user`: it is
+    /// `None` for errors whose type was already erased, such as the ones
+    /// raised with `raise_user_trap`. Like `std::any::type_name`, it is
+    /// meant for diagnostics only.
+    pub fn user_error_type_name(&self) -> Option<&'static str> {
+        match self {
+            Self::User { type_name, .. } => *type_name,
+            Self::Wasm { .. }
+            | Self::Lib { .. }
+            | Self::OOM { .. }
+            | Self::ResourceExhausted { .. } => None,
+        }
+    }
+
+    /// Formats the trap like `Display`, replacing any machine address with
+    /// an `<addr>` placeholder.
+    ///
+    /// Unlike the `Display` output, this is stable across runs, which makes it
+    /// suitable for snapshot assertions.
+    pub fn display_stable(&self) -> String {
+        struct Stable<'a>(&'a Trap);
+
+        impl fmt::Display for Stable<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_message(f, true)
+            }
+        }
+
+        Stable(self).to_string()
+    }
+
+    /// Renders the guest frames of this trap, innermost first, as
+    /// `name at 0xpc` lines.
+    ///
+    /// `symbolize` maps a program counter to the name of the wasm function
+    /// containing it, and returns `None` for host code: those frames, which
+    /// are most of the native backtrace, are left out. For `Wasm` traps, the
+    /// trapping program counter is the innermost frame.
+    pub fn wasm_backtrace(&self, symbolize: impl Fn(usize) -> Option<String>) -> Vec<String> {
+        let (pc, backtrace) = match self {
+            Self::Wasm { pc, backtrace, .. } => (Some(*pc), backtrace),
+            Self::Lib { backtrace, .. } | Self::OOM { backtrace, .. } => (None, backtrace),
+            Self::User { .. } | Self::ResourceExhausted { .. } => return Vec::new(),
+        };
+        let mut pcs: Vec<usize> = pc.into_iter().collect();
+        for frame in backtrace.frames() {
+            let frame_pc = frame.ip() as usize;
+            if pcs.last() != Some(&frame_pc) {
+                pcs.push(frame_pc);
+            }
+        }
+        pcs.into_iter()
+            .filter_map(|pc| symbolize(pc).map(|name| format!("{} at {:#x}", name, pc)))
+            .collect()
+    }
+
+    /// Renders this trap as shown to users: the message, then for traps with
+    /// a trap code, a remediation hint and a link to the documentation of
+    /// the trap code under `docs_base_url`.
+    pub fn full_report(&self, docs_base_url: &str) -> String {
+        match self.trap_code() {
+            Some(trap_code) => format!(
+                "{}\nhint: {}\nsee: {}#{}",
+                self,
+                trap_code.hint(),
+                docs_base_url,
+                trap_code.doc_anchor()
+            ),
+            None => self.to_string(),
+        }
+    }
+
+    /// Gets the canonical gRPC status code and the message of a call that
+    /// failed with this trap.
+    ///
+    /// The codes are:
+    ///
+    /// * `UNKNOWN` (2) for `User` traps, whose host error can't be told
+    ///   apart,
+    /// * `DEADLINE_EXCEEDED` (4) for the `WallClock` resource,
+    /// * `RESOURCE_EXHAUSTED` (8) for `OOM` traps, the other resources and
+    ///   stack overflows,
+    /// * `OUT_OF_RANGE` (11) for out-of-bounds memory and table accesses,
+    /// * `INVALID_ARGUMENT` (3) for the arithmetic and conversion traps,
+    ///   routinely raised by the operands a guest was called with,
+    /// * `INTERNAL` (13) for the other traps, such as `unreachable` or a bad
+    ///   indirect call, which reveal a bug of the guest.
+    ///
+    /// `Wasm` traps without a trap code are mapped like their
+    /// `best_effort_code`.
+    pub fn grpc_status(&self) -> (i32, String) {
+        const UNKNOWN: i32 = 2;
+        const INVALID_ARGUMENT: i32 = 3;
+        const DEADLINE_EXCEEDED: i32 = 4;
+        const RESOURCE_EXHAUSTED: i32 = 8;
+        const OUT_OF_RANGE: i32 = 11;
+        const INTERNAL: i32 = 13;
+        let code = match self {
+            Self::User { .. } => UNKNOWN,
+            Self::OOM { .. } => RESOURCE_EXHAUSTED,
+            Self::ResourceExhausted {
+                kind: ResourceKind::WallClock,
+                ..
+            } => DEADLINE_EXCEEDED,
+            Self::ResourceExhausted { .. } => RESOURCE_EXHAUSTED,
+            Self::Wasm { .. } | Self::Lib { .. } => match self.best_effort_code() {
+                TrapCode::StackOverflow => RESOURCE_EXHAUSTED,
+                TrapCode::HeapAccessOutOfBounds
+                | TrapCode::TableAccessOutOfBounds
+                | TrapCode::OutOfBounds
+                | TrapCode::AtomicAccessOutOfBounds => OUT_OF_RANGE,
+                TrapCode::IntegerOverflow
+                | TrapCode::IntegerDivisionByZero
+                | TrapCode::BadConversionToInteger => INVALID_ARGUMENT,
+                TrapCode::HeapMisaligned
+                | TrapCode::IndirectCallToNull
+                | TrapCode::BadSignature
+                | TrapCode::UnreachableCodeReached
+                | TrapCode::UnalignedAtomic
+                | TrapCode::UninitializedElement => INTERNAL,
+            },
+        };
+        (code, self.to_string())
+    }
+
+    /// Gets a copy of this trap without its sensitive details, to show to
+    /// untrusted callers while the original is logged.
+    ///
+    /// The copy keeps the variant only: the trap code, the program counter,
+    /// the memory index, the faulting address, the signatures, the function
+    /// and export names, the timestamp and the backtrace are cleared, and
+    /// its message only says `execution faulted`. The error of a `User`
+    /// trap is replaced, since its message comes from the host.
+    pub fn redacted(&self) -> Self {
+        let backtrace = || Backtrace::from(Vec::<BacktraceFrame>::new());
+        match self {
+            Self::User { .. } => Self::user(Redacted),
+            Self::Wasm { .. } => Self::Wasm {
+                pc: 0,
+                backtrace: backtrace(),
+                signal_trap: None,
+                memory_index: None,
+                function_name: None,
+                faulting_addr: None,
+                invoked_export: None,
+                timestamp: TrapTimestamp::default(),
+                redacted: true,
+            },
+            Self::Lib { .. } => Self::Lib {
+                trap_code: None,
+                backtrace: backtrace(),
+                memory_index: None,
+                signature_mismatch: None,
+                invoked_export: None,
+                timestamp: TrapTimestamp::default(),
+                redacted: true,
+            },
+            Self::OOM { .. } => Self::OOM {
+                backtrace: backtrace(),
+                timestamp: TrapTimestamp::default(),
+                redacted: true,
+            },
+            Self::ResourceExhausted { kind, .. } => Self::ResourceExhausted {
+                kind: *kind,
+                redacted: true,
+            },
+        }
+    }
+
+    /// Whether this trap is a copy returned by `Trap::redacted`.
+    pub fn is_redacted(&self) -> bool {
+        match self {
+            Self::User { error, .. } => error.is::<Redacted>(),
+            Self::Wasm { redacted, .. }
+            | Self::Lib { redacted, .. }
+            | Self::OOM { redacted, .. }
+            | Self::ResourceExhausted { redacted, .. } => *redacted,
+        }
+    }
+
+    /// Writes the message of this trap, eliding machine addresses if `stable`.
+    fn fmt_message(&self, f: &mut fmt::Formatter<'_>, stable: bool) -> fmt::Result {
+        match self {
+            _ if self.is_redacted() => return write!(f, "{}", Redacted),
+            Self::User { error, .. } => return write!(f, "user trap: {}", error),
+            Self::OOM { .. } => return write!(f, "out of memory"),
+            Self::ResourceExhausted { kind, .. } => {
+                return write!(f, "resource exhausted: {}", kind.message())
+            }
+            Self::Wasm { .. } if stable => write!(f, "wasm trap at <addr>")?,
+            Self::Wasm { pc, .. } => write!(f, "wasm trap at {:#x}", pc)?,
+            Self::Lib { .. } => write!(f, "lib trap")?,
+        }
+        if let Some(function_name) = self.function_name() {
+            write!(f, " in {}", function_name)?;
+        }
+        if let Some(trap_code) = self.trap_code() {
+            write!(f, ": {}", trap_code.message())?;
+        }
+        if let Some(SignatureMismatch { expected, actual }) = self.signature_mismatch() {
+            write!(f, ": expected type #{}, got type #{}", expected, actual)?;
+        }
+        match self.faulting_address() {
+            Some(_) if stable => write!(f, " at offset <addr>")?,
+            Some(faulting_address) => write!(f, " at offset {:#x}", faulting_address)?,
+            None => {}
+        }
+        if let Some(memory_index) = self.memory_index() {
+            write!(f, " on memory #{}", memory_index)?;
+        }
+        if let Some(invoked_export) = self.invoked_export() {
+            write!(f, " while running export `{}`", invoked_export)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_message(f, false)
+    }
+}
+
+impl Error for Trap {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::User { error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+/// Compares two traps raised by different engines running the same module,
+/// for differential testing.
+///
+/// Backtraces, program counters and the other diagnostics are ignored.
+/// `Wasm` and `Lib` traps are equal if their trap codes are, up to
+/// `TrapCode::differential_eq`: whether a trap is raised by a signal
+/// handler or by a libcall depends on the compiler. The ones without a trap
+/// code, such as bare faults, are only equal to each other. `User` traps
+/// are equal if their errors display the same message.
+pub fn trap_eq_ignoring_backtrace(a: &Trap, b: &Trap) -> bool {
+    match (a, b) {
+        (Trap::User { error: a, .. }, Trap::User { error: b, .. }) => {
+            a.to_string() == b.to_string()
+        }
+        (Trap::Wasm { .. }, Trap::Wasm { .. })
+        | (Trap::Wasm { .. }, Trap::Lib { .. })
+        | (Trap::Lib { .. }, Trap::Wasm { .. })
+        | (Trap::Lib { .. }, Trap::Lib { .. }) => match (a.trap_code(), b.trap_code()) {
+            (Some(a), Some(b)) => a.differential_eq(b),
+            (None, None) => true,
+            _ => false,
+        },
+        (Trap::OOM { .. }, Trap::OOM { .. }) => true,
+        (Trap::ResourceExhausted { kind: a, .. }, Trap::ResourceExhausted { kind: b, .. }) => {
+            a == b
+        }
+        _ => false,
     }
 }
 
+/// Asserts that a `&Trap` has the expected `TrapCode`.
+///
+/// On mismatch, panics with the expected trap code and the message of the
+/// actual trap, which tells its variant apart.
+///
+/// ```
+/// use wasmer_vm::{assert_trap_code, Trap, TrapCode};
+///
+/// let trap = Trap::lib(TrapCode::IntegerOverflow);
+/// assert_trap_code!(&trap, TrapCode::IntegerOverflow);
+/// ```
+#[macro_export]
+macro_rules! assert_trap_code {
+    ($trap:expr, $expected:expr $(,)?) => {{
+        let trap: &$crate::Trap = $trap;
+        let expected: $crate::TrapCode = $expected;
+        if trap.trap_code() != Some(expected) {
+            panic!("expected a trap with code `{:?}`, got `{}`", expected, trap);
+        }
+    }};
+}
+
 /// Call the wasm function pointed to by `callee`.
 ///
 /// * `vmctx` - the callee vmctx argument
@@ -614,6 +1382,7 @@ pub unsafe fn wasmer_call_trampoline(
     callee: *const VMFunctionBody,
     values_vec: *mut u8,
 ) -> Result<(), Trap> {
+    let _wasm_pcs = enter_wasm(callee as usize);
     catch_traps(trap_handler, || {
         mem::transmute::<_, extern "C" fn(VMFunctionEnvironment, *const VMFunctionBody, *mut u8)>(
             trampoline,
@@ -779,6 +1548,7 @@ impl<T> TrapHandlerContextInner<T> {
             backtrace,
             signal_trap,
             pc,
+            fault_address: maybe_fault_address,
         };
         let regs = self
             .coro_trap_handler
@@ -800,19 +1570,28 @@ enum UnwindReason {
         backtrace: Backtrace,
         pc: usize,
         signal_trap: Option<TrapCode>,
+        /// The raw address of the fault, as reported by the signal
+        fault_address: Option<usize>,
     },
 }
 
 impl UnwindReason {
     fn to_trap(self) -> Trap {
         match self {
-            UnwindReason::UserTrap(data) => Trap::User(data),
+            UnwindReason::UserTrap(data) => Trap::user_boxed(data),
             UnwindReason::LibTrap(trap) => trap,
             UnwindReason::WasmTrap {
                 backtrace,
                 pc,
                 signal_trap,
-            } => Trap::wasm(pc, backtrace, signal_trap),
+                fault_address,
+            } => {
+                let trap = Trap::wasm(pc, backtrace, signal_trap);
+                match fault_address.and_then(linear_memory_offset) {
+                    Some(offset) => trap.with_faulting_address(offset),
+                    None => trap,
+                }
+            }
             UnwindReason::Panic(panic) => std::panic::resume_unwind(panic),
         }
     }
@@ -1030,3 +1809,596 @@ pub fn lazy_per_thread_init() -> Result<(), Trap> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LinearMemory, Memory, MemoryStyle};
+    use wasmer_types::{MemoryType, Pages};
+
+    #[test]
+    fn display_stable_elides_pc() {
+        let a = Trap::wasm(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        );
+        let b = Trap::wasm(
+            0x2000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        );
+        assert_eq!(a.display_stable(), b.display_stable());
+        assert_eq!(
+            a.display_stable(),
+            "wasm trap at <addr>: out of bounds memory access"
+        );
+    }
+
+    #[test]
+    fn display_stable_elides_faulting_address() {
+        let a = Trap::wasm(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        )
+        .with_faulting_address(0x10000);
+        let b = Trap::wasm(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        )
+        .with_faulting_address(0x20000);
+        assert_ne!(a.to_string(), b.to_string());
+        assert_eq!(a.display_stable(), b.display_stable());
+        assert_eq!(
+            a.display_stable(),
+            "wasm trap at <addr>: out of bounds memory access at offset <addr>"
+        );
+    }
+
+    #[test]
+    fn redacted() {
+        let trap = Trap::wasm_named(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+            "load".to_string(),
+        )
+        .with_faulting_address(0x10000)
+        .with_memory_index(1);
+        let redacted = trap.redacted();
+        assert!(redacted.is_redacted());
+        assert!(!trap.is_redacted());
+        // The copy keeps the variant, but no code nor address.
+        assert!(matches!(redacted, Trap::Wasm { .. }));
+        assert_eq!(redacted.to_string(), "execution faulted");
+        assert_eq!(redacted.display_stable(), "execution faulted");
+        assert_eq!(redacted.trap_code(), None);
+        assert_eq!(
+            redacted.best_effort_code(),
+            TrapCode::UnreachableCodeReached
+        );
+        assert_eq!(redacted.memory_index(), None);
+        assert_eq!(redacted.faulting_address(), None);
+        assert_eq!(redacted.function_name(), None);
+        assert_eq!(redacted.origin(), TrapOrigin::Guest);
+        assert!(redacted
+            .wasm_backtrace(|_| Some("f".to_string()))
+            .is_empty());
+        // The original keeps its details, for logging.
+        assert_eq!(trap.function_name(), Some("load"));
+        assert_eq!(trap.faulting_address(), Some(0x10000));
+        assert_eq!(trap.trap_code(), Some(TrapCode::HeapAccessOutOfBounds));
+
+        let trap = Trap::lib_signature_mismatch(3, 5)
+            .with_memory_index(1)
+            .with_invoked_export("run");
+        let redacted = trap.redacted();
+        assert!(redacted.is_redacted());
+        assert!(!trap.is_redacted());
+        assert!(matches!(redacted, Trap::Lib { .. }));
+        assert_eq!(redacted.to_string(), "execution faulted");
+        assert_eq!(redacted.trap_code(), None);
+        assert_eq!(redacted.memory_index(), None);
+        assert_eq!(redacted.faulting_address(), None);
+        assert_eq!(redacted.signature_mismatch(), None);
+        assert_eq!(redacted.invoked_export(), None);
+
+        for trap in &[Trap::oom(), Trap::resource_exhausted(ResourceKind::Fuel)] {
+            let redacted = trap.redacted();
+            assert!(redacted.is_redacted());
+            assert_eq!(redacted.to_string(), "execution faulted");
+        }
+
+        // A real trap at pc 0 isn't taken for a redacted one.
+        let trap = Trap::wasm(0, Backtrace::from(Vec::new()), None);
+        assert!(!trap.is_redacted());
+        assert_eq!(trap.to_string(), "wasm trap at 0x0");
+
+        // The message of a host error isn't kept.
+        let redacted = Trap::user_boxed("secret.db: permission denied".into()).redacted();
+        assert!(redacted.is_redacted());
+        assert_eq!(redacted.to_string(), "execution faulted");
+        assert_eq!(redacted.origin(), TrapOrigin::Host);
+    }
+
+    #[test]
+    fn grpc_status() {
+        assert_eq!(
+            Trap::user_boxed("host error".into()).grpc_status(),
+            (2, "user trap: host error".to_string())
+        );
+        assert_eq!(Trap::oom().grpc_status(), (8, "out of memory".to_string()));
+        assert_eq!(
+            Trap::resource_exhausted(ResourceKind::WallClock).grpc_status(),
+            (
+                4,
+                "resource exhausted: wall-clock time limit exceeded".to_string()
+            )
+        );
+        assert_eq!(
+            Trap::resource_exhausted(ResourceKind::Fuel).grpc_status().0,
+            8
+        );
+        assert_eq!(
+            Trap::lib(TrapCode::UnreachableCodeReached).grpc_status(),
+            (13, "lib trap: unreachable".to_string())
+        );
+        assert_eq!(
+            Trap::lib(TrapCode::BadConversionToInteger).grpc_status(),
+            (3, "lib trap: invalid conversion to integer".to_string())
+        );
+        assert_eq!(Trap::lib(TrapCode::StackOverflow).grpc_status().0, 8);
+        let trap = Trap::wasm(0x1000, Backtrace::from(Vec::new()), None);
+        assert_eq!(trap.grpc_status(), (11, "wasm trap at 0x1000".to_string()));
+    }
+
+    #[test]
+    fn full_report() {
+        let report = Trap::lib(TrapCode::IntegerDivisionByZero)
+            .full_report("https://docs.wasmer.io/runtime/traps");
+        assert_eq!(
+            report,
+            "lib trap: integer divide by zero\n\
+             hint: check that the divisor is not zero\n\
+             see: https://docs.wasmer.io/runtime/traps#integer-division-by-zero"
+        );
+        assert_eq!(
+            Trap::oom().full_report("https://docs.wasmer.io/runtime/traps"),
+            "out of memory"
+        );
+    }
+
+    #[test]
+    fn lib_with_depth() {
+        for &max_frames in &[0, 1, 3] {
+            match Trap::lib_with_depth(TrapCode::StackOverflow, max_frames) {
+                Trap::Lib { backtrace, .. } => assert!(backtrace.frames().len() <= max_frames),
+                trap => panic!("unexpected trap: {}", trap),
+            }
+        }
+    }
+
+    #[test]
+    fn deferred() {
+        let frames = |trap: &Trap| match trap {
+            Trap::Lib { backtrace, .. } => backtrace.frames().len(),
+            trap => panic!("unexpected trap: {}", trap),
+        };
+        let trap = Trap::deferred(TrapCode::UnreachableCodeReached);
+        assert_eq!(trap.trap_code(), Some(TrapCode::UnreachableCodeReached));
+        assert_eq!(frames(&trap), 0);
+
+        // Attach the backtrace captured when the host function got suspended.
+        let suspended_at = Backtrace::new_unresolved();
+        let expected = suspended_at.frames().len();
+        assert!(expected > 0);
+        let trap = trap.with_backtrace(suspended_at.clone());
+        assert_eq!(frames(&trap), expected);
+        assert_eq!(
+            trap.display_stable(),
+            Trap::lib(TrapCode::UnreachableCodeReached).display_stable()
+        );
+
+        let trap = Trap::deferred_with_backtrace(TrapCode::StackOverflow, suspended_at);
+        assert_eq!(frames(&trap), expected);
+    }
+
+    #[test]
+    fn invoked_export() {
+        let trap = Trap::lib(TrapCode::IntegerDivisionByZero);
+        assert_eq!(trap.invoked_export(), None);
+        let trap = trap.with_invoked_export("run");
+        assert_eq!(trap.invoked_export(), Some("run"));
+        assert_eq!(
+            trap.to_string(),
+            "lib trap: integer divide by zero while running export `run`"
+        );
+
+        let trap = Trap::wasm_named(
+            0x1000,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::UnreachableCodeReached),
+            "abort".to_string(),
+        )
+        .with_invoked_export("main");
+        assert_eq!(
+            trap.display_stable(),
+            "wasm trap at <addr> in abort: unreachable while running export `main`"
+        );
+        assert_eq!(
+            Trap::oom().with_invoked_export("run").invoked_export(),
+            None
+        );
+    }
+
+    #[test]
+    fn faulting_address() {
+        let trap = Trap::wasm(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        );
+        assert_eq!(trap.faulting_address(), None);
+
+        let trap = trap.with_faulting_address(0x10000);
+        assert_eq!(trap.faulting_address(), Some(0x10000));
+        assert_eq!(
+            trap.to_string(),
+            "wasm trap at 0x1000: out of bounds memory access at address 0x10000"
+        );
+        let trap = Trap::lib(TrapCode::HeapAccessOutOfBounds).with_faulting_address(0x10000);
+        assert_eq!(trap.faulting_address(), None);
+    }
+
+    #[test]
+    fn signal_fault_address() {
+        // The signal handler translates the address of the fault to an
+        // offset in the linear memory containing it.
+        let memory = LinearMemory::new(
+            &MemoryType::new(Pages(1), None, false),
+            &MemoryStyle::Dynamic {
+                offset_guard_size: 0,
+            },
+        )
+        .unwrap();
+        let base = unsafe { memory.vmmemory().as_ref().base } as usize;
+        let trap = UnwindReason::WasmTrap {
+            backtrace: Backtrace::from(Vec::new()),
+            pc: 0x1000,
+            signal_trap: Some(TrapCode::HeapAccessOutOfBounds),
+            fault_address: Some(base + 0x10),
+        }
+        .to_trap();
+        assert_eq!(trap.faulting_address(), Some(0x10));
+
+        // Faults outside of every linear memory have no offset.
+        let trap = UnwindReason::WasmTrap {
+            backtrace: Backtrace::from(Vec::new()),
+            pc: 0x1000,
+            signal_trap: Some(TrapCode::StackOverflow),
+            fault_address: Some(&trap as *const Trap as usize),
+        }
+        .to_trap();
+        assert_eq!(trap.faulting_address(), None);
+
+        let trap = UnwindReason::WasmTrap {
+            backtrace: Backtrace::from(Vec::new()),
+            pc: 0x1000,
+            signal_trap: None,
+            fault_address: None,
+        }
+        .to_trap();
+        assert_eq!(trap.faulting_address(), None);
+
+        // Once the memory is gone, its range no longer translates.
+        drop(memory);
+        let trap = UnwindReason::WasmTrap {
+            backtrace: Backtrace::from(Vec::new()),
+            pc: 0x1000,
+            signal_trap: Some(TrapCode::HeapAccessOutOfBounds),
+            fault_address: Some(base + 0x10),
+        }
+        .to_trap();
+        assert_eq!(trap.faulting_address(), None);
+    }
+
+    #[test]
+    fn signature_mismatch() {
+        let trap = Trap::lib_signature_mismatch(3, 5);
+        assert_eq!(trap.trap_code(), Some(TrapCode::BadSignature));
+        assert_eq!(
+            trap.signature_mismatch(),
+            Some(SignatureMismatch {
+                expected: 3,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            trap.to_string(),
+            "lib trap: indirect call type mismatch: expected type #3, got type #5"
+        );
+        assert_eq!(Trap::lib(TrapCode::BadSignature).signature_mismatch(), None);
+    }
+
+    #[test]
+    fn wasm_backtrace() {
+        let backtrace = Backtrace::new_unresolved();
+        // Pretend the innermost native frame is a wasm caller of `fib`.
+        let caller = backtrace.frames()[0].ip() as usize;
+        let trap = Trap::wasm(0x1000, backtrace, Some(TrapCode::UnreachableCodeReached));
+        let symbolize = |pc| match pc {
+            0x1000 => Some("fib".to_string()),
+            _ if pc == caller => Some("main".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            trap.wasm_backtrace(symbolize),
+            vec![
+                "fib at 0x1000".to_string(),
+                format!("main at {:#x}", caller)
+            ]
+        );
+        assert!(Trap::user_boxed("host error".into())
+            .wasm_backtrace(symbolize)
+            .is_empty());
+    }
+
+    #[test]
+    fn assert_trap_code_matches() {
+        assert_trap_code!(&Trap::lib(TrapCode::BadSignature), TrapCode::BadSignature);
+        let trap = Trap::wasm(
+            0x1000,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::StackOverflow),
+        );
+        assert_trap_code!(&trap, TrapCode::StackOverflow);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a trap with code `IntegerOverflow`, got `user trap: oops`")]
+    fn assert_trap_code_mismatch() {
+        assert_trap_code!(&Trap::user_boxed("oops".into()), TrapCode::IntegerOverflow);
+    }
+
+    #[test]
+    fn timestamp() {
+        let start = Instant::now();
+        let traps = [
+            Trap::lib(TrapCode::IntegerOverflow),
+            Trap::wasm(0x1000, Backtrace::from(Vec::new()), None),
+            Trap::oom(),
+        ];
+        for trap in &traps {
+            if cfg!(feature = "trap-timestamps") {
+                assert!(trap.timestamp().unwrap() >= start);
+                assert!(trap.elapsed_since(start).is_some());
+            } else {
+                assert_eq!(trap.timestamp(), None);
+                assert_eq!(trap.elapsed_since(start), None);
+            }
+        }
+        assert_eq!(Trap::user_boxed("oops".into()).timestamp(), None);
+    }
+
+    #[cfg(not(feature = "trap-timestamps"))]
+    #[test]
+    fn timestamp_is_free() {
+        assert_eq!(std::mem::size_of::<TrapTimestamp>(), 0);
+    }
+
+    #[test]
+    fn on_code() {
+        let handled = Trap::lib(TrapCode::IntegerOverflow).on_code(TrapCode::IntegerOverflow, || 1);
+        assert_eq!(handled.ok(), Some(1));
+
+        let trap = Trap::lib(TrapCode::IntegerOverflow)
+            .on_code(TrapCode::StackOverflow, || 1)
+            .unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerOverflow));
+        assert!(Trap::oom()
+            .on_code(TrapCode::UnreachableCodeReached, || 1)
+            .is_err());
+    }
+
+    #[test]
+    fn on_oom() {
+        assert_eq!(Trap::oom().on_oom(|| "retry").ok(), Some("retry"));
+
+        let trap = Trap::lib(TrapCode::StackOverflow)
+            .on_oom(|| "retry")
+            .unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::StackOverflow));
+
+        // Falls through to the next handler.
+        let handled = Trap::user_boxed("host error".into())
+            .on_oom(|| "retry")
+            .or_else(|trap| trap.on_code(TrapCode::StackOverflow, || "unwind"))
+            .map_err(|trap| trap.to_string());
+        assert_eq!(handled, Err("user trap: host error".to_string()));
+    }
+
+    #[test]
+    fn best_effort_code() {
+        let trap = Trap::wasm(0x1000, Backtrace::from(Vec::new()), None);
+        assert_eq!(trap.trap_code(), None);
+        assert_eq!(trap.best_effort_code(), TrapCode::HeapAccessOutOfBounds);
+        let trap = Trap::wasm(
+            0x1000,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::StackOverflow),
+        );
+        assert_eq!(trap.best_effort_code(), TrapCode::StackOverflow);
+        assert_eq!(
+            Trap::lib(TrapCode::IntegerOverflow).best_effort_code(),
+            TrapCode::IntegerOverflow
+        );
+        // The traps not raised by the guest default to `unreachable`, but
+        // have no trap code.
+        for trap in &[
+            Trap::oom(),
+            Trap::user_boxed("host error".into()),
+            Trap::resource_exhausted(ResourceKind::Fuel),
+        ] {
+            assert_eq!(trap.best_effort_code(), TrapCode::UnreachableCodeReached);
+            assert_eq!(trap.trap_code(), None);
+        }
+    }
+
+    #[test]
+    fn function_name() {
+        let trap = Trap::wasm(0x1000, Backtrace::new_unresolved(), None);
+        assert_eq!(trap.function_name(), None);
+
+        let trap = Trap::wasm_named(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::IntegerDivisionByZero),
+            "fib".to_string(),
+        );
+        assert_eq!(trap.function_name(), Some("fib"));
+        assert_eq!(
+            trap.to_string(),
+            "wasm trap at 0x1000 in fib: integer divide by zero"
+        );
+        assert_eq!(Trap::lib(TrapCode::IntegerOverflow).function_name(), None);
+    }
+
+    #[test]
+    fn user_error_type_name() {
+        #[derive(Debug)]
+        struct QuotaExceeded;
+
+        impl fmt::Display for QuotaExceeded {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "quota exceeded")
+            }
+        }
+
+        impl Error for QuotaExceeded {}
+
+        let trap = Trap::user(QuotaExceeded);
+        assert_eq!(
+            trap.user_error_type_name(),
+            Some(type_name::<QuotaExceeded>())
+        );
+        assert!(trap
+            .user_error_type_name()
+            .unwrap()
+            .ends_with("QuotaExceeded"));
+        assert_eq!(trap.to_string(), "user trap: quota exceeded");
+        assert_eq!(
+            Trap::user_boxed(Box::new(QuotaExceeded)).user_error_type_name(),
+            None
+        );
+        assert_eq!(
+            Trap::lib(TrapCode::IntegerOverflow).user_error_type_name(),
+            None
+        );
+    }
+
+    #[test]
+    fn resource_exhausted() {
+        let messages = [
+            (ResourceKind::Fuel, "resource exhausted: out of fuel"),
+            (
+                ResourceKind::WallClock,
+                "resource exhausted: wall-clock time limit exceeded",
+            ),
+            (
+                ResourceKind::MemoryPages,
+                "resource exhausted: memory pages limit exceeded",
+            ),
+            (
+                ResourceKind::TableElements,
+                "resource exhausted: table elements limit exceeded",
+            ),
+        ];
+        for &(kind, message) in &messages {
+            let trap = Trap::resource_exhausted(kind);
+            assert_eq!(trap.to_string(), message);
+            assert_eq!(trap.origin(), TrapOrigin::Host);
+            assert_eq!(trap.trap_code(), None);
+        }
+    }
+
+    #[test]
+    fn origin() {
+        assert_eq!(
+            Trap::lib(TrapCode::IntegerOverflow).origin(),
+            TrapOrigin::Guest
+        );
+        assert_eq!(Trap::user_boxed("error".into()).origin(), TrapOrigin::Host);
+        assert_eq!(Trap::oom().origin(), TrapOrigin::Runtime);
+    }
+
+    #[test]
+    fn memory_index() {
+        let trap = Trap::lib(TrapCode::HeapAccessOutOfBounds);
+        assert_eq!(trap.memory_index(), None);
+        assert_eq!(trap.to_string(), "lib trap: out of bounds memory access");
+
+        let trap = trap.with_memory_index(2);
+        assert_eq!(trap.memory_index(), Some(2));
+        assert_eq!(
+            trap.to_string(),
+            "lib trap: out of bounds memory access on memory #2"
+        );
+        assert_eq!(Trap::oom().with_memory_index(2).memory_index(), None);
+    }
+
+    #[test]
+    fn anyhow_preserves_trap_code() {
+        fn run() -> anyhow::Result<()> {
+            Err(Trap::lib(TrapCode::IntegerOverflow))?;
+            Ok(())
+        }
+
+        let error = run().unwrap_err();
+        let trap = error.downcast_ref::<Trap>().unwrap();
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerOverflow));
+    }
+
+    #[test]
+    fn eq_ignoring_backtrace() {
+        let signal = Trap::wasm(
+            0x1000,
+            Backtrace::new_unresolved(),
+            Some(TrapCode::OutOfBounds),
+        );
+        let lib = Trap::lib(TrapCode::HeapAccessOutOfBounds);
+        assert!(trap_eq_ignoring_backtrace(&signal, &lib));
+        // A bare fault isn't guessed to be an out-of-bounds access.
+        let fault = Trap::wasm(0x2000, Backtrace::from(Vec::new()), None);
+        assert!(!trap_eq_ignoring_backtrace(&signal, &fault));
+        assert!(!trap_eq_ignoring_backtrace(&fault, &lib));
+        assert!(trap_eq_ignoring_backtrace(
+            &fault,
+            &Trap::wasm(0x3000, Backtrace::from(Vec::new()), None)
+        ));
+        assert!(!trap_eq_ignoring_backtrace(
+            &lib,
+            &Trap::lib(TrapCode::IntegerOverflow)
+        ));
+
+        assert!(trap_eq_ignoring_backtrace(
+            &Trap::user_boxed("host error".into()),
+            &Trap::user_boxed("host error".into())
+        ));
+        assert!(!trap_eq_ignoring_backtrace(
+            &Trap::user_boxed("host error".into()),
+            &Trap::user_boxed("other error".into())
+        ));
+        assert!(trap_eq_ignoring_backtrace(&Trap::oom(), &Trap::oom()));
+        assert!(!trap_eq_ignoring_backtrace(
+            &Trap::resource_exhausted(ResourceKind::Fuel),
+            &Trap::resource_exhausted(ResourceKind::WallClock)
+        ));
+        assert!(!trap_eq_ignoring_backtrace(&Trap::oom(), &lib));
+        assert!(!trap_eq_ignoring_backtrace(
+            &Trap::user_boxed("unreachable".into()),
+            &Trap::lib(TrapCode::UnreachableCodeReached)
+        ));
+    }
+}
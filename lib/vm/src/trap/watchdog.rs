@@ -0,0 +1,137 @@
+//! A watchdog of the traps of a guest, telling when it is stuck trapping
+//! the same way over and over.
+
+use super::trapcode::TrapCode;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Tracks the last `capacity` traps of a guest, as `(pc, code)` pairs, and
+/// tells when the latest one recurred more than `threshold` times within a
+/// sliding `window`.
+///
+/// A host resuming a guest after its traps consults `should_terminate` to
+/// stop resuming a guest stuck in a loop that keeps trapping the same way.
+#[derive(Debug, Clone)]
+pub struct TrapWatchdog {
+    capacity: usize,
+    threshold: usize,
+    window: Duration,
+    observations: VecDeque<(usize, TrapCode, Instant)>,
+}
+
+impl TrapWatchdog {
+    /// Creates a watchdog remembering the last `capacity` traps, and
+    /// allowing a trap to recur up to `threshold` times within any
+    /// `window`.
+    pub fn new(capacity: usize, threshold: usize, window: Duration) -> Self {
+        Self {
+            capacity,
+            threshold,
+            window,
+            observations: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records a trap with the given code at the program counter `pc`, now.
+    pub fn observe(&mut self, pc: usize, code: TrapCode) {
+        self.observe_at(pc, code, Instant::now())
+    }
+
+    /// Records a trap with the given code at the program counter `pc`, at
+    /// `now`.
+    ///
+    /// The instants of the traps are expected to be increasing, but a trap
+    /// recorded out of order doesn't panic: the traps with later instants
+    /// count as within its window.
+    pub fn observe_at(&mut self, pc: usize, code: TrapCode, now: Instant) {
+        if self.observations.len() == self.capacity {
+            self.observations.pop_front();
+        }
+        if self.capacity > 0 {
+            self.observations.push_back((pc, code, now));
+        }
+    }
+
+    /// Returns whether the latest trap recurred more than `threshold` times
+    /// within the window ending with it, among the remembered traps.
+    pub fn should_terminate(&self) -> bool {
+        let &(pc, code, latest) = match self.observations.back() {
+            Some(observation) => observation,
+            None => return false,
+        };
+        let recurrences = self
+            .observations
+            .iter()
+            .filter(|&&(other_pc, other_code, at)| {
+                (other_pc, other_code) == (pc, code)
+                    && latest.saturating_duration_since(at) < self.window
+            })
+            .count();
+        recurrences > self.threshold
+    }
+
+    /// Forgets the remembered traps.
+    pub fn reset(&mut self) {
+        self.observations.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_trap() {
+        let mut watchdog = TrapWatchdog::new(8, 3, Duration::from_secs(1));
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        for millis in 0..3 {
+            watchdog.observe_at(0x1000, TrapCode::IntegerDivisionByZero, at(millis));
+            assert!(!watchdog.should_terminate());
+        }
+        watchdog.observe_at(0x1000, TrapCode::IntegerDivisionByZero, at(3));
+        assert!(watchdog.should_terminate());
+
+        watchdog.reset();
+        assert!(!watchdog.should_terminate());
+    }
+
+    #[test]
+    fn varied_traps() {
+        let mut watchdog = TrapWatchdog::new(8, 3, Duration::from_secs(1));
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        for millis in 0..8 {
+            // Alternating between two program counters.
+            let pc = 0x1000 + 0x10 * (millis as usize % 2);
+            watchdog.observe_at(pc, TrapCode::HeapAccessOutOfBounds, at(millis));
+            // Alternating between two trap codes.
+            watchdog.observe_at(0x2000, TrapCode::IntegerOverflow, at(millis));
+            watchdog.observe_at(0x2000, TrapCode::IntegerDivisionByZero, at(millis));
+        }
+        assert!(!watchdog.should_terminate());
+
+        // Recurrences outside of the window don't count.
+        for secs in 0..8 {
+            watchdog.observe_at(
+                0x1000,
+                TrapCode::HeapAccessOutOfBounds,
+                at(1000 * (secs + 1)),
+            );
+            assert!(!watchdog.should_terminate());
+        }
+    }
+
+    #[test]
+    fn out_of_order_traps() {
+        let mut watchdog = TrapWatchdog::new(8, 1, Duration::from_secs(1));
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        watchdog.observe_at(0x1000, TrapCode::IntegerOverflow, at(500));
+        watchdog.observe_at(0x1000, TrapCode::IntegerOverflow, at(0));
+        assert!(watchdog.should_terminate());
+    }
+}
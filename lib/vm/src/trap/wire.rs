@@ -0,0 +1,635 @@
+//! A compact encoding of `Trap`s, used to send them across process
+//! boundaries (eg. from a sandboxed child process to its supervisor).
+//!
+//! The encoding is, in order:
+//!
+//! * the format version (`u8`),
+//! * the variant tag (`u8`),
+//! * the variant fields: the trap code (`u32`), the program counter (`u64`),
+//!   the memory index (`u32`), the function name, the faulting address
+//!   (`u64`), the expected and actual signatures (two `u32`s) and the name
+//!   of the invoked export when present, each optional field being prefixed
+//!   by a presence byte,
+//! * the message of the user error, for `User` traps, followed by its type
+//!   tag and payload when its type is in the `TrapTypeRegistry` in use,
+//! * the resource kind (`u8`), for `ResourceExhausted` traps,
+//! * whether the trap is redacted (`u8`), for all but `User` traps,
+//! * the resolved backtrace.
+//!
+//! Strings and payloads are encoded as their length (`u32`) followed by
+//! their bytes.
+//! All integers are little-endian.
+
+use super::trapcode::TrapCode;
+use super::traphandlers::{ResourceKind, SignatureMismatch, Trap, TrapTimestamp};
+use backtrace::Backtrace;
+use std::any::type_name;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use thiserror::Error;
+
+/// The version of the wire format written by `Trap::to_wire`.
+const WIRE_VERSION: u8 = 1;
+
+const TAG_USER: u8 = 0;
+const TAG_WASM: u8 = 1;
+const TAG_LIB: u8 = 2;
+const TAG_OOM: u8 = 3;
+const TAG_RESOURCE_EXHAUSTED: u8 = 4;
+
+const RESOURCE_KINDS: [ResourceKind; 4] = [
+    ResourceKind::Fuel,
+    ResourceKind::WallClock,
+    ResourceKind::MemoryPages,
+    ResourceKind::TableElements,
+];
+
+/// Error type describing things that can go wrong when decoding a `Trap`
+/// from its wire format.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WireError {
+    /// The bytes were encoded with an unknown version of the format.
+    #[error("unsupported trap wire format version {0}")]
+    UnsupportedVersion(u8),
+    /// The bytes ended before the trap was fully decoded.
+    #[error("unexpected end of the encoded trap")]
+    UnexpectedEnd,
+    /// The variant tag doesn't match any `Trap` variant.
+    #[error("invalid trap variant tag {0}")]
+    InvalidTag(u8),
+    /// The trap code doesn't match any `TrapCode`.
+    #[error("invalid trap code {0}")]
+    InvalidTrapCode(u32),
+    /// The resource kind doesn't match any `ResourceKind`.
+    #[error("invalid resource kind {0}")]
+    InvalidResourceKind(u8),
+    /// A string is not valid UTF-8.
+    #[error("invalid UTF-8 string in the encoded trap")]
+    InvalidUtf8,
+    /// There are bytes left after the encoded trap.
+    #[error("trailing bytes after the encoded trap")]
+    TrailingBytes,
+}
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+type EncodeFn = dyn Fn(&(dyn Error + Send + Sync + 'static)) -> Option<Vec<u8>> + Send + Sync;
+type DecodeFn = dyn Fn(&[u8]) -> Option<BoxedError> + Send + Sync;
+
+/// A user error type registered in a `TrapTypeRegistry`.
+struct RegisteredType {
+    tag: String,
+    type_name: &'static str,
+    encode: Box<EncodeFn>,
+    decode: Box<DecodeFn>,
+}
+
+/// The types of the `User` errors that `Trap::to_wire_with_registry` and
+/// `Trap::from_wire_with_registry` preserve, for processes sharing the
+/// same registry.
+///
+/// Each type is registered under a tag, with functions encoding its values
+/// to bytes and decoding them back. The `User` errors of other types, or
+/// whose tag is unknown to the decoding side, are decoded as plain string
+/// errors with the original message, as `Trap::from_wire` does.
+#[derive(Default)]
+pub struct TrapTypeRegistry {
+    types: Vec<RegisteredType>,
+}
+
+impl TrapTypeRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the user error type `E` under `tag`, replacing any type
+    /// registered under the same tag.
+    ///
+    /// `decode` returns `None` if the bytes aren't valid, in which case the
+    /// error is decoded as a string error.
+    pub fn register<E: Error + Send + Sync + 'static>(
+        &mut self,
+        tag: impl Into<String>,
+        encode: fn(&E) -> Vec<u8>,
+        decode: fn(&[u8]) -> Option<E>,
+    ) -> &mut Self {
+        let tag = tag.into();
+        self.types.retain(|registered| registered.tag != tag);
+        self.types.push(RegisteredType {
+            tag,
+            type_name: type_name::<E>(),
+            encode: Box::new(move |error: &(dyn Error + Send + Sync + 'static)| {
+                error.downcast_ref::<E>().map(encode)
+            }),
+            decode: Box::new(move |bytes: &[u8]| {
+                decode(bytes).map(|error| Box::new(error) as BoxedError)
+            }),
+        });
+        self
+    }
+
+    /// Encodes `error` with the first registered type it is of, returning
+    /// the tag of the type and the payload.
+    fn encode(&self, error: &(dyn Error + Send + Sync + 'static)) -> Option<(&str, Vec<u8>)> {
+        self.types.iter().find_map(|registered| {
+            (registered.encode)(error).map(|payload| (registered.tag.as_str(), payload))
+        })
+    }
+
+    /// Decodes a payload of the type registered under `tag`, returning the
+    /// error and the name of its type.
+    fn decode(&self, tag: &str, payload: &[u8]) -> Option<(BoxedError, &'static str)> {
+        let registered = self.types.iter().find(|registered| registered.tag == tag)?;
+        (registered.decode)(payload).map(|error| (error, registered.type_name))
+    }
+}
+
+impl fmt::Debug for TrapTypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.types.iter().map(|registered| &registered.tag))
+            .finish()
+    }
+}
+
+impl Trap {
+    /// Encodes this trap in a compact format that can be sent to another
+    /// process, and decoded there with `Trap::from_wire`.
+    ///
+    /// The backtrace is resolved and sent as a string, and a `User` error
+    /// is only sent as its message.
+    pub fn to_wire(&self) -> Vec<u8> {
+        self.to_wire_with_registry(&TrapTypeRegistry::new())
+    }
+
+    /// Encodes this trap like `Trap::to_wire`, also sending a `User` error
+    /// whose type is in `registry` as its tag and payload, for
+    /// `Trap::from_wire_with_registry` to decode it back to its type.
+    pub fn to_wire_with_registry(&self, registry: &TrapTypeRegistry) -> Vec<u8> {
+        let mut bytes = vec![WIRE_VERSION];
+        let backtrace = match self {
+            Self::User { error, .. } => {
+                bytes.push(TAG_USER);
+                write_str(&mut bytes, &error.to_string());
+                match registry.encode(&**error) {
+                    Some((tag, payload)) => {
+                        bytes.push(1);
+                        write_str(&mut bytes, tag);
+                        write_bytes(&mut bytes, &payload);
+                    }
+                    None => bytes.push(0),
+                }
+                None
+            }
+            Self::Wasm {
+                pc,
+                backtrace,
+                signal_trap,
+                memory_index,
+                function_name,
+                faulting_addr,
+                invoked_export,
+                redacted,
+                ..
+            } => {
+                bytes.push(TAG_WASM);
+                bytes.extend_from_slice(&(*pc as u64).to_le_bytes());
+                write_opt_u32(&mut bytes, signal_trap.map(|trap_code| trap_code as u32));
+                write_opt_u32(&mut bytes, *memory_index);
+                write_opt_str(&mut bytes, function_name.as_deref());
+                write_opt_u64(&mut bytes, *faulting_addr);
+                write_opt_str(&mut bytes, invoked_export.as_deref());
+                bytes.push(*redacted as u8);
+                Some(backtrace)
+            }
+            Self::Lib {
+                trap_code,
+                backtrace,
+                memory_index,
+                signature_mismatch,
+                invoked_export,
+                redacted,
+                ..
+            } => {
+                bytes.push(TAG_LIB);
+                write_opt_u32(&mut bytes, trap_code.map(|trap_code| trap_code as u32));
+                write_opt_u32(&mut bytes, *memory_index);
+                match signature_mismatch {
+                    Some(SignatureMismatch { expected, actual }) => {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&expected.to_le_bytes());
+                        bytes.extend_from_slice(&actual.to_le_bytes());
+                    }
+                    None => bytes.push(0),
+                }
+                write_opt_str(&mut bytes, invoked_export.as_deref());
+                bytes.push(*redacted as u8);
+                Some(backtrace)
+            }
+            Self::OOM {
+                backtrace,
+                redacted,
+                ..
+            } => {
+                bytes.push(TAG_OOM);
+                bytes.push(*redacted as u8);
+                Some(backtrace)
+            }
+            Self::ResourceExhausted { kind, redacted } => {
+                bytes.push(TAG_RESOURCE_EXHAUSTED);
+                let index = RESOURCE_KINDS.iter().position(|k| k == kind).unwrap();
+                bytes.push(index as u8);
+                bytes.push(*redacted as u8);
+                None
+            }
+        };
+        let backtrace = backtrace.map_or_else(String::new, |backtrace| {
+            let mut backtrace = backtrace.clone();
+            backtrace.resolve();
+            format!("{:?}", backtrace)
+        });
+        write_str(&mut bytes, &backtrace);
+        bytes
+    }
+
+    /// Decodes a trap encoded with `Trap::to_wire`.
+    ///
+    /// The decoded trap has an empty backtrace, and a `User` trap holds a
+    /// plain string error with the original message. Use
+    /// `Trap::from_wire_with_backtrace` to also get the original backtrace.
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, WireError> {
+        Self::from_wire_with_backtrace(bytes).map(|(trap, _)| trap)
+    }
+
+    /// Decodes a trap encoded with `Trap::to_wire_with_registry`, decoding
+    /// a `User` error whose type tag is in `registry` back to its type, as
+    /// `Trap::user` does.
+    pub fn from_wire_with_registry(
+        bytes: &[u8],
+        registry: &TrapTypeRegistry,
+    ) -> Result<Self, WireError> {
+        Self::decode_wire(bytes, registry).map(|(trap, _)| trap)
+    }
+
+    /// Decodes a trap encoded with `Trap::to_wire`, along with the resolved
+    /// backtrace of the original trap.
+    pub fn from_wire_with_backtrace(bytes: &[u8]) -> Result<(Self, String), WireError> {
+        Self::decode_wire(bytes, &TrapTypeRegistry::new())
+    }
+
+    fn decode_wire(bytes: &[u8], registry: &TrapTypeRegistry) -> Result<(Self, String), WireError> {
+        let mut reader = Reader { bytes };
+        let version = reader.u8()?;
+        if version != WIRE_VERSION {
+            return Err(WireError::UnsupportedVersion(version));
+        }
+        let backtrace = Backtrace::from(Vec::new());
+        let trap = match reader.u8()? {
+            TAG_USER => {
+                let message = reader.string()?;
+                let typed = if reader.u8()? != 0 {
+                    let tag = reader.string()?;
+                    registry.decode(&tag, reader.byte_string()?)
+                } else {
+                    None
+                };
+                match typed {
+                    Some((error, type_name)) => Self::User {
+                        error,
+                        type_name: Some(type_name),
+                    },
+                    None => Self::user_boxed(message.into()),
+                }
+            }
+            TAG_WASM => Self::Wasm {
+                pc: reader.u64()? as usize,
+                backtrace,
+                signal_trap: reader.opt_u32()?.map(trap_code).transpose()?,
+                memory_index: reader.opt_u32()?,
+                function_name: reader.opt_string()?,
+                faulting_addr: reader.opt_u64()?,
+                invoked_export: reader.opt_string()?,
+                timestamp: TrapTimestamp::default(),
+                redacted: reader.u8()? != 0,
+            },
+            TAG_LIB => Self::Lib {
+                trap_code: reader.opt_u32()?.map(trap_code).transpose()?,
+                backtrace,
+                memory_index: reader.opt_u32()?,
+                signature_mismatch: if reader.u8()? != 0 {
+                    Some(SignatureMismatch {
+                        expected: reader.u32()?,
+                        actual: reader.u32()?,
+                    })
+                } else {
+                    None
+                },
+                invoked_export: reader.opt_string()?,
+                timestamp: TrapTimestamp::default(),
+                redacted: reader.u8()? != 0,
+            },
+            TAG_OOM => Self::OOM {
+                backtrace,
+                timestamp: TrapTimestamp::default(),
+                redacted: reader.u8()? != 0,
+            },
+            TAG_RESOURCE_EXHAUSTED => {
+                let index = reader.u8()?;
+                let kind = *RESOURCE_KINDS
+                    .get(index as usize)
+                    .ok_or(WireError::InvalidResourceKind(index))?;
+                Self::ResourceExhausted {
+                    kind,
+                    redacted: reader.u8()? != 0,
+                }
+            }
+            tag => return Err(WireError::InvalidTag(tag)),
+        };
+        let backtrace = reader.string()?;
+        if !reader.bytes.is_empty() {
+            return Err(WireError::TrailingBytes);
+        }
+        Ok((trap, backtrace))
+    }
+}
+
+fn trap_code(code: u32) -> Result<TrapCode, WireError> {
+    TrapCode::from_u32(code).ok_or(WireError::InvalidTrapCode(code))
+}
+
+fn write_opt_u32(bytes: &mut Vec<u8>, value: Option<u32>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn write_opt_u64(bytes: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn write_opt_str(bytes: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            write_str(bytes, value);
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    write_bytes(bytes, s.as_bytes());
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, payload: &[u8]) {
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(payload);
+}
+
+/// Reads the encoded fields, from the start of `bytes`.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        if self.bytes.len() < len {
+            return Err(WireError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, WireError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, WireError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, WireError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn opt_u32(&mut self) -> Result<Option<u32>, WireError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.u32().map(Some),
+        }
+    }
+
+    fn opt_u64(&mut self) -> Result<Option<u64>, WireError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.u64().map(Some),
+        }
+    }
+
+    fn opt_string(&mut self) -> Result<Option<String>, WireError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.string().map(Some),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, WireError> {
+        let bytes = self.byte_string()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| WireError::InvalidUtf8)
+    }
+
+    fn byte_string(&mut self) -> Result<&'a [u8], WireError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lib_round_trip() {
+        let trap = Trap::lib(TrapCode::HeapAccessOutOfBounds).with_memory_index(1);
+        let (decoded, backtrace) = Trap::from_wire_with_backtrace(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.trap_code(), Some(TrapCode::HeapAccessOutOfBounds));
+        assert_eq!(decoded.memory_index(), Some(1));
+        assert!(matches!(decoded, Trap::Lib { .. }));
+        assert!(!backtrace.is_empty());
+    }
+
+    #[test]
+    fn lib_signature_mismatch_round_trip() {
+        let trap = Trap::lib_signature_mismatch(3, 5);
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.signature_mismatch(), trap.signature_mismatch());
+        assert_eq!(decoded.trap_code(), Some(TrapCode::BadSignature));
+    }
+
+    #[test]
+    fn oom_round_trip() {
+        let decoded = Trap::from_wire(&Trap::oom().to_wire()).unwrap();
+        assert!(matches!(decoded, Trap::OOM { .. }));
+    }
+
+    #[test]
+    fn wasm_round_trip() {
+        let trap = Trap::wasm(0x1234, Backtrace::from(Vec::new()), None);
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.display_stable(), trap.display_stable());
+        assert!(matches!(decoded, Trap::Wasm { pc: 0x1234, .. }));
+    }
+
+    #[test]
+    fn wasm_named_round_trip() {
+        let trap = Trap::wasm_named(
+            0x1234,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::UnreachableCodeReached),
+            "fib".to_string(),
+        );
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.function_name(), Some("fib"));
+        assert_eq!(decoded.trap_code(), Some(TrapCode::UnreachableCodeReached));
+    }
+
+    #[test]
+    fn wasm_faulting_address_round_trip() {
+        let trap = Trap::wasm(
+            0x1234,
+            Backtrace::from(Vec::new()),
+            Some(TrapCode::HeapAccessOutOfBounds),
+        )
+        .with_faulting_address(0x10000);
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.faulting_address(), Some(0x10000));
+    }
+
+    #[test]
+    fn invoked_export_round_trip() {
+        let trap = Trap::lib(TrapCode::IntegerOverflow).with_invoked_export("run");
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.invoked_export(), Some("run"));
+
+        let trap =
+            Trap::wasm(0x1234, Backtrace::from(Vec::new()), None).with_invoked_export("main");
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.invoked_export(), Some("main"));
+    }
+
+    #[test]
+    fn resource_exhausted_round_trip() {
+        for &kind in &RESOURCE_KINDS {
+            let trap = Trap::resource_exhausted(kind);
+            let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+            assert!(matches!(decoded, Trap::ResourceExhausted { kind: k, .. } if k == kind));
+        }
+    }
+
+    #[test]
+    fn user_round_trip() {
+        let trap = Trap::user_boxed("host function failed".into());
+        let decoded = Trap::from_wire(&trap.to_wire()).unwrap();
+        assert_eq!(decoded.to_string(), "user trap: host function failed");
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct ExitCode(i32);
+
+    impl fmt::Display for ExitCode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "exited with code {}", self.0)
+        }
+    }
+
+    impl Error for ExitCode {}
+
+    fn registry() -> TrapTypeRegistry {
+        let mut registry = TrapTypeRegistry::new();
+        registry.register(
+            "exit-code",
+            |exit_code: &ExitCode| exit_code.0.to_le_bytes().to_vec(),
+            |bytes| Some(ExitCode(i32::from_le_bytes(bytes.try_into().ok()?))),
+        );
+        registry
+    }
+
+    #[test]
+    fn user_registered_type_round_trip() {
+        let registry = registry();
+        let trap = Trap::user(ExitCode(3));
+        let decoded =
+            Trap::from_wire_with_registry(&trap.to_wire_with_registry(&registry), &registry)
+                .unwrap();
+        assert_eq!(decoded.user_error_type_name(), trap.user_error_type_name());
+        match &decoded {
+            Trap::User { error, .. } => {
+                assert_eq!(error.downcast_ref::<ExitCode>(), Some(&ExitCode(3)))
+            }
+            _ => panic!("expected a user trap, got `{}`", decoded),
+        }
+
+        // Without the registry on either side, only the message is kept.
+        for decoded in [
+            Trap::from_wire(&trap.to_wire_with_registry(&registry)).unwrap(),
+            Trap::from_wire_with_registry(&trap.to_wire(), &registry).unwrap(),
+        ] {
+            assert_eq!(decoded.to_string(), "user trap: exited with code 3");
+            assert!(!matches!(decoded, Trap::User { error, .. } if error.is::<ExitCode>()));
+            assert_eq!(decoded.user_error_type_name(), None);
+        }
+
+        // Errors of other types are sent as their message.
+        let trap = Trap::user_boxed("host function failed".into());
+        let decoded =
+            Trap::from_wire_with_registry(&trap.to_wire_with_registry(&registry), &registry)
+                .unwrap();
+        assert_eq!(decoded.to_string(), "user trap: host function failed");
+    }
+
+    #[test]
+    fn redacted_round_trip() {
+        let traps = [
+            Trap::lib(TrapCode::IntegerOverflow),
+            Trap::wasm(0x1234, Backtrace::from(Vec::new()), None),
+            Trap::oom(),
+            Trap::resource_exhausted(ResourceKind::Fuel),
+        ];
+        for trap in &traps {
+            let decoded = Trap::from_wire(&trap.redacted().to_wire()).unwrap();
+            assert!(decoded.is_redacted());
+            assert_eq!(decoded.trap_code(), None);
+            assert_eq!(decoded.to_string(), "execution faulted");
+            assert!(!Trap::from_wire(&trap.to_wire()).unwrap().is_redacted());
+        }
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let bytes = Trap::lib(TrapCode::IntegerOverflow).to_wire();
+        assert_eq!(
+            Trap::from_wire(&bytes[..bytes.len() - 1]).unwrap_err(),
+            WireError::UnexpectedEnd
+        );
+        assert_eq!(
+            Trap::from_wire(&[WIRE_VERSION + 1]).unwrap_err(),
+            WireError::UnsupportedVersion(WIRE_VERSION + 1)
+        );
+        assert_eq!(
+            Trap::from_wire(&[WIRE_VERSION, 42]).unwrap_err(),
+            WireError::InvalidTag(42)
+        );
+    }
+}
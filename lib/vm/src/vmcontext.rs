@@ -1002,9 +1002,19 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_externref_dec_index() -> Self {
         Self(25)
     }
+    /// Returns an index for a function raising the trap of an indirect call
+    /// to a null table element.
+    pub const fn get_table_null_element_trap_index() -> Self {
+        Self(26)
+    }
+    /// Returns an index for a function raising the trap of an indirect call
+    /// with a mismatching signature.
+    pub const fn get_signature_mismatch_trap_index() -> Self {
+        Self(27)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        26
+        28
     }
 
     /// Return the index as an u32 number.
@@ -1086,6 +1096,10 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_externref_inc as usize;
         ptrs[VMBuiltinFunctionIndex::get_externref_dec_index().index() as usize] =
             wasmer_vm_externref_dec as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_null_element_trap_index().index() as usize] =
+            wasmer_vm_table_null_element_trap as usize;
+        ptrs[VMBuiltinFunctionIndex::get_signature_mismatch_trap_index().index() as usize] =
+            wasmer_vm_signature_mismatch_trap as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
@@ -0,0 +1,61 @@
+//! The allocation test of `TrapPool`, in its own test binary since it
+//! replaces the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use wasmer_vm::{Trap, TrapCode, TrapPool};
+
+/// Counts the allocations of each thread, so that the tests running in
+/// parallel don't disturb each other.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn frame_count(trap: &Trap) -> usize {
+    match trap {
+        Trap::Lib { backtrace, .. } => backtrace.frames().len(),
+        _ => panic!("expected a lib trap, got `{}`", trap),
+    }
+}
+
+#[test]
+fn repeated_requests_dont_allocate() {
+    // Warms up the pool, and the thread-locals of the backtrace crate.
+    for _ in 0..2 {
+        TrapPool::recycle(TrapPool::lib(TrapCode::HeapMisaligned));
+    }
+
+    let before = allocations();
+    for _ in 0..100 {
+        let trap = TrapPool::lib(TrapCode::HeapMisaligned);
+        assert_ne!(frame_count(&trap), 0);
+        TrapPool::recycle(trap);
+    }
+    assert_eq!(allocations(), before);
+    assert_eq!(TrapPool::len(), 1);
+
+    // Unlike `Trap::lib`.
+    let before = allocations();
+    drop(Trap::lib(TrapCode::HeapMisaligned));
+    assert!(allocations() > before);
+}
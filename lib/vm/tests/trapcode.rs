@@ -0,0 +1,63 @@
+use wasmer_vm::TrapCode;
+
+#[cfg(not(feature = "exhaustive-trap-codes"))]
+#[test]
+fn match_needs_wildcard() {
+    // `TrapCode` is `#[non_exhaustive]`, so the wildcard arm is required
+    // even though every trap code is matched.
+    fn is_proposal_specific(trap_code: TrapCode) -> bool {
+        match trap_code {
+            TrapCode::HeapMisaligned
+            | TrapCode::UnalignedAtomic
+            | TrapCode::AtomicAccessOutOfBounds
+            | TrapCode::UninitializedElement => true,
+            TrapCode::StackOverflow
+            | TrapCode::HeapAccessOutOfBounds
+            | TrapCode::TableAccessOutOfBounds
+            | TrapCode::OutOfBounds
+            | TrapCode::IndirectCallToNull
+            | TrapCode::BadSignature
+            | TrapCode::IntegerOverflow
+            | TrapCode::IntegerDivisionByZero
+            | TrapCode::BadConversionToInteger
+            | TrapCode::UnreachableCodeReached => false,
+            _ => false,
+        }
+    }
+    assert!(is_proposal_specific(TrapCode::UnalignedAtomic));
+    assert!(!is_proposal_specific(TrapCode::StackOverflow));
+}
+
+#[cfg(feature = "exhaustive-trap-codes")]
+#[test]
+fn match_is_exhaustive() {
+    // Doesn't compile once a trap code is added, which is the point of the
+    // feature.
+    fn is_proposal_specific(trap_code: TrapCode) -> bool {
+        match trap_code {
+            TrapCode::HeapMisaligned
+            | TrapCode::UnalignedAtomic
+            | TrapCode::AtomicAccessOutOfBounds
+            | TrapCode::UninitializedElement => true,
+            TrapCode::StackOverflow
+            | TrapCode::HeapAccessOutOfBounds
+            | TrapCode::TableAccessOutOfBounds
+            | TrapCode::OutOfBounds
+            | TrapCode::IndirectCallToNull
+            | TrapCode::BadSignature
+            | TrapCode::IntegerOverflow
+            | TrapCode::IntegerDivisionByZero
+            | TrapCode::BadConversionToInteger
+            | TrapCode::UnreachableCodeReached => false,
+        }
+    }
+    assert!(is_proposal_specific(TrapCode::UnalignedAtomic));
+    assert!(!is_proposal_specific(TrapCode::StackOverflow));
+}
+
+#[test]
+fn display_and_parse() {
+    for trap_code in TrapCode::all() {
+        assert_eq!(trap_code.to_string().parse(), Ok(trap_code));
+    }
+}
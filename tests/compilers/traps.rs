@@ -73,6 +73,28 @@ fn test_trap_trace(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(traps)]
+fn test_trap_invoked_export(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (func $hello (unreachable))
+            (export "run" (func $hello))
+            (export "other" (func $hello))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    for name in &["run", "other"] {
+        let func = instance.exports.get_function(name)?;
+        let e = func.call(&[]).err().expect("error calling function");
+        assert_eq!(e.invoked_export(), Some(*name));
+    }
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     let store = config.store();
@@ -409,7 +431,7 @@ fn call_signature_mismatch(config: crate::Config) -> Result<()> {
     assert_eq!(
         format!("{}", err),
         "\
-RuntimeError: indirect call type mismatch
+RuntimeError: indirect call type mismatch: expected type #0, got type #1
     at foo (a[0]:0x30)\
 "
     );
@@ -469,3 +491,91 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn call_indirect_null_element(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (type $t (func))
+            (table 2 funcref)
+            (elem (i32.const 0) funcref (ref.null func))
+            (func (export "call") (param i32)
+                local.get 0
+                call_indirect (type $t))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let call = instance.exports.get_function("call")?;
+
+    let err = call.call(&[Val::I32(0)]).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "uninitialized element: null function reference"
+    );
+
+    let err = call.call(&[Val::I32(1)]).unwrap_err();
+    assert_eq!(err.message(), "uninitialized element");
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn atomic_access_out_of_bounds(config: crate::Config) -> Result<()> {
+    let mut config = config;
+    let mut features = Features::default();
+    features.threads(true);
+    config.set_features(features);
+    let store = config.store();
+    let wat = r#"
+        (module
+            (memory 1)
+            (func (export "load") (result i32)
+                i32.const 65536
+                i32.atomic.load)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let load = instance.exports.get_function("load")?;
+
+    // Only Singlepass tells apart the out-of-bounds atomic accesses, the
+    // other compilers share the bounds checks of the regular accesses.
+    let err = load.call(&[]).unwrap_err();
+    if config.compiler == crate::Compiler::Singlepass {
+        assert_eq!(err.message(), "atomic: out of bounds memory access");
+    } else {
+        assert_eq!(err.message(), "out of bounds memory access");
+    }
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn call_indirect_signature_mismatch(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (type $t0 (func))
+            (type $t1 (func (result i32)))
+            (table 1 funcref)
+            (elem (i32.const 0) $f)
+            (func $f (type $t1) (i32.const 0))
+            (func (export "call")
+                i32.const 0
+                call_indirect (type $t0))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let call = instance.exports.get_function("call")?;
+
+    let err = call.call(&[]).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "indirect call type mismatch: expected type #0, got type #1"
+    );
+    Ok(())
+}
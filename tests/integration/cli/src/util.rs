@@ -1,8 +1,19 @@
-use anyhow::bail;
-use std::path::Path;
-use std::process::Command;
+use crate::assets::{get_wasmer_path, WASMER_INCLUDE_PATH};
+use crate::link_code::LinkCode;
+use anyhow::{anyhow, bail, Context};
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output};
+use std::time::{Duration, Instant};
+use wasmer_vm::TrapCode;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Compiler {
     Cranelift,
     LLVM,
@@ -10,16 +21,166 @@ pub enum Compiler {
 }
 
 impl Compiler {
-    pub const fn to_flag(self) -> &'static str {
+    pub const ALL: &'static [Compiler] =
+        &[Compiler::Cranelift, Compiler::LLVM, Compiler::Singlepass];
+
+    /// The name of the compiler, as used in its CLI flags.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Compiler::Cranelift => "cranelift",
+            Compiler::LLVM => "llvm",
+            Compiler::Singlepass => "singlepass",
+        }
+    }
+
+    pub fn to_flag(self) -> String {
+        format!("--{}", self.name())
+    }
+
+    /// The flag selecting the optimization level `level` for this compiler,
+    /// if it supports it.
+    pub fn opt_flag(self, level: OptLevel) -> Option<String> {
+        let value = match (self, level) {
+            (Compiler::Cranelift, OptLevel::None) => "none",
+            (Compiler::Cranelift, OptLevel::Speed) => "speed",
+            (Compiler::Cranelift, OptLevel::SpeedAndSize) => "speed_and_size",
+            (Compiler::LLVM, OptLevel::None) => "0",
+            (Compiler::LLVM, OptLevel::Speed) => "3",
+            (Compiler::LLVM, OptLevel::SpeedAndSize) | (Compiler::Singlepass, _) => return None,
+        };
+        Some(format!("{}-opt-level={}", self.to_flag(), value))
+    }
+
+    /// Whether this compiler is known to produce byte-identical artifacts
+    /// when compiling the same module twice on the same host.
+    ///
+    /// Only Singlepass qualifies: it compiles each function in a single
+    /// forward pass, with no state shared between functions. Cranelift
+    /// compiles the functions in parallel, and the order in which the
+    /// custom sections and relocations get emitted may vary between runs.
+    /// LLVM output depends on the LLVM version and on its target options,
+    /// which aren't pinned by the tests.
+    pub fn is_deterministic_output(self) -> bool {
+        match self {
+            Compiler::Singlepass => true,
+            Compiler::Cranelift | Compiler::LLVM => false,
+        }
+    }
+
+    /// The flag forcing stack probes on for this compiler, so that a deep
+    /// recursion traps with `StackOverflow` instead of crashing, or `None`
+    /// if they are always on.
+    ///
+    /// No compiler needs one yet: Cranelift calls `wasmer_vm_probestack`
+    /// for large frames, LLVM marks every function with
+    /// `"probe-stack"="inline-asm"`, and Singlepass touches every page of
+    /// its frames when it sets them up.
+    pub fn stack_probe_flag(self) -> Option<String> {
         match self {
-            Compiler::Cranelift => "--cranelift",
-            Compiler::LLVM => "--llvm",
-            Compiler::Singlepass => "--singlepass",
+            Compiler::Cranelift | Compiler::LLVM | Compiler::Singlepass => None,
+        }
+    }
+
+    /// The flag requesting the relocation model `model` for this compiler,
+    /// if it needs one.
+    ///
+    /// Cranelift and LLVM honor `--enable-pic`. Singlepass always emits
+    /// position-independent code, and ignores it.
+    pub fn relocation_flag(self, model: RelocationModel) -> Option<&'static str> {
+        match (self, model) {
+            (Compiler::Cranelift, RelocationModel::Pic)
+            | (Compiler::LLVM, RelocationModel::Pic) => Some("--enable-pic"),
+            (_, RelocationModel::Default) | (Compiler::Singlepass, _) => None,
+        }
+    }
+
+    /// Whether this compiler can compile modules using the feature `feature`.
+    ///
+    /// Singlepass supports neither SIMD nor multi-value.
+    pub fn supports_feature(self, feature: WasmFeature) -> bool {
+        match (self, feature) {
+            (Compiler::Singlepass, WasmFeature::Simd)
+            | (Compiler::Singlepass, WasmFeature::MultiValue) => false,
+            (Compiler::Cranelift, _) | (Compiler::LLVM, _) | (Compiler::Singlepass, _) => true,
+        }
+    }
+
+    /// The flags enabling `features` for this compiler.
+    ///
+    /// The features this compiler doesn't support are skipped, with a
+    /// warning.
+    pub fn feature_flags(self, features: &[WasmFeature]) -> Vec<String> {
+        features
+            .iter()
+            .filter(|&&feature| {
+                let supported = self.supports_feature(feature);
+                if !supported {
+                    eprintln!(
+                        "warning: {} doesn't support `{}`, skipping it",
+                        self.name(),
+                        feature.flag()
+                    );
+                }
+                supported
+            })
+            .map(|feature| feature.flag().to_string())
+            .collect()
+    }
+
+    /// Whether this compiler can generate code for the host architecture.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compiler::Cranelift | Compiler::Singlepass => {
+                cfg!(any(target_arch = "x86_64", target_arch = "aarch64"))
+            }
+            Compiler::LLVM => true,
+        }
+    }
+}
+
+/// A WebAssembly proposal that the CLI can enable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WasmFeature {
+    Simd,
+    Threads,
+    ReferenceTypes,
+    MultiValue,
+    BulkMemory,
+}
+
+impl WasmFeature {
+    /// The CLI flag enabling the feature.
+    pub const fn flag(self) -> &'static str {
+        match self {
+            WasmFeature::Simd => "--enable-simd",
+            WasmFeature::Threads => "--enable-threads",
+            WasmFeature::ReferenceTypes => "--enable-reference-types",
+            WasmFeature::MultiValue => "--enable-multi-value",
+            WasmFeature::BulkMemory => "--enable-bulk-memory",
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// An optimization level, as supported by the compilers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+/// A relocation model for the generated code.
+///
+/// The dylib and staticlib engines always emit position-independent code,
+/// whatever the model; `Pic` makes it explicit, eg. for artifacts meant to
+/// be embedded in a shared library and `dlopen`ed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RelocationModel {
+    Default,
+    Pic,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Engine {
     Universal,
     Dylib,
@@ -27,6 +188,17 @@ pub enum Engine {
 }
 
 impl Engine {
+    pub const ALL: &'static [Engine] = &[Engine::Universal, Engine::Dylib, Engine::Staticlib];
+
+    /// The name of the engine, as used in its CLI flag.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Engine::Universal => "universal",
+            Engine::Dylib => "dylib",
+            Engine::Staticlib => "staticlib",
+        }
+    }
+
     pub const fn to_flag(self) -> &'static str {
         match self {
             Engine::Universal => "--universal",
@@ -34,29 +206,1205 @@ impl Engine {
             Engine::Staticlib => "--staticlib",
         }
     }
+
+    /// The default extension of the artifacts of this engine, on the host.
+    pub fn artifact_extension(self) -> &'static str {
+        match self {
+            Engine::Universal => "wasmu",
+            Engine::Dylib if cfg!(windows) => "dll",
+            Engine::Dylib if cfg!(target_vendor = "apple") => "dylib",
+            Engine::Dylib => "so",
+            Engine::Staticlib if cfg!(windows) => "obj",
+            Engine::Staticlib => "o",
+        }
+    }
+
+    /// The leading bytes of the artifacts of this engine, on the host, as
+    /// checked by the engine before loading them.
+    pub fn artifact_magic(self) -> &'static [u8] {
+        match self {
+            Engine::Universal => b"wasmer-universal",
+            Engine::Dylib => NATIVE_DYLIB_MAGIC,
+            Engine::Staticlib => NATIVE_OBJECT_MAGIC,
+        }
+    }
 }
 
-pub fn run_code(
+/// The magic header of the shared libraries of the host.
+#[cfg(windows)]
+const NATIVE_DYLIB_MAGIC: &[u8] = &[b'M', b'Z'];
+#[cfg(not(windows))]
+const NATIVE_DYLIB_MAGIC: &[u8] = NATIVE_OBJECT_MAGIC;
+
+/// The magic header of the native object files of the host.
+///
+/// COFF object files have no magic, they start with the machine type.
+#[cfg(target_vendor = "apple")]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[207, 250, 237, 254];
+#[cfg(all(windows, target_arch = "x86_64"))]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[0x64, 0x86];
+#[cfg(all(windows, target_arch = "aarch64"))]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[0x64, 0xaa];
+#[cfg(all(windows, target_arch = "x86"))]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[0x4c, 0x01];
+#[cfg(all(
+    not(target_vendor = "apple"),
+    not(windows),
+    target_pointer_width = "64"
+))]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[0x7f, b'E', b'L', b'F', 2];
+#[cfg(all(
+    not(target_vendor = "apple"),
+    not(windows),
+    target_pointer_width = "32"
+))]
+const NATIVE_OBJECT_MAGIC: &[u8] = &[0x7f, b'E', b'L', b'F', 1];
+
+/// A compiler and engine pair to run a module with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Backend {
+    pub compiler: Compiler,
+    pub engine: Engine,
+}
+
+impl Backend {
+    pub fn is_supported(self) -> bool {
+        self.compiler.is_supported()
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.compiler.name(), self.engine.name())
+    }
+}
+
+/// The directory under `base` where the artifacts of `engine` are cached.
+pub fn cache_dir_for(engine: Engine, base: &Path) -> PathBuf {
+    base.join(engine.name())
+}
+
+/// Remove the artifacts of `engine` cached under `base`, if any.
+pub fn clear_cache(engine: Engine, base: &Path) -> anyhow::Result<()> {
+    let cache_dir = cache_dir_for(engine, base);
+    match fs::remove_dir_all(&cache_dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e)
+            .with_context(|| format!("failed to clear the cache at `{}`", cache_dir.display())),
+        _ => Ok(()),
+    }
+}
+
+/// All the supported compiler and engine pairs.
+pub fn all_backends() -> Vec<Backend> {
+    Compiler::ALL
+        .iter()
+        .flat_map(|&compiler| {
+            Engine::ALL
+                .iter()
+                .map(move |&engine| Backend { compiler, engine })
+        })
+        .filter(|backend| backend.is_supported())
+        .collect()
+}
+
+/// Run the executable, returning its output regardless of its exit status.
+pub fn run_code_with_output(
     operating_dir: &Path,
     executable_path: &Path,
     args: &[String],
-) -> anyhow::Result<String> {
+) -> anyhow::Result<Output> {
     let output = Command::new(executable_path.canonicalize()?)
         .current_dir(operating_dir)
         .args(args)
         .output()?;
 
+    Ok(output)
+}
+
+/// Why running `wasmer` failed, as told by its stderr.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    Compile,
+    Link,
+    Trap,
+    Usage,
+    Other,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FailureKind::Compile => "the module failed to compile",
+            FailureKind::Link => "the module failed to link",
+            FailureKind::Trap => "the module trapped",
+            FailureKind::Usage => "the command line is invalid",
+            FailureKind::Other => "the executable failed",
+        })
+    }
+}
+
+/// Classify a failure of `wasmer` from its stderr.
+pub fn classify_failure(stderr: &str) -> FailureKind {
+    // Traps while linking, eg. when initializing data segments, mention the
+    // runtime error, so link errors are checked first.
+    if stderr.contains("Error while importing ")
+        || stderr.contains("RuntimeError occurred during linking: ")
+    {
+        FailureKind::Link
+    } else if stderr.contains("RuntimeError: ") {
+        FailureKind::Trap
+    } else if stderr.contains("module instantiation failed")
+        || stderr.contains("Validation error: ")
+        || stderr.contains("Compilation error: ")
+        || stderr.contains("WebAssembly translation error: ")
+    {
+        FailureKind::Compile
+    } else if stderr.contains("USAGE:") {
+        FailureKind::Usage
+    } else {
+        FailureKind::Other
+    }
+}
+
+/// Run the executable, returning its stdout.
+///
+/// On failure, the error has the `FailureKind` of the failure as context,
+/// see `classify_failure`.
+pub fn run_code(
+    operating_dir: &Path,
+    executable_path: &Path,
+    args: &[String],
+) -> anyhow::Result<String> {
+    let output = run_code_with_output(operating_dir, executable_path, args)?;
+
     if !output.status.success() {
-        bail!(
+        let stderr = std::str::from_utf8(&output.stderr)
+            .expect("stderr is not utf8! need to handle arbitrary bytes");
+        return Err(anyhow!(
             "running executable failed: stdout: {}\n\nstderr: {}",
             std::str::from_utf8(&output.stdout)
                 .expect("stdout is not utf8! need to handle arbitrary bytes"),
-            std::str::from_utf8(&output.stderr)
-                .expect("stderr is not utf8! need to handle arbitrary bytes")
-        );
+            stderr
+        )
+        .context(classify_failure(stderr)));
     }
     let output =
         std::str::from_utf8(&output.stdout).expect("output from running executable is not utf-8");
 
     Ok(output.to_owned())
 }
+
+/// Run the executable, and check that its stdout is `expected_stdout`.
+/// Leading and trailing whitespace is ignored on both sides.
+pub fn run_expect(
+    operating_dir: &Path,
+    executable_path: &Path,
+    args: &[String],
+    expected_stdout: &str,
+) -> anyhow::Result<()> {
+    let stdout = run_code(operating_dir, executable_path, args)?;
+    check_stdout_eq(&stdout, expected_stdout)
+}
+
+/// Run the executable, and check that its stdout contains `needle`.
+pub fn run_contains(
+    operating_dir: &Path,
+    executable_path: &Path,
+    args: &[String],
+    needle: &str,
+) -> anyhow::Result<()> {
+    let stdout = run_code(operating_dir, executable_path, args)?;
+    check_stdout_contains(&stdout, needle)
+}
+
+fn check_stdout_eq(stdout: &str, expected: &str) -> anyhow::Result<()> {
+    let (stdout, expected) = (stdout.trim(), expected.trim());
+    if stdout != expected {
+        bail!(
+            "unexpected stdout\n\nexpected:\n{}\n\nactual:\n{}",
+            expected,
+            stdout
+        );
+    }
+    Ok(())
+}
+
+fn check_stdout_contains(stdout: &str, needle: &str) -> anyhow::Result<()> {
+    if !stdout.contains(needle) {
+        bail!(
+            "stdout doesn't contain `{}`\n\nstdout:\n{}",
+            needle,
+            stdout.trim()
+        );
+    }
+    Ok(())
+}
+
+/// The C entrypoint of the executables generated by `wasmer create-exe`,
+/// which runs the (WASI) module of a staticlib object.
+const CREATE_EXE_MAIN_C_SOURCE: &[u8] =
+    include_bytes!("../../../../lib/cli/src/commands/wasmer_create_exe_main.c");
+
+/// Compile the C code.
+pub fn run_c_compile(
+    current_dir: &Path,
+    path_to_c_src: &Path,
+    output_name: &Path,
+) -> anyhow::Result<()> {
+    #[cfg(not(windows))]
+    let c_compiler = "cc";
+    #[cfg(windows)]
+    let c_compiler = "clang++";
+
+    let output = Command::new(c_compiler)
+        .current_dir(current_dir)
+        .arg("-O2")
+        .arg("-c")
+        .arg(path_to_c_src)
+        .arg("-I")
+        .arg(WASMER_INCLUDE_PATH)
+        .arg("-o")
+        .arg(output_name)
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "C code compile failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+    Ok(())
+}
+
+/// Link the objects, along with the static libwasmer, into an executable.
+pub fn link_object(
+    current_dir: &Path,
+    object_paths: &[PathBuf],
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    LinkCode {
+        current_dir: current_dir.to_owned(),
+        object_paths: object_paths.to_vec(),
+        output_path: output_path.to_owned(),
+        ..Default::default()
+    }
+    .run()
+}
+
+/// The arguments of `wasmer` compiling `wasm` to the object file `output`
+/// with `compiler`, the staticlib (object file) engine and the relocation
+/// model `relocation_model`.
+pub fn object_compile_args(
+    wasm: &Path,
+    compiler: Compiler,
+    relocation_model: RelocationModel,
+    output: &Path,
+) -> Vec<OsString> {
+    let mut args = vec![
+        OsString::from("compile"),
+        wasm.into(),
+        compiler.to_flag().into(),
+        Engine::Staticlib.to_flag().into(),
+    ];
+    if let Some(flag) = compiler.relocation_flag(relocation_model) {
+        args.push(flag.into());
+    }
+    args.push("-o".into());
+    args.push(output.into());
+    args
+}
+
+/// Compile the module to an object file with `compiler` and the staticlib
+/// (object file) engine, and disassemble it with `objdump`.
+///
+/// The CLI can't print the code it generates, so the object file goes
+/// through a temp dir. The functions of the module are named
+/// `wasmer_function_PREFIX_INDEX` in the disassembly.
+pub fn dump_asm(executable: &Path, wasm: &Path, compiler: Compiler) -> anyhow::Result<String> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let object_path = temp_dir.path().join("wasm.o");
+    let output = Command::new(executable)
+        .args(object_compile_args(
+            wasm,
+            compiler,
+            RelocationModel::Default,
+            &object_path,
+        ))
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stdout: {}\n\nstderr: {}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let output = Command::new("objdump")
+        .arg("--disassemble")
+        .arg(&object_path)
+        .output()
+        .context("Failed to run objdump")?;
+    if !output.status.success() {
+        bail!(
+            "objdump failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).context("output of objdump is not utf-8")
+}
+
+/// Compile the module to an object file with `compiler` and the staticlib
+/// (object file) engine, link it into an executable, and run it.
+pub fn compile_link_run(
+    wasm: &Path,
+    compiler: Compiler,
+    args: &[String],
+) -> anyhow::Result<String> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir = temp_dir.path();
+    #[cfg(not(windows))]
+    let (wasm_object_path, c_object_path) =
+        (operating_dir.join("wasm.o"), operating_dir.join("main.o"));
+    #[cfg(windows)]
+    let (wasm_object_path, c_object_path) = (
+        operating_dir.join("wasm.obj"),
+        operating_dir.join("main.obj"),
+    );
+    let c_src_path = operating_dir.join("main.c");
+    let executable_path = operating_dir.join("a.out");
+
+    let output = Command::new(get_wasmer_path())
+        .current_dir(operating_dir)
+        .args(object_compile_args(
+            &wasm.canonicalize()?,
+            compiler,
+            RelocationModel::Default,
+            &wasm_object_path,
+        ))
+        .arg("--header")
+        .arg(operating_dir.join("my_wasm.h"))
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    fs::write(&c_src_path, CREATE_EXE_MAIN_C_SOURCE)?;
+    run_c_compile(operating_dir, &c_src_path, &c_object_path)
+        .context("Failed to compile C source code")?;
+    link_object(
+        operating_dir,
+        &[c_object_path, wasm_object_path],
+        &executable_path,
+    )
+    .context("Failed to link objects together")?;
+
+    run_code(operating_dir, &executable_path, args).context("Failed to run generated executable")
+}
+
+/// The compilers and engines a `wasmer` binary accepts, see
+/// `capability_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityReport {
+    /// The output of `wasmer --version`, trimmed.
+    pub version: String,
+    /// The compilers with a flag in the help.
+    pub compilers: Vec<Compiler>,
+    /// The engines with a flag in the help.
+    pub engines: Vec<Engine>,
+}
+
+/// Report the version of the `wasmer` binary at `executable`, and the
+/// compilers and engines whose flags it accepts.
+///
+/// The flags are read from the help of `wasmer run`, since `wasmer --help`
+/// only lists the subcommands. A listed compiler may still fail to compile
+/// for the host architecture, see `Compiler::is_supported`.
+pub fn capability_report(executable: &Path) -> anyhow::Result<CapabilityReport> {
+    let stdout_of = |args: &[&str]| -> anyhow::Result<String> {
+        let output = Command::new(executable).args(args).output()?;
+        if !output.status.success() {
+            bail!(
+                "`{} {}` failed: stderr: {}",
+                executable.display(),
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let version = stdout_of(&["--version"])?;
+    let help = stdout_of(&["run", "--help"])?;
+    parse_capabilities(&version, &help)
+}
+
+fn parse_capabilities(version: &str, help: &str) -> anyhow::Result<CapabilityReport> {
+    // Match whole flags only: `--llvm-opt-level` doesn't mean `--llvm`.
+    let has_flag = |flag: &str| -> anyhow::Result<bool> {
+        let regex = Regex::new(&format!(r"(^|\s){}(\s|,|$)", regex::escape(flag)))?;
+        Ok(help.lines().any(|line| regex.is_match(line)))
+    };
+    let mut compilers = Vec::new();
+    for &compiler in Compiler::ALL {
+        if has_flag(&compiler.to_flag())? {
+            compilers.push(compiler);
+        }
+    }
+    let mut engines = Vec::new();
+    for &engine in Engine::ALL {
+        if has_flag(engine.to_flag())? {
+            engines.push(engine);
+        }
+    }
+    Ok(CapabilityReport {
+        version: version.trim().to_string(),
+        compilers,
+        engines,
+    })
+}
+
+/// Check whether the `wasmer` binary at `executable` can load the artifact
+/// at `artifact`, serialized by `engine`, possibly by another version.
+///
+/// The artifact is run without arguments nor stdin; it counts as loaded if
+/// it ran, even if it then trapped, failed to instantiate or exited with an
+/// error code, but not if `wasmer` crashed.
+pub fn can_load_artifact(
+    executable: &Path,
+    artifact: &Path,
+    engine: Engine,
+) -> anyhow::Result<bool> {
+    let output = Command::new(executable)
+        .arg("run")
+        .arg(artifact)
+        .arg(engine.to_flag())
+        .output()?;
+    let stderr = std::str::from_utf8(&output.stderr)
+        .expect("stderr is not utf8! need to handle arbitrary bytes");
+    Ok(output.status.success() || (!crashed(&output.status) && ran_before_failing(stderr)))
+}
+
+/// Compile `wasm` with `executable` for `engine`, and return the bytes of
+/// the serialized artifact.
+///
+/// The CLI can't write artifacts to a pipe, since `wasmer compile` prints
+/// to stdout too, so the artifact goes through a file in a temp dir of its
+/// own, which keeps parallel tests apart.
+pub fn serialize_to_bytes(
+    executable: &Path,
+    wasm: &Path,
+    engine: Engine,
+) -> anyhow::Result<Vec<u8>> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let artifact = temp_dir
+        .path()
+        .join(format!("module.{}", engine.artifact_extension()));
+    let output = Command::new(executable)
+        .arg("compile")
+        .arg(wasm)
+        .arg(engine.to_flag())
+        .arg("-o")
+        .arg(&artifact)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(fs::read(&artifact)?)
+}
+
+/// Run the serialized artifact `bytes` of `engine` with `executable`, and
+/// return its stdout.
+///
+/// `wasmer run` reads the artifact from its path more than once, so like
+/// `serialize_to_bytes` the artifact goes through a file in a temp dir of
+/// its own. The staticlib engine's object files can't be run.
+pub fn run_from_bytes(
+    executable: &Path,
+    bytes: &[u8],
+    engine: Engine,
+    args: &[String],
+) -> anyhow::Result<String> {
+    if engine == Engine::Staticlib {
+        bail!("the artifacts of the staticlib engine can't be run");
+    }
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let artifact = temp_dir
+        .path()
+        .join(format!("module.{}", engine.artifact_extension()));
+    fs::write(&artifact, bytes)?;
+    let output = Command::new(executable)
+        .arg("run")
+        .arg(&artifact)
+        .arg(engine.to_flag())
+        .arg("--")
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "running the artifact failed: stdout: {}\n\nstderr: {}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).context("output of the artifact is not utf-8")
+}
+
+/// Compiled artifacts of test modules, keyed by the hash of the module and
+/// the backend they were compiled with, so that a test binary compiles each
+/// module once per backend.
+///
+/// The artifacts live in a temp dir, removed with the cache.
+#[derive(Debug)]
+pub struct FixtureCache {
+    dir: tempfile::TempDir,
+    artifacts: HashMap<(u64, Backend), PathBuf>,
+    hits: usize,
+}
+
+impl FixtureCache {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            dir: tempfile::tempdir().context("Making a temp dir")?,
+            artifacts: HashMap::new(),
+            hits: 0,
+        })
+    }
+
+    /// The artifact of `wasm` compiled with `backend`, compiling it unless
+    /// it is already cached.
+    pub fn artifact(&mut self, wasm: &Path, backend: Backend) -> anyhow::Result<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        fs::read(wasm)?.hash(&mut hasher);
+        let key = (hasher.finish(), backend);
+        if let Some(artifact) = self.artifacts.get(&key) {
+            self.hits += 1;
+            return Ok(artifact.clone());
+        }
+
+        let artifact = self.dir.path().join(format!(
+            "{:016x}-{}-{}.{}",
+            key.0,
+            backend.compiler.name(),
+            backend.engine.name(),
+            backend.engine.artifact_extension()
+        ));
+        let output = Command::new(get_wasmer_path())
+            .arg("compile")
+            .arg(wasm)
+            .arg(backend.compiler.to_flag())
+            .arg(backend.engine.to_flag())
+            .arg("-o")
+            .arg(&artifact)
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "wasmer compile failed with: stderr: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        self.artifacts.insert(key, artifact.clone());
+        Ok(artifact)
+    }
+
+    /// Run the artifact of `wasm` compiled with `backend`, and return its
+    /// stdout. The staticlib engine's object files can't be run.
+    pub fn run_from_artifact(
+        &mut self,
+        wasm: &Path,
+        backend: Backend,
+        args: &[String],
+    ) -> anyhow::Result<String> {
+        if backend.engine == Engine::Staticlib {
+            bail!("the artifacts of the staticlib engine can't be run");
+        }
+        let artifact = self.artifact(wasm, backend)?;
+        let output = Command::new(get_wasmer_path())
+            .arg("run")
+            .arg(&artifact)
+            .arg(backend.engine.to_flag())
+            .arg("--")
+            .args(args)
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "running `{}` failed: stdout: {}\n\nstderr: {}",
+                artifact.display(),
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        String::from_utf8(output.stdout).context("output of the artifact is not utf-8")
+    }
+
+    /// How many artifacts were found in the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+/// Run the module at `path` with `wasmer run` on both backends, and check
+/// that their stdout is the same.
+///
+/// On mismatch, the error shows a line diff of the two stdouts. The
+/// staticlib engine's object files can't be run.
+pub fn assert_same_output(
+    path: &Path,
+    backend_a: Backend,
+    backend_b: Backend,
+    args: &[String],
+) -> anyhow::Result<()> {
+    let stdout_a = run_with_backend(path, backend_a, args)?;
+    let stdout_b = run_with_backend(path, backend_b, args)?;
+    if stdout_a != stdout_b {
+        bail!(
+            "running `{}` gave different outputs:\n{}",
+            path.display(),
+            unified_diff(
+                &backend_a.to_string(),
+                &stdout_a,
+                &backend_b.to_string(),
+                &stdout_b
+            )
+        );
+    }
+    Ok(())
+}
+
+fn run_with_backend(path: &Path, backend: Backend, args: &[String]) -> anyhow::Result<String> {
+    if backend.engine == Engine::Staticlib {
+        bail!("the artifacts of the staticlib engine can't be run");
+    }
+    let output = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg(path)
+        .arg(backend.compiler.to_flag())
+        .arg(backend.engine.to_flag())
+        .arg("--")
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "running `{}` with {} failed: stdout: {}\n\nstderr: {}",
+            path.display(),
+            backend,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    String::from_utf8(output.stdout).context("output of the module is not utf-8")
+}
+
+/// A diff of the lines of `a` and `b`, in the style of `diff -u` but with
+/// all the lines as context.
+fn unified_diff(label_a: &str, a: &str, label_b: &str, b: &str) -> String {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of
+    // a[i..] and b[j..].
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", label_a, label_b);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push_str(&format!(" {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", a[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", b[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// The errors reported by `wasmer run` when it can't load an artifact: the
+/// errors of its deserialization, and the error of the compilation of the
+/// artifacts it doesn't recognize as such, as if they were wasm.
+const LOAD_ERRORS: &[&str] = &[
+    "incompatible binary: ",
+    "corrupted binary: ",
+    "module instantiation failed",
+];
+
+/// Whether the error reported by a failed `wasmer run` happened after the
+/// module was loaded.
+fn ran_before_failing(stderr: &str) -> bool {
+    !LOAD_ERRORS.iter().any(|error| stderr.contains(error))
+}
+
+/// Whether a process died of a signal, or of an unhandled exception on
+/// Windows, rather than exiting.
+fn crashed(status: &ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal().is_some()
+    }
+    #[cfg(not(unix))]
+    {
+        // The NTSTATUS of the exception, with the error severity.
+        status
+            .code()
+            .map_or(true, |code| code as u32 & 0xC000_0000 == 0xC000_0000)
+    }
+}
+
+/// Run the module with `wasmer run` on the given backend, and return how
+/// long the run took, compilation included.
+pub fn run_timed(path: &Path, compiler: Compiler, engine: Engine) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    let output = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg(compiler.to_flag())
+        .arg(engine.to_flag())
+        .arg(path)
+        .output()?;
+    let elapsed = start.elapsed();
+
+    if !output.status.success() {
+        bail!(
+            "running `{}` failed: stderr: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(elapsed)
+}
+
+/// The durations of the runs of a benchmark, see `bench_run`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl BenchResult {
+    /// Summarize the durations of the runs, or `None` if there were none.
+    ///
+    /// With an even number of runs, the median is the slower of the two
+    /// middle runs.
+    pub fn from_durations(mut durations: Vec<Duration>) -> Option<Self> {
+        durations.sort();
+        Some(Self {
+            min: *durations.first()?,
+            median: durations[durations.len() / 2],
+            max: *durations.last()?,
+        })
+    }
+}
+
+/// Run the module `iterations` times with `run_timed`, and summarize the
+/// durations of the runs.
+pub fn bench_run(
+    path: &Path,
+    compiler: Compiler,
+    engine: Engine,
+    iterations: usize,
+) -> anyhow::Result<BenchResult> {
+    let durations = (0..iterations)
+        .map(|_| run_timed(path, compiler, engine))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    BenchResult::from_durations(durations).context("benchmarks need at least one iteration")
+}
+
+/// Run the module with `wasmer run`, and check that it exits with the
+/// status code `expected_code`, returning its output.
+///
+/// Unlike `run_code`, a non-zero status code isn't an error by itself,
+/// eg. to check the code passed to WASI's `proc_exit`.
+pub fn run_expect_exit(path: &Path, args: &[String], expected_code: i32) -> anyhow::Result<Output> {
+    let output = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg(path)
+        .arg("--")
+        .args(args)
+        .output()?;
+    if output.status.code() != Some(expected_code) {
+        bail!(
+            "running `{}` exited with {}, expected the code {}: stdout: {}\n\nstderr: {}",
+            path.display(),
+            output.status,
+            expected_code,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output)
+}
+
+/// Run the module with `wasmer run`, and check that its stdout matches the
+/// regular expression `pattern`. `args` are passed to the module.
+pub fn run_and_match(path: &Path, args: &[String], pattern: &str) -> anyhow::Result<()> {
+    let regex = Regex::new(pattern)?;
+    let output = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg(path)
+        .arg("--")
+        .args(args)
+        .output()?;
+    let stdout = std::str::from_utf8(&output.stdout)
+        .expect("stdout is not utf8! need to handle arbitrary bytes");
+
+    if !output.status.success() {
+        bail!(
+            "running `{}` failed: stdout: {}\n\nstderr: {}",
+            path.display(),
+            stdout,
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+    if !regex.is_match(stdout) {
+        bail!(
+            "the stdout of `{}` doesn't match `{}`\n\nstdout: {}",
+            path.display(),
+            pattern,
+            stdout
+        );
+    }
+
+    Ok(())
+}
+
+/// Run the module with `wasmer run`, and check that it traps with `expected`.
+pub fn assert_traps_with(path: &Path, args: &[String], expected: TrapCode) -> anyhow::Result<()> {
+    let output = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg(path)
+        .arg("--")
+        .args(args)
+        .output()?;
+    let stderr = std::str::from_utf8(&output.stderr)
+        .expect("stderr is not utf8! need to handle arbitrary bytes");
+
+    if output.status.success() {
+        bail!(
+            "running `{}` succeeded, but it was expected to trap with `{}`",
+            path.display(),
+            expected
+        );
+    }
+    match parse_trap_code(stderr) {
+        Some(trap_code) if trap_code == expected => Ok(()),
+        Some(trap_code) => bail!(
+            "running `{}` trapped with `{}`, but it was expected to trap with `{}`",
+            path.display(),
+            trap_code,
+            expected
+        ),
+        None => bail!(
+            "running `{}` failed without a trap, but it was expected to trap with `{}`\n\nstderr: {}",
+            path.display(),
+            expected,
+            stderr
+        ),
+    }
+}
+
+/// Find the trap code reported in the stderr of `wasmer run`, either by
+/// its message, its short identifier or its number.
+fn parse_trap_code(stderr: &str) -> Option<TrapCode> {
+    stderr.lines().find_map(|line| {
+        let (_, reported) = line.split_once("RuntimeError: ")?;
+        let reported = reported.trim();
+        // Some messages are prefixes of others (eg. "out of bounds"), so
+        // pick the longest one that matches.
+        TrapCode::all()
+            .filter(|trap_code| reported.starts_with(trap_code.message()))
+            .max_by_key(|trap_code| trap_code.message().len())
+            .or_else(|| TrapCode::parse_lenient(reported).ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_of_lines() {
+        assert_eq!(
+            unified_diff("a", "1\n2\n3\n", "b", "1\n3\n4\n"),
+            "--- a\n+++ b\n 1\n-2\n 3\n+4\n"
+        );
+        assert_eq!(unified_diff("a", "", "b", "1"), "--- a\n+++ b\n+1\n");
+    }
+
+    #[test]
+    fn feature_flags() {
+        let features = [WasmFeature::Simd, WasmFeature::Threads];
+        for compiler in [Compiler::Cranelift, Compiler::LLVM] {
+            assert_eq!(
+                compiler.feature_flags(&features),
+                ["--enable-simd", "--enable-threads"]
+            );
+        }
+        assert_eq!(
+            Compiler::Singlepass.feature_flags(&features),
+            ["--enable-threads"]
+        );
+    }
+
+    #[test]
+    fn parse_trap_code_from_stderr() {
+        let stderr = |reported: &str| {
+            format!(
+                "error: failed to run `trap.wat`\n╰─▶ 1: RuntimeError: {}",
+                reported
+            )
+        };
+        assert_eq!(
+            parse_trap_code(&stderr("out of bounds memory access")),
+            Some(TrapCode::HeapAccessOutOfBounds)
+        );
+        assert_eq!(
+            parse_trap_code(&stderr("int_divz")),
+            Some(TrapCode::IntegerDivisionByZero)
+        );
+        assert_eq!(
+            parse_trap_code(&stderr("7")),
+            Some(TrapCode::IntegerOverflow)
+        );
+        assert_eq!(parse_trap_code("error: unknown import"), None);
+    }
+
+    #[test]
+    fn classify_failure_from_stderr() {
+        assert_eq!(
+            classify_failure(
+                "error: failed to run `invalid.wat`\n\
+                 ╰─▶ 1: module instantiation failed (engine: universal, compiler: cranelift)\n\
+                 \x20   2: Validation error: type mismatch"
+            ),
+            FailureKind::Compile
+        );
+        assert_eq!(
+            classify_failure(
+                "error: failed to run `trap.wat`\n\
+                 ╰─▶ 1: RuntimeError: integer divide by zero"
+            ),
+            FailureKind::Trap
+        );
+        assert_eq!(
+            classify_failure(
+                "error: failed to run `data.wat`\n\
+                 ╰─▶ 1: RuntimeError occurred during linking: RuntimeError: out of bounds memory access"
+            ),
+            FailureKind::Link
+        );
+        assert_eq!(
+            classify_failure("error: Error while importing \"env\".\"f\": unknown import"),
+            FailureKind::Link
+        );
+        assert_eq!(
+            classify_failure("error: Found argument '--nope' which wasn't expected\n\nUSAGE:\n"),
+            FailureKind::Usage
+        );
+        assert_eq!(classify_failure("Segmentation fault"), FailureKind::Other);
+    }
+
+    #[test]
+    fn artifact_extension_and_magic() {
+        assert_eq!(Engine::Universal.artifact_extension(), "wasmu");
+        if cfg!(target_os = "linux") {
+            assert_eq!(Engine::Dylib.artifact_extension(), "so");
+            assert_eq!(Engine::Staticlib.artifact_extension(), "o");
+        }
+        for engine in Engine::ALL {
+            assert!(!engine.artifact_magic().is_empty());
+        }
+    }
+
+    #[test]
+    fn cache_dir_for_engines() -> anyhow::Result<()> {
+        let base = tempfile::tempdir()?;
+        let dirs: Vec<_> = Engine::ALL
+            .iter()
+            .map(|&engine| cache_dir_for(engine, base.path()))
+            .collect();
+        for (i, dir) in dirs.iter().enumerate() {
+            assert_eq!(dir.parent(), Some(base.path()));
+            assert!(!dirs[i + 1..].contains(dir));
+        }
+
+        let universal = cache_dir_for(Engine::Universal, base.path());
+        fs::create_dir_all(&universal)?;
+        fs::write(universal.join("module.wasmu"), b"wasmer-universal")?;
+        clear_cache(Engine::Universal, base.path())?;
+        assert!(!universal.exists());
+        // Clearing an empty cache is fine.
+        clear_cache(Engine::Universal, base.path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn parse_capabilities_matches_whole_flags() -> anyhow::Result<()> {
+        let help = "FLAGS:\n        --cranelift    Use Cranelift compiler.\n        \
+                    --universal    Use the Universal Engine.\n\nOPTIONS:\n        \
+                    --llvm-opt-level <llvm-opt-level>    LLVM optimization level\n";
+        let report = parse_capabilities("wasmer 2.2.1\n", help)?;
+        assert_eq!(report.version, "wasmer 2.2.1");
+        assert_eq!(report.compilers, vec![Compiler::Cranelift]);
+        assert_eq!(report.engines, vec![Engine::Universal]);
+        Ok(())
+    }
+
+    #[test]
+    fn bench_result() {
+        let ms = Duration::from_millis;
+        let result = BenchResult::from_durations(vec![ms(30), ms(10), ms(20), ms(50)]).unwrap();
+        assert_eq!(
+            result,
+            BenchResult {
+                min: ms(10),
+                median: ms(30),
+                max: ms(50),
+            }
+        );
+        assert!(result.min <= result.median && result.median <= result.max);
+        assert!(result.min > Duration::from_secs(0));
+        assert_eq!(BenchResult::from_durations(Vec::new()), None);
+    }
+
+    #[test]
+    fn ran_before_failing_tells_load_failures_apart() {
+        assert!(ran_before_failing(""));
+        assert!(ran_before_failing(
+            "error: failed to run `trap.wasmu`\n╰─> 1: RuntimeError: unreachable\n"
+        ));
+        assert!(ran_before_failing(
+            "error: failed to run `wasi.wasmu`\n╰─> 1: failed to instantiate WASI module\n"
+        ));
+        assert!(ran_before_failing(
+            "error: failed to run `main.wasmu`\n╰─> 1: Incompatible Export Type\n"
+        ));
+        assert!(!ran_before_failing(
+            "error: failed to run `old.wasmu`\n╰─> 1: incompatible binary: The provided bytes \
+             were serialized by an incompatible version\n"
+        ));
+        assert!(!ran_before_failing(
+            "error: failed to run `old.wasmu`\n╰─> 1: corrupted binary: invalid metadata header\n"
+        ));
+        assert!(!ran_before_failing(
+            "error: failed to run `old.wasmu`\n├╌ module instantiation failed (engine: \
+             universal, compiler: cranelift)\n╰─> 1: Validation error\n"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn crashes_are_not_loads() {
+        use std::os::unix::process::ExitStatusExt;
+        assert!(!crashed(&ExitStatus::from_raw(0)));
+        // Exited with status 1.
+        assert!(!crashed(&ExitStatus::from_raw(1 << 8)));
+        // Killed by SIGSEGV, signal 11.
+        assert!(crashed(&ExitStatus::from_raw(11)));
+    }
+
+    #[test]
+    fn check_stdout() {
+        assert!(check_stdout_eq("Hello, World\n", "  Hello, World").is_ok());
+        let error = check_stdout_eq("Hello, World\n", "Goodbye").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unexpected stdout\n\nexpected:\nGoodbye\n\nactual:\nHello, World"
+        );
+
+        assert!(check_stdout_contains("version 2.2.1\n", "2.2.1").is_ok());
+        assert!(check_stdout_contains("version 2.2.1\n", "3.0.0").is_err());
+    }
+
+    #[test]
+    fn name_matches_flag() {
+        for compiler in Compiler::ALL {
+            assert_eq!(format!("--{}", compiler.name()), compiler.to_flag());
+        }
+    }
+
+    #[test]
+    fn is_deterministic_output() {
+        assert!(Compiler::Singlepass.is_deterministic_output());
+        assert!(!Compiler::Cranelift.is_deterministic_output());
+        assert!(!Compiler::LLVM.is_deterministic_output());
+    }
+
+    #[test]
+    fn opt_flag() {
+        assert_eq!(
+            Compiler::LLVM.opt_flag(OptLevel::Speed).as_deref(),
+            Some("--llvm-opt-level=3")
+        );
+        assert_eq!(
+            Compiler::Cranelift
+                .opt_flag(OptLevel::SpeedAndSize)
+                .as_deref(),
+            Some("--cranelift-opt-level=speed_and_size")
+        );
+        assert_eq!(Compiler::LLVM.opt_flag(OptLevel::SpeedAndSize), None);
+        assert_eq!(Compiler::Singlepass.opt_flag(OptLevel::None), None);
+    }
+
+    #[test]
+    fn stack_probe_flag() {
+        for &compiler in Compiler::ALL {
+            assert_eq!(compiler.stack_probe_flag(), None, "{:?}", compiler);
+        }
+    }
+
+    #[test]
+    fn object_compile_args_relocation_model() {
+        let args = |compiler, model| {
+            object_compile_args(Path::new("a.wasm"), compiler, model, Path::new("a.o"))
+        };
+        let pic = args(Compiler::Cranelift, RelocationModel::Pic);
+        assert!(pic.iter().any(|arg| arg == "--enable-pic"));
+        assert_eq!(pic.last(), Some(&OsString::from("a.o")));
+        assert!(args(Compiler::LLVM, RelocationModel::Pic)
+            .iter()
+            .any(|arg| arg == "--enable-pic"));
+        assert!(!args(Compiler::Cranelift, RelocationModel::Default)
+            .iter()
+            .any(|arg| arg == "--enable-pic"));
+        assert!(!args(Compiler::Singlepass, RelocationModel::Pic)
+            .iter()
+            .any(|arg| arg == "--enable-pic"));
+    }
+
+    #[test]
+    fn all_backends_covers_supported_compilers() {
+        let supported_compilers = Compiler::ALL
+            .iter()
+            .filter(|compiler| compiler.is_supported())
+            .count();
+        assert_eq!(
+            all_backends().len(),
+            supported_compilers * Engine::ALL.len()
+        );
+    }
+}
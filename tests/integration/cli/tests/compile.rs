@@ -78,37 +78,16 @@ impl WasmerCompile {
     }
 }
 
-/// Compile the C code.
-fn run_c_compile(
-    current_dir: &Path,
-    path_to_c_src: &Path,
-    output_name: &Path,
-) -> anyhow::Result<()> {
-    #[cfg(not(windows))]
-    let c_compiler = "cc";
-    #[cfg(windows)]
-    let c_compiler = "clang++";
-
-    let output = Command::new(c_compiler)
-        .current_dir(current_dir)
-        .arg("-O2")
-        .arg("-c")
-        .arg(path_to_c_src)
-        .arg("-I")
-        .arg(WASMER_INCLUDE_PATH)
-        .arg("-o")
-        .arg(output_name)
-        .output()?;
+#[test]
+fn compile_link_run_works() -> anyhow::Result<()> {
+    let result = compile_link_run(
+        Path::new(&staticlib_engine_test_wasm_path()),
+        Compiler::Cranelift,
+        &["--eval".to_string(), "function greet(name) { return JSON.stringify('Hello, ' + name); }; print(greet('World'));".to_string()],
+    )?;
+    let result_lines = result.lines().collect::<Vec<&str>>();
+    assert_eq!(result_lines, vec!["\"Hello, World\""]);
 
-    if !output.status.success() {
-        bail!(
-            "C code compile failed with: stdout: {}\n\nstderr: {}",
-            std::str::from_utf8(&output.stdout)
-                .expect("stdout is not utf8! need to handle arbitrary bytes"),
-            std::str::from_utf8(&output.stderr)
-                .expect("stderr is not utf8! need to handle arbitrary bytes")
-        );
-    }
     Ok(())
 }
 
@@ -170,3 +149,108 @@ fn staticlib_engine_works() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn can_load_freshly_compiled_artifact() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let artifact = temp_dir.path().join("qjs.wasmu");
+    let output = Command::new(get_wasmer_path())
+        .arg("compile")
+        .arg(staticlib_engine_test_wasm_path())
+        .arg(Compiler::Cranelift.to_flag())
+        .arg(Engine::Universal.to_flag())
+        .arg("-o")
+        .arg(&artifact)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile failed with: stderr: {}",
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    assert!(can_load_artifact(
+        &get_wasmer_path(),
+        &artifact,
+        Engine::Universal
+    )?);
+    Ok(())
+}
+
+#[test]
+fn dump_asm_of_add() -> anyhow::Result<()> {
+    let wasm = Path::new(ASSET_PATH).join("add.wat");
+    let asm = dump_asm(&get_wasmer_path(), &wasm, Compiler::Cranelift)?;
+    assert!(
+        asm.contains("wasmer_function_"),
+        "no function in the disassembly:\n{}",
+        asm
+    );
+    Ok(())
+}
+
+/// Compiles `add.wat` to a universal artifact with `compiler` and the extra
+/// arguments `args`, returning the stderr of `wasmer` if it fails.
+fn compile_add(compiler: Compiler, args: &[String]) -> anyhow::Result<Result<(), String>> {
+    let temp_dir = tempfile::tempdir()?;
+    let output = Command::new(get_wasmer_path())
+        .arg("compile")
+        .arg(Path::new(ASSET_PATH).join("add.wat"))
+        .arg(compiler.to_flag())
+        .arg(Engine::Universal.to_flag())
+        .args(args)
+        .arg("-o")
+        .arg(temp_dir.path().join("add.wasmu"))
+        .output()?;
+    if output.status.success() {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(String::from_utf8_lossy(&output.stderr).into_owned()))
+    }
+}
+
+#[test]
+fn compile_with_each_opt_level() -> anyhow::Result<()> {
+    for &compiler in &[Compiler::Cranelift, Compiler::LLVM] {
+        if let Err(stderr) = compile_add(compiler, &[])? {
+            // LLVM is optional in the `wasmer` binary.
+            if compiler == Compiler::LLVM && stderr.contains("is not included in this binary") {
+                continue;
+            }
+            bail!("wasmer compile failed with: stderr: {}", stderr);
+        }
+        for &level in &[OptLevel::None, OptLevel::Speed, OptLevel::SpeedAndSize] {
+            let flag = match compiler.opt_flag(level) {
+                Some(flag) => flag,
+                None => continue,
+            };
+            if let Err(stderr) = compile_add(compiler, &[flag.clone()])? {
+                bail!("wasmer compile {} failed with: stderr: {}", flag, stderr);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn compile_with_enable_pic() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let object_path = temp_dir.path().join("add.o");
+    let output = Command::new(get_wasmer_path())
+        .args(object_compile_args(
+            &Path::new(ASSET_PATH).join("add.wat"),
+            Compiler::Cranelift,
+            RelocationModel::Pic,
+            &object_path,
+        ))
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "wasmer compile --enable-pic failed with: stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    assert!(object_path.exists());
+    Ok(())
+}
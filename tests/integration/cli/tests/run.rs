@@ -1,8 +1,14 @@
 //! Basic tests for the `run` subcommand
 
 use anyhow::bail;
+use std::path::Path;
 use std::process::Command;
-use wasmer_integration_tests_cli::{ASSET_PATH, C_ASSET_PATH, WASMER_PATH};
+use wasmer_integration_tests_cli::{
+    assert_same_output, assert_traps_with, run_and_match, run_code, run_code_with_output,
+    run_expect_exit, run_from_bytes, serialize_to_bytes, Backend, Compiler, Engine, FailureKind,
+    FixtureCache, ASSET_PATH, C_ASSET_PATH, WASMER_PATH,
+};
+use wasmer_vm::TrapCode;
 
 fn wasi_test_wasm_path() -> String {
     format!("{}/{}", C_ASSET_PATH, "qjs.wasm")
@@ -16,6 +22,18 @@ fn test_no_start_wat_path() -> String {
     format!("{}/{}", ASSET_PATH, "no_start.wat")
 }
 
+fn test_trap_wat_path() -> String {
+    format!("{}/{}", ASSET_PATH, "trap.wat")
+}
+
+fn test_invalid_wat_path() -> String {
+    format!("{}/{}", ASSET_PATH, "invalid.wat")
+}
+
+fn test_proc_exit_wat_path() -> String {
+    format!("{}/{}", ASSET_PATH, "proc_exit.wat")
+}
+
 #[test]
 fn run_wasi_works() -> anyhow::Result<()> {
     let output = Command::new(WASMER_PATH)
@@ -42,6 +60,65 @@ fn run_wasi_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn fixture_cache_reuses_artifacts() -> anyhow::Result<()> {
+    let mut cache = FixtureCache::new()?;
+    let wasm = wasi_test_wasm_path();
+    let backend = Backend {
+        compiler: Compiler::Cranelift,
+        engine: Engine::Universal,
+    };
+    let args = ["-e".to_string(), "print(3 * (4 + 5))".to_string()];
+    let first = cache.run_from_artifact(Path::new(&wasm), backend, &args)?;
+    assert_eq!(cache.hits(), 0);
+    let second = cache.run_from_artifact(Path::new(&wasm), backend, &args)?;
+    assert_eq!(cache.hits(), 1);
+    assert_eq!(first, "27\n");
+    assert_eq!(second, first);
+    Ok(())
+}
+
+#[test]
+fn run_proc_exit_code() -> anyhow::Result<()> {
+    let output = run_expect_exit(Path::new(&test_proc_exit_wat_path()), &[], 2)?;
+    assert!(output.stdout.is_empty());
+    assert!(run_expect_exit(Path::new(&test_proc_exit_wat_path()), &[], 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn run_serialized_bytes_matches_direct_run() -> anyhow::Result<()> {
+    let args = ["-e".to_string(), "print(3 * (4 + 5))".to_string()];
+    let direct = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(wasi_test_wasm_path())
+        .arg("--")
+        .args(&args)
+        .output()?;
+    assert!(direct.status.success());
+
+    let bytes = serialize_to_bytes(
+        Path::new(WASMER_PATH),
+        Path::new(&wasi_test_wasm_path()),
+        Engine::Universal,
+    )?;
+    let output = run_from_bytes(Path::new(WASMER_PATH), &bytes, Engine::Universal, &args)?;
+    assert_eq!(output, std::str::from_utf8(&direct.stdout)?);
+    Ok(())
+}
+
+#[test]
+fn run_wasi_output_matches() -> anyhow::Result<()> {
+    run_and_match(
+        Path::new(&wasi_test_wasm_path()),
+        &[
+            "-e".to_string(),
+            "print('version ' + [2, 2, 1].join('.'))".to_string(),
+        ],
+        r"^version \d+\.\d+\.\d+\n$",
+    )
+}
+
 #[test]
 
 fn run_no_imports_wasm_works() -> anyhow::Result<()> {
@@ -75,3 +152,66 @@ fn run_no_start_wasm_report_error() -> anyhow::Result<()> {
     assert_eq!(result.contains("Can not find any export functions."), true);
     Ok(())
 }
+
+#[test]
+fn run_trap_as_exit_code() -> anyhow::Result<()> {
+    let output = run_code_with_output(
+        Path::new(ASSET_PATH),
+        Path::new(WASMER_PATH),
+        &[
+            "run".to_string(),
+            test_trap_wat_path(),
+            "--trap-as-exit-code".to_string(),
+        ],
+    )?;
+
+    assert_eq!(
+        output.status.code(),
+        Some(TrapCode::IntegerDivisionByZero.exit_code())
+    );
+    Ok(())
+}
+
+#[test]
+fn run_trap_reports_trap_code() -> anyhow::Result<()> {
+    assert_traps_with(
+        Path::new(&test_trap_wat_path()),
+        &[],
+        TrapCode::IntegerDivisionByZero,
+    )
+}
+
+#[test]
+fn run_failure_kind() -> anyhow::Result<()> {
+    let failure_kind = |wat_path: String| {
+        run_code(
+            Path::new(ASSET_PATH),
+            Path::new(WASMER_PATH),
+            &["run".to_string(), wat_path],
+        )
+        .expect_err("running the module succeeded")
+        .downcast_ref::<FailureKind>()
+        .copied()
+    };
+
+    assert_eq!(
+        failure_kind(test_invalid_wat_path()),
+        Some(FailureKind::Compile)
+    );
+    assert_eq!(failure_kind(test_trap_wat_path()), Some(FailureKind::Trap));
+    Ok(())
+}
+
+#[test]
+fn run_same_output_across_backends() -> anyhow::Result<()> {
+    let universal = |compiler| Backend {
+        compiler,
+        engine: Engine::Universal,
+    };
+    assert_same_output(
+        Path::new(&wasi_test_wasm_path()),
+        universal(Compiler::Cranelift),
+        universal(Compiler::Singlepass),
+        &["-e".to_string(), "print(3 * (4 + 5))".to_string()],
+    )
+}
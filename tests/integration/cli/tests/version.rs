@@ -1,6 +1,8 @@
 use anyhow::bail;
 use std::process::Command;
-use wasmer_integration_tests_cli::WASMER_PATH;
+use wasmer_integration_tests_cli::{
+    capability_report, get_wasmer_path, Compiler, Engine, WASMER_PATH,
+};
 
 const WASMER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -60,3 +62,12 @@ fn help_text_contains_version() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn capability_report_lists_cranelift_and_universal() -> anyhow::Result<()> {
+    let report = capability_report(&get_wasmer_path())?;
+    assert_eq!(report.version, format!("wasmer {}", WASMER_VERSION));
+    assert!(report.compilers.contains(&Compiler::Cranelift));
+    assert!(report.engines.contains(&Engine::Universal));
+    Ok(())
+}